@@ -1,7 +1,15 @@
 use criterion::{criterion_group, criterion_main, Criterion};
 use cs257_project::{
     example_graphs::{
-        buy_sell_stock::BuySellStockGraph, linear::Linear, MakeGraph, WorkflowGraphExt,
+        buy_sell_stock::BuySellStockGraph,
+        etl_pipeline::EtlPipeline,
+        linear::Linear,
+        loan_approval::LoanApproval,
+        order_fulfilment::OrderFulfilment,
+        payment_saga::PaymentSaga,
+        random::{Diamonds, FanOut, Layered, RandomDag, RandomParams},
+        user_signup::UserSignup,
+        MakeGraph, WorkflowGraphExt,
     },
     verifier::GraphVerifier,
 };
@@ -37,11 +45,29 @@ fn benchmark_for_graph<M: MakeGraph>(mk: &M, c: &mut Criterion) {
 
 fn benchmark(c: &mut Criterion) {
     benchmark_for_graph(&BuySellStockGraph::new(false), c);
+    benchmark_for_graph(&OrderFulfilment, c);
+    benchmark_for_graph(&LoanApproval, c);
+    benchmark_for_graph(&EtlPipeline, c);
+    benchmark_for_graph(&UserSignup, c);
+    benchmark_for_graph(&PaymentSaga, c);
     benchmark_for_graph(&Linear(20), c);
     benchmark_for_graph(&Linear(40), c);
     benchmark_for_graph(&Linear(60), c);
     benchmark_for_graph(&Linear(80), c);
     benchmark_for_graph(&Linear(100), c);
+    for num_nodes in [20, 40, 60] {
+        let params = RandomParams::new(0x12345678, num_nodes);
+        benchmark_for_graph(&RandomDag(params.clone()), c);
+        benchmark_for_graph(&Diamonds(params.clone()), c);
+        benchmark_for_graph(&FanOut(params.clone()), c);
+        benchmark_for_graph(
+            &Layered {
+                params,
+                num_layers: 4,
+            },
+            c,
+        );
+    }
 }
 
 criterion_group!(benches, benchmark);
@@ -0,0 +1,33 @@
+//! Manage the verification result cache.
+//!
+//! Usage: `verify_cache invalidate [DIR]`. The directory defaults to `VERIFY_CACHE_DIR`, or else
+//! `.verify-cache`.
+
+use std::process::ExitCode;
+
+use cs257_project::verifier::cache::{ResultCache, DEFAULT_CACHE_DIR};
+
+fn main() -> ExitCode {
+    let args = std::env::args().skip(1).collect::<Vec<_>>();
+    let dir = args
+        .get(1)
+        .cloned()
+        .or_else(|| std::env::var("VERIFY_CACHE_DIR").ok())
+        .unwrap_or_else(|| DEFAULT_CACHE_DIR.to_string());
+    match args.first().map(String::as_str) {
+        Some("invalidate") if args.len() <= 2 => match ResultCache::new(&dir).invalidate() {
+            Ok(removed) => {
+                println!("removed cached results of {removed} graphs from {dir}");
+                ExitCode::SUCCESS
+            }
+            Err(e) => {
+                eprintln!("cannot invalidate {dir}: {e}");
+                ExitCode::FAILURE
+            }
+        },
+        _ => {
+            eprintln!("usage: verify_cache invalidate [DIR]");
+            ExitCode::from(2)
+        }
+    }
+}
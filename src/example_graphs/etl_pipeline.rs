@@ -0,0 +1,100 @@
+use crate::workflow::{
+    failure::ErrorMatch,
+    schema::{InputCond, KeyRule, OutputSchema},
+    WorkflowGraph,
+};
+
+use super::{MakeGraph, WorkflowGraphExt};
+
+/// A batch ETL job: extract rows, validate, transform and load them, then update the catalog and refresh the
+/// dashboards in parallel before reporting. Rows that fail validation are quarantined, and an unavailable source
+/// is deliberately left unhandled. The node to test is `report`.
+pub struct EtlPipeline;
+
+impl MakeGraph for EtlPipeline {
+    fn name(&self) -> String {
+        "etl_pipeline".to_string()
+    }
+
+    fn make_graph(&self) -> WorkflowGraphExt {
+        let mut g = WorkflowGraph::new();
+        let extract = g.add_node(
+            "extract",
+            vec!["source_uri".to_string()],
+            OutputSchema::new()
+                .add_fixed("raw_rows")
+                .carry_all()
+                .build(),
+        );
+        let validate = g.add_node(
+            "validate",
+            vec!["raw_rows".to_string()],
+            OutputSchema::new()
+                .add_fixed("valid_rows")
+                .carry_all()
+                .build(),
+        );
+        // the previous inputs are kept under `input.`, for lineage
+        let transform = g.add_node(
+            "transform",
+            vec!["valid_rows".to_string()],
+            OutputSchema::new()
+                .add_fixed("clean_rows")
+                .add_rule_for_every_input(
+                    KeyRule::IdWithPrefix("input.".to_string()),
+                    InputCond::Always,
+                )
+                .build(),
+        );
+        let load = g.add_node(
+            "load",
+            vec!["clean_rows".to_string(), "input.target_table".to_string()],
+            OutputSchema::new().add_fixed("load_id").build(),
+        );
+        let quarantine = g.add_node(
+            "quarantine",
+            vec!["rejected_rows".to_string(), "source_uri".to_string()],
+            OutputSchema::new().add_fixed("quarantine_path").build(),
+        );
+        let publish = g.add_fork("publish", OutputSchema::new().carry_all().build());
+        let update_catalog = g.add_node(
+            "update_catalog",
+            vec!["load_id".to_string()],
+            OutputSchema::new().add_fixed("catalog_version").build(),
+        );
+        let refresh_dashboards = g.add_node(
+            "refresh_dashboards",
+            vec!["load_id".to_string()],
+            OutputSchema::new().add_fixed("dashboard_ids").build(),
+        );
+        let report = g.add_join(
+            "report",
+            vec!["catalog_version".to_string(), "dashboard_ids".to_string()],
+            OutputSchema::new().carry_all().build(),
+        );
+        g.add_edge(extract, validate, vec![])
+            .add_failure(
+                extract,
+                "SourceUnavailable",
+                OutputSchema::new().carry_all().build(),
+            )
+            .add_edge(validate, transform, vec![])
+            .add_failure(
+                validate,
+                "SchemaMismatch",
+                OutputSchema::new()
+                    .add_fixed("rejected_rows")
+                    .carry_all()
+                    .build(),
+            )
+            .add_catch(validate, ErrorMatch::All, quarantine)
+            .add_edge(transform, load, vec![])
+            .add_edge(load, publish, vec![])
+            .add_edge(publish, update_catalog, vec![])
+            .add_edge(publish, refresh_dashboards, vec![])
+            .add_edge(update_catalog, report, vec![])
+            .add_edge(refresh_dashboards, report, vec![])
+            .set_start(extract);
+        WorkflowGraphExt::new(g, report)
+    }
+}
@@ -0,0 +1,99 @@
+use crate::workflow::{
+    failure::ErrorMatch,
+    schema::{InputCond, OutputSchema},
+    WorkflowGraph,
+};
+
+use super::{MakeGraph, WorkflowGraphExt};
+
+/// A loan application: a credit check feeds an automatic decision, which approves, rejects or asks for a manual
+/// review. When the credit bureau is unavailable, the application goes to manual review directly.
+/// The node to test is `approve_loan`.
+pub struct LoanApproval;
+
+impl MakeGraph for LoanApproval {
+    fn name(&self) -> String {
+        "loan_approval".to_string()
+    }
+
+    fn make_graph(&self) -> WorkflowGraphExt {
+        let decision_is = |decision: &str| {
+            vec![InputCond::MatchesKeyValue(
+                "decision".to_string(),
+                decision.to_string(),
+            )]
+        };
+        let mut g = WorkflowGraph::new();
+        let receive_application = g.add_node(
+            "receive_application",
+            vec!["applicant_id".to_string(), "amount".to_string()],
+            OutputSchema::new().carry_all().build(),
+        );
+        let credit_check = g.add_node(
+            "credit_check",
+            vec!["applicant_id".to_string()],
+            OutputSchema::new()
+                .add_fixed("credit_score")
+                .carry_all()
+                .build(),
+        );
+        let auto_decision = g.add_node(
+            "auto_decision",
+            vec!["credit_score".to_string(), "amount".to_string()],
+            OutputSchema::new()
+                .add_fixed("decision")
+                .carry_all()
+                .build(),
+        );
+        let manual_review = g.add_node(
+            "manual_review",
+            vec!["applicant_id".to_string(), "amount".to_string()],
+            OutputSchema::new()
+                .add_fixed("decision")
+                .add_fixed("reviewer")
+                .carry_all()
+                .build(),
+        );
+        let approve_loan = g.add_node(
+            "approve_loan",
+            vec!["amount".to_string(), "decision".to_string()],
+            OutputSchema::new().add_fixed("loan_id").carry_all().build(),
+        );
+        let reject_loan = g.add_node(
+            "reject_loan",
+            vec!["decision".to_string()],
+            OutputSchema::new().carry_all().build(),
+        );
+        let notify_applicant = g.add_node(
+            "notify_applicant",
+            vec!["applicant_id".to_string(), "decision".to_string()],
+            OutputSchema::new().build(),
+        );
+        g.add_edge(receive_application, credit_check, vec![])
+            .add_edge(credit_check, auto_decision, vec![])
+            .add_failure(
+                credit_check,
+                "BureauUnavailable",
+                OutputSchema::new().carry_all().build(),
+            )
+            .add_retry(
+                credit_check,
+                ErrorMatch::Named(vec!["BureauUnavailable".to_string()]),
+                3,
+            )
+            .add_catch(
+                credit_check,
+                ErrorMatch::Named(vec!["BureauUnavailable".to_string()]),
+                manual_review,
+            )
+            .add_edge(auto_decision, approve_loan, decision_is("approve"))
+            .add_edge(auto_decision, reject_loan, decision_is("reject"))
+            .add_edge(auto_decision, manual_review, decision_is("review"))
+            .add_edge(manual_review, approve_loan, decision_is("approve"))
+            .add_edge(manual_review, reject_loan, decision_is("reject"))
+            .add_edge(approve_loan, notify_applicant, vec![])
+            .add_edge(reject_loan, notify_applicant, vec![])
+            .set_start(receive_application);
+        WorkflowGraphExt::new(g, approve_loan)
+    }
+}
@@ -1,5 +1,11 @@
 pub mod buy_sell_stock;
+pub mod etl_pipeline;
 pub mod linear;
+pub mod loan_approval;
+pub mod order_fulfilment;
+pub mod payment_saga;
+pub mod random;
+pub mod user_signup;
 use crate::workflow::{NodeIdx, WorkflowGraph};
 
 pub struct WorkflowGraphExt {
@@ -0,0 +1,118 @@
+use crate::workflow::{
+    failure::ErrorMatch,
+    schema::{InputCond, OutputSchema},
+    WorkflowGraph,
+};
+
+use super::{MakeGraph, WorkflowGraphExt};
+
+/// An online order: check stock, reserve it, charge the customer and ship.
+/// Orders out of stock are backordered, and a declined payment releases the reserved stock.
+/// Every path ends by notifying the customer, and the node to test is `ship_order`.
+pub struct OrderFulfilment;
+
+impl MakeGraph for OrderFulfilment {
+    fn name(&self) -> String {
+        "order_fulfilment".to_string()
+    }
+
+    fn make_graph(&self) -> WorkflowGraphExt {
+        let mut g = WorkflowGraph::new();
+        let receive_order = g.add_node(
+            "receive_order",
+            vec!["items".to_string()],
+            OutputSchema::new()
+                .add_fixed("order_id")
+                .carry_all()
+                .build(),
+        );
+        let check_inventory = g.add_node(
+            "check_inventory",
+            vec!["items".to_string()],
+            OutputSchema::new()
+                .add_fixed("stock_status")
+                .carry_all()
+                .build(),
+        );
+        let reserve_inventory = g.add_node(
+            "reserve_inventory",
+            vec!["order_id".to_string(), "items".to_string()],
+            OutputSchema::new()
+                .add_fixed("reservation_id")
+                .carry_all()
+                .build(),
+        );
+        let charge_payment = g.add_node(
+            "charge_payment",
+            vec!["order_id".to_string(), "payment_method".to_string()],
+            OutputSchema::new()
+                .add_fixed("payment_id")
+                .carry_all()
+                .build(),
+        );
+        let release_inventory = g.add_node(
+            "release_inventory",
+            vec!["reservation_id".to_string()],
+            OutputSchema::new().carry_all().build(),
+        );
+        let ship_order = g.add_node(
+            "ship_order",
+            vec![
+                "reservation_id".to_string(),
+                "payment_id".to_string(),
+                "address".to_string(),
+            ],
+            OutputSchema::new()
+                .add_fixed("tracking_number")
+                .carry_all()
+                .build(),
+        );
+        let notify_backorder = g.add_node(
+            "notify_backorder",
+            vec!["order_id".to_string()],
+            OutputSchema::new().carry_all().build(),
+        );
+        let notify_customer = g.add_node(
+            "notify_customer",
+            vec!["order_id".to_string()],
+            OutputSchema::new().build(),
+        );
+        g.add_edge(receive_order, check_inventory, vec![])
+            .add_edge(
+                check_inventory,
+                reserve_inventory,
+                vec![InputCond::MatchesKeyValue(
+                    "stock_status".to_string(),
+                    "available".to_string(),
+                )],
+            )
+            .add_edge(
+                check_inventory,
+                notify_backorder,
+                vec![InputCond::MatchesKeyValue(
+                    "stock_status".to_string(),
+                    "unavailable".to_string(),
+                )],
+            )
+            .add_edge(reserve_inventory, charge_payment, vec![])
+            .add_edge(charge_payment, ship_order, vec![])
+            .add_failure(
+                charge_payment,
+                "PaymentDeclined",
+                OutputSchema::new()
+                    .add_fixed("decline_reason")
+                    .carry_all()
+                    .build(),
+            )
+            .add_catch(
+                charge_payment,
+                ErrorMatch::Named(vec!["PaymentDeclined".to_string()]),
+                release_inventory,
+            )
+            .add_edge(release_inventory, notify_customer, vec![])
+            .add_edge(ship_order, notify_customer, vec![])
+            .add_edge(notify_backorder, notify_customer, vec![])
+            .set_start(receive_order);
+        WorkflowGraphExt::new(g, ship_order)
+    }
+}
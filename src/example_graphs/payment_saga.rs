@@ -0,0 +1,83 @@
+use crate::workflow::{failure::ErrorMatch, schema::OutputSchema, WorkflowGraph};
+
+use super::{MakeGraph, WorkflowGraphExt};
+
+/// A saga over three services: reserve stock, charge the card and create a shipment. Each failure runs the
+/// declared compensations of the steps before it in reverse order, through catch edges, and then cancels the order.
+/// The node to test is `confirm_order`.
+pub struct PaymentSaga;
+
+impl MakeGraph for PaymentSaga {
+    fn name(&self) -> String {
+        "payment_saga".to_string()
+    }
+
+    fn make_graph(&self) -> WorkflowGraphExt {
+        let mut g = WorkflowGraph::new();
+        let reserve_inventory = g.add_node(
+            "reserve_inventory",
+            vec!["order_id".to_string(), "items".to_string()],
+            OutputSchema::new()
+                .add_fixed("reservation_id")
+                .carry_all()
+                .build(),
+        );
+        let charge_card = g.add_node(
+            "charge_card",
+            vec!["order_id".to_string(), "card".to_string()],
+            OutputSchema::new()
+                .add_fixed("payment_id")
+                .carry_all()
+                .build(),
+        );
+        let create_shipment = g.add_node(
+            "create_shipment",
+            vec!["reservation_id".to_string(), "address".to_string()],
+            OutputSchema::new()
+                .add_fixed("shipment_id")
+                .carry_all()
+                .build(),
+        );
+        let confirm_order = g.add_node(
+            "confirm_order",
+            vec!["shipment_id".to_string(), "payment_id".to_string()],
+            OutputSchema::new().build(),
+        );
+        let refund_payment = g.add_node(
+            "refund_payment",
+            vec!["payment_id".to_string()],
+            OutputSchema::new().carry_all().build(),
+        );
+        let release_inventory = g.add_node(
+            "release_inventory",
+            vec!["reservation_id".to_string()],
+            OutputSchema::new().carry_all().build(),
+        );
+        let cancel_order = g.add_node(
+            "cancel_order",
+            vec!["order_id".to_string()],
+            OutputSchema::new().build(),
+        );
+        g.add_edge(reserve_inventory, charge_card, vec![])
+            .add_edge(charge_card, create_shipment, vec![])
+            .add_failure(
+                charge_card,
+                "CardDeclined",
+                OutputSchema::new().carry_all().build(),
+            )
+            .add_catch(charge_card, ErrorMatch::All, release_inventory)
+            .add_edge(create_shipment, confirm_order, vec![])
+            .add_failure(
+                create_shipment,
+                "CarrierUnavailable",
+                OutputSchema::new().carry_all().build(),
+            )
+            .add_catch(create_shipment, ErrorMatch::All, refund_payment)
+            .add_edge(refund_payment, release_inventory, vec![])
+            .add_edge(release_inventory, cancel_order, vec![])
+            .set_compensation(reserve_inventory, release_inventory)
+            .set_compensation(charge_card, refund_payment)
+            .set_start(reserve_inventory);
+        WorkflowGraphExt::new(g, confirm_order)
+    }
+}
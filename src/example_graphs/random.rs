@@ -0,0 +1,330 @@
+//! Seeded random graphs of various shapes, for fuzzing and benchmarks.
+//!
+//! Every node is reachable from the start node, and the last node is the one to test reachability for.
+//! Only `Cyclic` graphs have cycles, which the verifier rejects.
+
+use rand::{Rng, SeedableRng};
+use rand_xorshift::XorShiftRng;
+
+use crate::workflow::{
+    schema::{InputCond, KeyRule, OutputSchema},
+    NodeIdx, WorkflowGraph,
+};
+
+use super::{MakeGraph, WorkflowGraphExt};
+
+/// The prefix of `KeyRule::IdWithPrefix` rules. Required inputs sometimes use it too.
+const PREFIX: &str = "prev.";
+
+/// Relative weights of the kinds of rules in output schemas.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RuleMix {
+    pub identity: u32,
+    pub fixed: u32,
+    pub prefix: u32,
+}
+
+impl Default for RuleMix {
+    fn default() -> Self {
+        Self {
+            identity: 2,
+            fixed: 2,
+            prefix: 1,
+        }
+    }
+}
+
+/// Parameters shared by all shapes.
+#[derive(Debug, Clone)]
+pub struct RandomParams {
+    pub seed: u64,
+    pub num_nodes: usize,
+    /// Probability of each extra edge that the shape allows, on top of the edges that make up the shape.
+    pub edge_density: f64,
+    /// Number of distinct keys, `key_0` to `key_{n-1}`.
+    pub num_keys: usize,
+    pub rule_mix: RuleMix,
+    /// Probability that an edge needs a key in the outputs of its source.
+    pub conditional_edges: f64,
+}
+
+impl RandomParams {
+    pub fn new(seed: u64, num_nodes: usize) -> Self {
+        Self {
+            seed,
+            num_nodes,
+            edge_density: 0.1,
+            num_keys: 10,
+            rule_mix: RuleMix::default(),
+            conditional_edges: 0.2,
+        }
+    }
+
+    pub fn edge_density(mut self, edge_density: f64) -> Self {
+        self.edge_density = edge_density;
+        self
+    }
+
+    pub fn num_keys(mut self, num_keys: usize) -> Self {
+        self.num_keys = num_keys;
+        self
+    }
+
+    pub fn rule_mix(mut self, rule_mix: RuleMix) -> Self {
+        self.rule_mix = rule_mix;
+        self
+    }
+
+    pub fn conditional_edges(mut self, conditional_edges: f64) -> Self {
+        self.conditional_edges = conditional_edges;
+        self
+    }
+}
+
+struct RandomGraphBuilder<'a> {
+    params: &'a RandomParams,
+    rng: XorShiftRng,
+    graph: WorkflowGraph,
+}
+
+impl<'a> RandomGraphBuilder<'a> {
+    fn new(params: &'a RandomParams) -> Self {
+        Self {
+            params,
+            rng: XorShiftRng::seed_from_u64(params.seed),
+            graph: WorkflowGraph::new(),
+        }
+    }
+
+    fn random_key(&mut self) -> String {
+        format!("key_{}", self.rng.gen_range(0..self.params.num_keys.max(1)))
+    }
+
+    fn random_schema(&mut self) -> OutputSchema {
+        let RuleMix {
+            identity,
+            fixed,
+            prefix,
+        } = self.params.rule_mix;
+        let mut schema = OutputSchema::new();
+        for _ in 0..self.rng.gen_range(1..=3) {
+            let pick = self.rng.gen_range(0..(identity + fixed + prefix).max(1));
+            schema = if pick < identity {
+                match self.rng.gen_bool(0.5) {
+                    true => schema.carry_all(),
+                    false => {
+                        let key = self.random_key();
+                        schema
+                            .add_rule_for_every_input(KeyRule::Identity, InputCond::MatchesKey(key))
+                    }
+                }
+            } else if pick < identity + fixed {
+                schema.add_fixed(self.random_key())
+            } else {
+                schema.add_rule_for_every_input(
+                    KeyRule::IdWithPrefix(PREFIX.to_string()),
+                    InputCond::Always,
+                )
+            };
+        }
+        schema.build()
+    }
+
+    /// Add a task with up to two random required inputs. The start node needs none.
+    fn add_node(&mut self, name: &str) -> NodeIdx {
+        let required_inputs = match self.graph.nodes.is_empty() {
+            true => vec![],
+            false => (0..self.rng.gen_range(0..=2))
+                .map(|_| match self.rng.gen_ratio(1, 4) {
+                    true => format!("{PREFIX}{}", self.random_key()),
+                    false => self.random_key(),
+                })
+                .collect::<Vec<_>>(),
+        };
+        let schema = self.random_schema();
+        self.graph.add_node(name, required_inputs, schema)
+    }
+
+    fn add_edge(&mut self, src: NodeIdx, dst: NodeIdx) {
+        if self.graph.adj_list[src]
+            .iter()
+            .any(|(child, _)| *child == dst)
+        {
+            return;
+        }
+        let conds = match self.rng.gen_bool(self.params.conditional_edges) {
+            true => vec![InputCond::MatchesKey(self.random_key())],
+            false => vec![],
+        };
+        self.graph.add_edge(src, dst, conds);
+    }
+
+    /// Add each edge from an earlier node to a later one in `order` with probability `edge_density`.
+    fn add_forward_edges(&mut self, order: &[NodeIdx]) {
+        for (i, src) in order.iter().enumerate() {
+            for dst in &order[i + 1..] {
+                if self.rng.gen_bool(self.params.edge_density) {
+                    self.add_edge(*src, *dst);
+                }
+            }
+        }
+    }
+
+    fn finish(mut self, start: NodeIdx, test_reachable_node: NodeIdx) -> WorkflowGraphExt {
+        self.graph.set_start(start);
+        WorkflowGraphExt::new(self.graph, test_reachable_node)
+    }
+}
+
+/// A random DAG: every node has an edge from a random earlier node.
+pub struct RandomDag(pub RandomParams);
+
+impl MakeGraph for RandomDag {
+    fn name(&self) -> String {
+        format!("random_dag_{}_{}", self.0.num_nodes, self.0.seed)
+    }
+
+    fn make_graph(&self) -> WorkflowGraphExt {
+        let mut b = RandomGraphBuilder::new(&self.0);
+        let nodes = (0..self.0.num_nodes.max(1))
+            .map(|i| b.add_node(&format!("node_{i}")))
+            .collect::<Vec<_>>();
+        for i in 1..nodes.len() {
+            let src = nodes[b.rng.gen_range(0..i)];
+            b.add_edge(src, nodes[i]);
+        }
+        b.add_forward_edges(&nodes);
+        b.finish(nodes[0], *nodes.last().unwrap())
+    }
+}
+
+/// A chain of diamonds: each top node branches into two nodes that merge into the top node of the next diamond.
+pub struct Diamonds(pub RandomParams);
+
+impl MakeGraph for Diamonds {
+    fn name(&self) -> String {
+        format!("diamonds_{}_{}", self.0.num_nodes, self.0.seed)
+    }
+
+    fn make_graph(&self) -> WorkflowGraphExt {
+        let mut b = RandomGraphBuilder::new(&self.0);
+        let mut top = b.add_node("top_0");
+        let mut order = vec![top];
+        for i in 0..self.0.num_nodes.saturating_sub(1) / 3 {
+            let left = b.add_node(&format!("left_{i}"));
+            let right = b.add_node(&format!("right_{i}"));
+            let bottom = b.add_node(&format!("top_{}", i + 1));
+            b.add_edge(top, left);
+            b.add_edge(top, right);
+            b.add_edge(left, bottom);
+            b.add_edge(right, bottom);
+            order.extend([left, right, bottom]);
+            top = bottom;
+        }
+        b.add_forward_edges(&order);
+        b.finish(order[0], top)
+    }
+}
+
+/// A fork into every other node but the last, and a join of all of them into the last node.
+pub struct FanOut(pub RandomParams);
+
+impl MakeGraph for FanOut {
+    fn name(&self) -> String {
+        format!("fan_out_{}_{}", self.0.num_nodes, self.0.seed)
+    }
+
+    fn make_graph(&self) -> WorkflowGraphExt {
+        let mut b = RandomGraphBuilder::new(&self.0);
+        let schema = b.random_schema();
+        let fork = b.graph.add_fork("fork", schema);
+        let workers = (0..self.0.num_nodes.saturating_sub(2))
+            .map(|i| b.add_node(&format!("worker_{i}")))
+            .collect::<Vec<_>>();
+        let schema = b.random_schema();
+        let join = b.graph.add_join("join", vec![], schema);
+        workers.iter().for_each(|worker| {
+            b.add_edge(fork, *worker);
+            b.add_edge(*worker, join);
+        });
+        if workers.is_empty() {
+            b.add_edge(fork, join);
+        }
+        b.finish(fork, join)
+    }
+}
+
+/// Nodes in `num_layers` layers, where every node has an edge from a random node of the previous layer,
+/// and extra edges only go from a layer to the next one.
+pub struct Layered {
+    pub params: RandomParams,
+    pub num_layers: usize,
+}
+
+impl MakeGraph for Layered {
+    fn name(&self) -> String {
+        format!(
+            "layered_{}_{}_{}",
+            self.params.num_nodes, self.num_layers, self.params.seed
+        )
+    }
+
+    fn make_graph(&self) -> WorkflowGraphExt {
+        let mut b = RandomGraphBuilder::new(&self.params);
+        let start = b.add_node("layer_0_0");
+        let num_layers = self.num_layers.max(1);
+        let per_layer = (self.params.num_nodes.saturating_sub(1) / num_layers).max(1);
+        let mut previous = vec![start];
+        for layer in 1..=num_layers {
+            let current = (0..per_layer)
+                .map(|i| b.add_node(&format!("layer_{layer}_{i}")))
+                .collect::<Vec<_>>();
+            for dst in &current {
+                let src = previous[b.rng.gen_range(0..previous.len())];
+                b.add_edge(src, *dst);
+                for src in &previous {
+                    if b.rng.gen_bool(self.params.edge_density) {
+                        b.add_edge(*src, *dst);
+                    }
+                }
+            }
+            previous = current;
+        }
+        let end = *previous.last().unwrap();
+        b.finish(start, end)
+    }
+}
+
+/// A random DAG, plus edges back to earlier nodes with probability `edge_density`, and at least one.
+pub struct Cyclic(pub RandomParams);
+
+impl MakeGraph for Cyclic {
+    fn name(&self) -> String {
+        format!("cyclic_{}_{}", self.0.num_nodes, self.0.seed)
+    }
+
+    fn make_graph(&self) -> WorkflowGraphExt {
+        let WorkflowGraphExt {
+            graph,
+            test_reachable_node,
+        } = RandomDag(self.0.clone()).make_graph();
+        let mut b = RandomGraphBuilder::new(&self.0);
+        b.graph = graph;
+        let n = b.graph.nodes.len();
+        for dst in 0..n {
+            for src in dst + 1..n {
+                if b.rng.gen_bool(self.0.edge_density) {
+                    b.add_edge(src, dst);
+                }
+            }
+        }
+        if n > 1 {
+            // node i is reachable from node 0, and node 0 from node i
+            let src = b.rng.gen_range(1..n);
+            b.add_edge(src, 0);
+        } else {
+            b.add_edge(0, 0);
+        }
+        WorkflowGraphExt::new(b.graph, test_reachable_node)
+    }
+}
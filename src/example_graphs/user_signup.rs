@@ -0,0 +1,106 @@
+use crate::workflow::{
+    failure::ErrorMatch,
+    schema::{InputCond, KeyRule, OutputSchema},
+    WorkflowGraph,
+};
+
+use super::{MakeGraph, WorkflowGraphExt};
+
+/// A user signup: check that the email is not taken, create the account and send a verification email, then
+/// activate the account once it is verified. The password is not carried past `create_account`. A referral code,
+/// if given, earns a bonus, and an expired verification deletes the account. The node to test is `welcome_user`.
+pub struct UserSignup;
+
+impl MakeGraph for UserSignup {
+    fn name(&self) -> String {
+        "user_signup".to_string()
+    }
+
+    fn make_graph(&self) -> WorkflowGraphExt {
+        let mut g = WorkflowGraph::new();
+        let submit_signup = g.add_node(
+            "submit_signup",
+            vec!["email".to_string(), "password".to_string()],
+            OutputSchema::new().add_fixed("user_id").carry_all().build(),
+        );
+        let check_email = g.add_node(
+            "check_email",
+            vec!["email".to_string()],
+            OutputSchema::new().carry_all().build(),
+        );
+        let carry = |key: &str| InputCond::MatchesKey(key.to_string());
+        let create_account = g.add_node(
+            "create_account",
+            vec!["user_id".to_string(), "password".to_string()],
+            OutputSchema::new()
+                .add_fixed("account_id")
+                .add_fixed("verification_token")
+                .add_rule_for_every_input(KeyRule::Identity, carry("email"))
+                .add_rule_for_every_input(KeyRule::Identity, carry("user_id"))
+                .add_rule_for_every_input(KeyRule::Identity, carry("referral_code"))
+                .build(),
+        );
+        let send_verification = g.add_node(
+            "send_verification",
+            vec!["email".to_string(), "verification_token".to_string()],
+            OutputSchema::new().carry_all().build(),
+        );
+        let await_verification = g.add_node(
+            "await_verification",
+            vec!["verification_token".to_string()],
+            OutputSchema::new()
+                .add_fixed("verified_at")
+                .carry_all()
+                .build(),
+        );
+        let activate_account = g.add_node(
+            "activate_account",
+            vec!["account_id".to_string(), "verified_at".to_string()],
+            OutputSchema::new().carry_all().build(),
+        );
+        let grant_referral_bonus = g.add_node(
+            "grant_referral_bonus",
+            vec!["account_id".to_string(), "referral_code".to_string()],
+            OutputSchema::new()
+                .add_fixed("bonus_id")
+                .carry_all()
+                .build(),
+        );
+        let welcome_user = g.add_node(
+            "welcome_user",
+            vec!["email".to_string(), "account_id".to_string()],
+            OutputSchema::new().build(),
+        );
+        let show_error = g.add_node(
+            "show_error",
+            vec!["email".to_string(), "error".to_string()],
+            OutputSchema::new().build(),
+        );
+        let delete_account = g.add_node(
+            "delete_account",
+            vec!["account_id".to_string()],
+            OutputSchema::new().build(),
+        );
+        let with_error = OutputSchema::new().add_fixed("error").carry_all().build();
+        g.add_edge(submit_signup, check_email, vec![])
+            .add_failure(check_email, "EmailTaken", with_error.clone())
+            .add_catch(check_email, ErrorMatch::All, show_error)
+            .add_edge(check_email, create_account, vec![])
+            .add_edge(create_account, send_verification, vec![])
+            .add_failure(send_verification, "EmailBounced", with_error.clone())
+            .add_catch(send_verification, ErrorMatch::All, show_error)
+            .add_edge(send_verification, await_verification, vec![])
+            .add_failure(await_verification, "VerificationExpired", with_error)
+            .add_catch(await_verification, ErrorMatch::All, delete_account)
+            .add_edge(await_verification, activate_account, vec![])
+            .add_edge(
+                activate_account,
+                grant_referral_bonus,
+                vec![InputCond::MatchesKey("referral_code".to_string())],
+            )
+            .add_edge(activate_account, welcome_user, vec![])
+            .add_edge(grant_referral_bonus, welcome_user, vec![])
+            .set_start(submit_signup);
+        WorkflowGraphExt::new(g, welcome_user)
+    }
+}
@@ -4,10 +4,14 @@ use z3::ast::Ast;
 use z3::Model;
 use z3::{ast::Bool, Context};
 
-use crate::workflow::{Node, WorkflowGraph};
+use crate::workflow::contract::Contract;
+use crate::workflow::{Node, NodeKind, WorkflowGraph};
 
 use crate::verifier::symbol::symbol;
-use crate::workflow::schema::{InputCond, KeyRule};
+use crate::verifier::value::ValueAST;
+use crate::workflow::path;
+use crate::workflow::schema::{InputCond, KeyRule, OutputSchema};
+use crate::workflow::value::{CmpOp, ValueSpec};
 
 pub struct NodeAST<'ctx, 'g> {
     pub ctx: &'ctx Context,
@@ -16,6 +20,236 @@ pub struct NodeAST<'ctx, 'g> {
     pub output_keys: HashMap<&'g str, Bool<'ctx>>, // output_keys[s] = true iff s is an output key
     pub transition_constraints: Vec<Bool<'ctx>>, // transition_constraints[i] corresponds to adj[nodeIdx][i]
     pub schema_constraints: Vec<Bool<'ctx>>,     // schema_constraints should ALL be satisfied
+    pub failures: Vec<Bool<'ctx>>, // failures[i] = true iff the node fails with node.failures[i]
+    pub failure_output_keys: Vec<HashMap<&'g str, Bool<'ctx>>>, // failure_output_keys[i][s] = true iff s is an output key when failing with node.failures[i]
+    pub catch_constraints: Vec<Bool<'ctx>>, // catch_constraints[i] corresponds to catch_list[nodeIdx][i]
+    pub exported_keys: HashMap<&'g str, Bool<'ctx>>, // exported_keys[s] = true iff s is output by the map or the module run by the node
+    pub input_values: HashMap<&'g str, ValueAST<'ctx>>, // input_values[s] is the value of the typed key s, if it is an input key
+    pub output_values: HashMap<&'g str, ValueAST<'ctx>>, // output_values[s] is the value of the typed key s, if it is an output key
+    pub failure_output_values: Vec<HashMap<&'g str, ValueAST<'ctx>>>, // failure_output_values[i][s] is the value of s when failing with node.failures[i]
+}
+
+/// The boolean of key `s` in `keys`, which is created if missing.
+fn key<'ctx, 'g>(
+    ctx: &'ctx Context,
+    keys: &mut HashMap<&'g str, Bool<'ctx>>,
+    s: &'g str,
+) -> Bool<'ctx> {
+    keys.entry(s)
+        .or_insert_with(|| Bool::new_const(ctx, symbol!()))
+        .clone()
+}
+
+/// Whether `cond` holds on `keys` and their `values`, or `None` if it always holds.
+/// A condition on the value of a key without a declared type only needs the key.
+fn cond_holds<'ctx, 'g>(
+    ctx: &'ctx Context,
+    cond: &'g InputCond,
+    keys: &mut HashMap<&'g str, Bool<'ctx>>,
+    values: &HashMap<&'g str, ValueAST<'ctx>>,
+) -> Option<Bool<'ctx>> {
+    let (s, value_holds) = match cond {
+        InputCond::Always => return None,
+        InputCond::MatchesKey(s) => (s, None),
+        InputCond::MatchesKeyValue(s, value) => {
+            (s, values.get(s.as_str()).map(|v| v.matches(value)))
+        }
+        InputCond::Compare(s, op, value) => {
+            (s, values.get(s.as_str()).map(|v| v.compare(*op, value)))
+        }
+    };
+    let has_key = key(ctx, keys, s);
+    Some(match value_holds {
+        Some(value_holds) => Bool::and(ctx, &[&has_key, &value_holds]),
+        None => has_key,
+    })
+}
+
+/// Whether `value` is computed as `spec` says from the inputs, or `None` if any value will do.
+fn spec_holds<'ctx, 'g>(
+    ctx: &'ctx Context,
+    spec: &'g ValueSpec,
+    value: &ValueAST<'ctx>,
+    input_keys: &mut HashMap<&'g str, Bool<'ctx>>,
+    input_values: &HashMap<&'g str, ValueAST<'ctx>>,
+) -> Option<Bool<'ctx>> {
+    match spec {
+        ValueSpec::Unknown => None,
+        ValueSpec::OneOf(values) => {
+            let is_one_of = values
+                .iter()
+                .map(|v| value.compare(CmpOp::Eq, v))
+                .collect::<Vec<_>>();
+            Some(Bool::or(ctx, &is_one_of.iter().collect::<Vec<_>>()))
+        }
+        ValueSpec::CopyOf(s) => {
+            let v_in = input_values.get(s.as_str())?;
+            if v_in.ty != value.ty {
+                panic!(
+                    "copy of {s} of type {:?} into a key of type {:?}",
+                    v_in.ty, value.ty
+                );
+            }
+            Some(key(ctx, input_keys, s).implies(&value.same_as(v_in)))
+        }
+        ValueSpec::Expr(expr) => {
+            let is_expr = value.is_expr(expr, input_values)?;
+            let has_keys = expr
+                .keys()
+                .into_iter()
+                .map(|s| key(ctx, input_keys, s))
+                .collect::<Vec<_>>();
+            Some(Bool::and(ctx, &has_keys.iter().collect::<Vec<_>>()).implies(&is_expr))
+        }
+    }
+}
+
+/// For each s, if s is an input key of the child, then s must be an output key of the parent, with the same value.
+/// `output_keys` are the outputs of the parent, and missing ones are created.
+/// A join child merges several branches, so each branch only contributes its outputs to the join's inputs;
+/// the converse, and the values, are enforced by `GraphVerifier` once all branches are built.
+/// Every condition in `conds` must also hold on the outputs.
+fn transition_constraint<'ctx, 'g>(
+    ctx: &'ctx Context,
+    child: &NodeAST<'ctx, 'g>,
+    conds: &'g [InputCond],
+    output_keys: &mut HashMap<&'g str, Bool<'ctx>>,
+    output_values: &HashMap<&'g str, ValueAST<'ctx>>,
+) -> Bool<'ctx> {
+    let mut implications = child
+        .input_keys
+        .iter()
+        .map(|(s, b_in)| {
+            let b_out = key(ctx, output_keys, s);
+            match &child.node.kind {
+                NodeKind::Join => b_out.implies(b_in),
+                _ => {
+                    let passed = match (child.input_values.get(s), output_values.get(s)) {
+                        (Some(v_in), Some(v_out)) => b_in.implies(&v_in.same_as(v_out)),
+                        _ => Bool::from_bool(ctx, true),
+                    };
+                    Bool::and(ctx, &[&b_in._eq(&b_out), &passed]) // TODO: check whether use eq or implies
+                }
+            }
+        })
+        .collect::<Vec<_>>();
+    implications.extend(
+        conds
+            .iter()
+            .filter_map(|cond| cond_holds(ctx, cond, output_keys, output_values)),
+    );
+    Bool::and(ctx, &(implications.iter().collect::<Vec<_>>()))
+}
+
+/// Constraints that define `output_keys` and `output_values` from `input_keys` and `input_values` according to
+/// `output_schema`. A key in `extra_outputs` is also output if its boolean is true. Input keys used by the schema
+/// are added to `input_keys`.
+/// A created key takes any value of its type, and a carried key the value of the input key it comes from.
+pub(crate) fn output_schema_constraints<'ctx, 'g>(
+    ctx: &'ctx Context,
+    output_schema: &'g OutputSchema,
+    extra_outputs: &HashMap<&'g str, Bool<'ctx>>,
+    input_keys: &mut HashMap<&'g str, Bool<'ctx>>,
+    input_values: &HashMap<&'g str, ValueAST<'ctx>>,
+    output_keys: &HashMap<&'g str, Bool<'ctx>>,
+    output_values: &HashMap<&'g str, ValueAST<'ctx>>,
+) -> Vec<Bool<'ctx>> {
+    let mut constraints = vec![];
+    output_keys.iter().for_each(|(s, b_out)| {
+        // if output[s] is true, then at least one of the keys that create or carry s must be true
+        let mut created = vec![];
+        let mut carried = vec![];
+        if output_schema.fixed_keys().any(|fixed| fixed == *s) {
+            created.push(Bool::from_bool(ctx, true));
+        }
+        // whether a may-key is output is up to the solver: chosen to reach a node, or to avoid it
+        if output_schema.may_keys().any(|may| may == *s) {
+            created.push(Bool::new_const(ctx, symbol!(format!("may output {s}"))));
+        }
+        if let Some(b) = extra_outputs.get(s) {
+            created.push(b.clone());
+        }
+        output_schema.dynamic_keys.iter().for_each(|(rule, cond)| {
+            // the input key carried to s, if any
+            let carried_key = match rule {
+                KeyRule::Fixed(ss) if ss == s => {
+                    created.push(
+                        cond_holds(ctx, cond, input_keys, input_values)
+                            .unwrap_or_else(|| Bool::from_bool(ctx, true)),
+                    );
+                    None
+                }
+                _ => rule.carried_key(s),
+            };
+            let Some(carried_key) = carried_key else {
+                return;
+            };
+            let applies = match cond {
+                InputCond::Always => Some(key(ctx, input_keys, carried_key)),
+                InputCond::MatchesKey(ss)
+                | InputCond::MatchesKeyValue(ss, _)
+                | InputCond::Compare(ss, _, _)
+                    if ss == carried_key =>
+                {
+                    cond_holds(ctx, cond, input_keys, input_values)
+                }
+                _ => None,
+            };
+            if let Some(applies) = applies {
+                carried.push((applies, carried_key));
+            }
+        });
+
+        // a key below s is only output with s
+        let below = output_keys
+            .iter()
+            .filter(|(ss, _)| **ss != *s && path::is_within(ss, s))
+            .map(|(_, b)| b)
+            .collect::<Vec<_>>();
+        let sources = created
+            .iter()
+            .chain(carried.iter().map(|(b, _)| b))
+            .chain(below.iter().copied());
+        let or = match created.len() + carried.len() + below.len() {
+            0 => Bool::from_bool(ctx, false),
+            _ => Bool::or(ctx, &sources.collect::<Vec<_>>()),
+        };
+        constraints.push(b_out._eq(&or)); // TODO: check whether use implication or equivalence
+
+        // a value spec decides the value, otherwise a created key takes precedence over carried ones,
+        // and a carried key of another type has any value
+        let Some(v_out) = output_values.get(s) else {
+            return;
+        };
+        if let Some(spec) = output_schema.value_spec(s) {
+            if let Some(spec_holds) = spec_holds(ctx, spec, v_out, input_keys, input_values) {
+                constraints.push(b_out.implies(&spec_holds));
+            }
+            return;
+        }
+        let carried_values = carried
+            .iter()
+            .map(|(b, carried_key)| {
+                let v_in = input_values
+                    .get(carried_key)
+                    .filter(|v_in| v_in.ty == v_out.ty)?;
+                Some(Bool::and(ctx, &[b, &v_out.same_as(v_in)]))
+            })
+            .collect::<Option<Vec<_>>>();
+        if let Some(carried_values) = carried_values.filter(|v| !v.is_empty()) {
+            let is_carried = Bool::and(
+                ctx,
+                &[
+                    &Bool::or(ctx, &carried.iter().map(|(b, _)| b).collect::<Vec<_>>()),
+                    &Bool::or(ctx, &created.iter().collect::<Vec<_>>()).not(),
+                ],
+            );
+            constraints.push(
+                is_carried.implies(&Bool::or(ctx, &carried_values.iter().collect::<Vec<_>>())),
+            );
+        }
+    });
+    constraints
 }
 
 impl<'ctx, 'g> NodeAST<'ctx, 'g> {
@@ -25,133 +259,185 @@ impl<'ctx, 'g> NodeAST<'ctx, 'g> {
         graph: &'g WorkflowGraph,
         children_ast: &[&NodeAST<'ctx, 'g>],
     ) -> Self {
-        let mut input_keys = node
-            .required_inputs
+        Self::build(ctx, node, graph, children_ast, &[], &[], None)
+    }
+
+    /// `handlers_ast[i]` is the AST of the target of `graph.catch_list[node.id][i]`.
+    /// `observed_outputs` are output keys that are used outside of `graph`, in addition to the ones used by the children,
+    /// both on success and on failure.
+    /// With a `contract`, the node needs the assumed keys and outputs exactly the guaranteed keys on success.
+    pub fn build(
+        ctx: &'ctx Context,
+        node: &'g Node,
+        graph: &'g WorkflowGraph,
+        children_ast: &[&NodeAST<'ctx, 'g>],
+        handlers_ast: &[&NodeAST<'ctx, 'g>],
+        observed_outputs: &[&'g str],
+        contract: Option<&'g Contract>,
+    ) -> Self {
+        let required_inputs = match contract {
+            Some(contract) => &contract.assumes,
+            None => &node.required_inputs,
+        };
+        let mut input_keys = required_inputs
             .iter()
             .map(|s| (s.as_str(), Bool::from_bool(ctx, true)))
             .collect::<HashMap<_, _>>();
-        let mut output_keys = HashMap::new();
-
-        let mut schema_constraints = Vec::new();
+        let mut output_keys = observed_outputs
+            .iter()
+            .map(|s| (*s, Bool::new_const(ctx, symbol!())))
+            .collect::<HashMap<_, _>>();
+        // every typed key has a value, whether or not it is present
+        let new_values = || {
+            graph
+                .key_types
+                .iter()
+                .map(|(s, ty)| (s.as_str(), ValueAST::new_const(ctx, ty)))
+                .collect::<HashMap<_, _>>()
+        };
+        let input_values = new_values();
+        let output_values = new_values();
+        let failure_output_values = node
+            .failures
+            .iter()
+            .map(|_| new_values())
+            .collect::<Vec<_>>();
 
-        // first, sanity check if children_ast is valid
+        // first, sanity check if children_ast and handlers_ast are valid
         if graph.adj_list[node.id].len() != children_ast.len() {
             panic!("invalid children_ast");
         };
+        if graph.catch_list[node.id].len() != handlers_ast.len() {
+            panic!("invalid handlers_ast");
+        };
 
-        // add transition constraints. For each child, for each s, if s is an input key of the child, then s must be an output key of current
+        // add transition constraints. For each child, for each s, if s is an input key of the child, then s must be an output key of current.
         let transition_constraints = children_ast
             .iter()
-            .map(|c| {
-                let implications = c
-                    .input_keys
+            .zip(graph.adj_list[node.id].iter())
+            .map(|(c, (_, conds))| {
+                transition_constraint(ctx, c, conds, &mut output_keys, &output_values)
+            })
+            .collect();
+
+        // add failure outcomes. A failure is caught by the first matching catch edge, whose target takes the failure outputs.
+        let failures = node
+            .failures
+            .iter()
+            .map(|failure| {
+                Bool::new_const(
+                    ctx,
+                    symbol!(format!("{} fails with {}", node.name, failure.error)),
+                )
+            })
+            .collect::<Vec<_>>();
+        let mut failure_output_keys = node
+            .failures
+            .iter()
+            .map(|_| {
+                observed_outputs
                     .iter()
-                    .map(|(s, b_in)| {
-                        let b_out = output_keys
-                            .entry(*s)
-                            .or_insert_with(|| Bool::new_const(ctx, symbol!()));
-                        b_in._eq(b_out) // TODO: check whether use eq or implies
-                    })
-                    .collect::<Vec<_>>();
-                Bool::and(ctx, &(implications.iter().collect::<Vec<_>>()))
+                    .map(|s| (*s, Bool::new_const(ctx, symbol!())))
+                    .collect::<HashMap<_, _>>()
             })
+            .collect::<Vec<_>>();
+        let mut caught = graph.catch_list[node.id]
+            .iter()
+            .map(|_| Vec::new())
+            .collect::<Vec<_>>();
+        node.failures.iter().enumerate().for_each(|(i, failure)| {
+            if let Some(catch_idx) = graph.catch_for(node.id, &failure.error) {
+                let transition = transition_constraint(
+                    ctx,
+                    handlers_ast[catch_idx],
+                    &[],
+                    &mut failure_output_keys[i],
+                    &failure_output_values[i],
+                );
+                caught[catch_idx].push(Bool::and(ctx, &[&failures[i], &transition]));
+            }
+        });
+        let catch_constraints = caught
+            .iter()
+            .map(|v| Bool::or(ctx, &v.iter().collect::<Vec<_>>()))
             .collect();
 
         // add schema constraints.
-
-        let mut disjuncts = output_keys
-            .keys()
-            .map(|s| (*s, Vec::new()))
-            .collect::<HashMap<_, _>>();
-        // disjuncts[s] is a list of booleans such that if output[s] is true,
-        // then at least one of the booleans in disjuncts[s] must be true
-        node.output_schema.fixed_keys().for_each(|s| {
-            if let Some(v) = disjuncts.get_mut(s) {
-                v.push(Bool::from_bool(ctx, true));
+        // a map node also outputs the aggregated results of its iterations,
+        // and a sub-workflow node also outputs what its module exports, which is bound by `GraphVerifier`
+        let exported_keys = match &node.kind {
+            _ if contract.is_some() => HashMap::new(),
+            NodeKind::Map(map_spec) => {
+                HashMap::from([(map_spec.results_key.as_str(), Bool::from_bool(ctx, true))])
             }
+            NodeKind::SubWorkflow(module) => module
+                .outputs
+                .iter()
+                .map(|s| (s.as_str(), Bool::new_const(ctx, symbol!())))
+                .collect(),
+            _ => HashMap::new(),
+        };
+        // a created key below an output key makes it present, so it is needed to encode the output key
+        let add_created_below =
+            |schema: &'g OutputSchema, keys: &mut HashMap<&'g str, Bool<'ctx>>| {
+                let below = schema
+                    .created_keys()
+                    .filter(|s| path::ancestors(s).any(|a| keys.contains_key(a)))
+                    .collect::<Vec<_>>();
+                below.into_iter().for_each(|s| {
+                    keys.entry(s)
+                        .or_insert_with(|| Bool::new_const(ctx, symbol!()));
+                });
+            };
+        if contract.is_none() {
+            add_created_below(&node.output_schema, &mut output_keys);
+        }
+        node.failures.iter().enumerate().for_each(|(i, failure)| {
+            add_created_below(&failure.output_schema, &mut failure_output_keys[i]);
         });
-        output_keys.keys().for_each(|s| {
-            node.output_schema
-                .dynamic_keys
+        let mut schema_constraints = match contract {
+            Some(contract) => output_keys
                 .iter()
-                .for_each(|(rule, cond)| {
-                    let constraint = match rule {
-                        KeyRule::Identity => match cond {
-                            InputCond::Always => Some(
-                                input_keys
-                                    .entry(s)
-                                    .or_insert_with(|| Bool::new_const(ctx, symbol!()))
-                                    .clone(),
-                            ),
-                            InputCond::MatchesKey(ss) if ss == s => Some(
-                                input_keys
-                                    .entry(s)
-                                    .or_insert_with(|| Bool::new_const(ctx, symbol!()))
-                                    .clone(),
-                            ),
-                            _ => None,
-                        },
-                        KeyRule::Fixed(ss) if ss == s => match cond {
-                            InputCond::Always => Some(Bool::from_bool(ctx, true)),
-                            InputCond::MatchesKey(s_input) => Some(
-                                input_keys
-                                    .entry(s_input)
-                                    .or_insert_with(|| Bool::new_const(ctx, symbol!()))
-                                    .clone(),
-                            ),
-                            InputCond::MatchesKeyValue(s_input, _) => Some(
-                                input_keys
-                                    .entry(s_input)
-                                    .or_insert_with(|| Bool::new_const(ctx, symbol!()))
-                                    .clone(),
-                                // TODO: value is ignored for now
-                            ),
-                        },
-                        KeyRule::IdWithPrefix(prefix) if s.starts_with(prefix) => {
-                            let expected_input = s.strip_prefix(prefix).unwrap();
-                            match cond {
-                                InputCond::Always => Some(
-                                    input_keys
-                                        .entry(expected_input)
-                                        .or_insert_with(|| Bool::new_const(ctx, symbol!()))
-                                        .clone(),
-                                ),
-                                InputCond::MatchesKey(s_input) if s_input == expected_input => {
-                                    Some(
-                                        input_keys
-                                            .entry(s_input)
-                                            .or_insert_with(|| Bool::new_const(ctx, symbol!()))
-                                            .clone(),
-                                    )
-                                }
-                                InputCond::MatchesKeyValue(s_input, _)
-                                    if s_input == expected_input =>
-                                {
-                                    Some(
-                                        input_keys
-                                            .entry(s_input)
-                                            .or_insert_with(|| Bool::new_const(ctx, symbol!()))
-                                            .clone(),
-                                    )
-                                } // TODO: value is ignored for now
-                                _ => None,
-                            }
-                        }
-                        _ => None,
-                    };
-                    if let Some(c) = constraint {
-                        disjuncts.get_mut(s).unwrap().push(c);
-                    }
+                .map(|(s, b)| {
+                    b._eq(&Bool::from_bool(
+                        ctx,
+                        contract.guarantees.iter().any(|g| g == s),
+                    ))
                 })
+                .collect(),
+            None => output_schema_constraints(
+                ctx,
+                &node.output_schema,
+                &exported_keys,
+                &mut input_keys,
+                &input_values,
+                &output_keys,
+                &output_values,
+            ),
+        };
+        node.failures.iter().enumerate().for_each(|(i, failure)| {
+            schema_constraints.extend(output_schema_constraints(
+                ctx,
+                &failure.output_schema,
+                &HashMap::new(),
+                &mut input_keys,
+                &input_values,
+                &failure_output_keys[i],
+                &failure_output_values[i],
+            ));
+        });
+        // an input key implies the input keys above it
+        input_keys.iter().for_each(|(s, b)| {
+            path::ancestors(s)
+                .filter_map(|a| input_keys.get(a))
+                .for_each(|a| schema_constraints.push(b.implies(a)));
+        });
+        // a node fails in at most one way
+        failures.iter().enumerate().for_each(|(i, f1)| {
+            failures[i + 1..].iter().for_each(|f2| {
+                schema_constraints.push(Bool::and(ctx, &[f1, f2]).not());
+            })
         });
-
-        for (s, v) in disjuncts {
-            let or = match v.len() {
-                0 => Bool::from_bool(ctx, false),
-                _ => Bool::or(ctx, &(v.iter().collect::<Vec<_>>())),
-            };
-            schema_constraints.push(output_keys[s]._eq(&or)); // TODO: check whether use implication or equivalence
-        }
 
         Self {
             ctx,
@@ -160,10 +446,22 @@ impl<'ctx, 'g> NodeAST<'ctx, 'g> {
             output_keys,
             transition_constraints,
             schema_constraints,
+            failures,
+            failure_output_keys,
+            catch_constraints,
+            exported_keys,
+            input_values,
+            output_values,
+            failure_output_values,
         }
     }
 
-    fn eval_keys(map: HashMap<&str, Bool<'ctx>>, model: &Model) -> HashMap<String, bool> {
+    /// Whether the node fails in any way.
+    pub fn fails(&self) -> Bool<'ctx> {
+        Bool::or(self.ctx, &self.failures.iter().collect::<Vec<_>>())
+    }
+
+    pub fn eval_keys(map: HashMap<&str, Bool<'ctx>>, model: &Model) -> HashMap<String, bool> {
         map.iter()
             .map(|(s, b)| {
                 (
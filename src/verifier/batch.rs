@@ -0,0 +1,101 @@
+//! Run many queries on one graph in parallel. Each worker thread has its own z3 `Context` and `GraphVerifier`,
+//! and takes the next query as soon as it is done with the previous one.
+
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use z3::{Config, Context};
+
+use crate::workflow::{NodeIdx, WorkflowGraph};
+
+use super::{ExecutionModel, GraphVerifier};
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum Query {
+    /// `GraphVerifier::is_reachable`.
+    Reachable(NodeIdx),
+    /// `GraphVerifier::can_eventually_reach`.
+    CanEventuallyReach(Vec<NodeIdx>),
+    /// `GraphVerifier::minimum_input_set_for_reachable`.
+    MinimumInputSet(NodeIdx),
+    /// `GraphVerifier::is_robust`.
+    Robust(Vec<NodeIdx>, usize),
+    /// `GraphVerifier::unhandled_failures`.
+    UnhandledFailures,
+}
+
+#[derive(Debug)]
+pub enum QueryResult {
+    /// The nodes on a path of an execution that reaches the node, if there is one.
+    Reachable(Option<Vec<ExecutionModel>>),
+    CanEventuallyReach(bool),
+    /// The input keys, sorted, if the node is reachable.
+    MinimumInputSet(Option<Vec<String>>),
+    Robust(bool),
+    UnhandledFailures(Vec<(NodeIdx, String)>),
+}
+
+#[derive(Debug)]
+pub struct TimedResult {
+    pub result: QueryResult,
+    /// Time to answer the query, not counting the encoding of the graph. Zero for cached results.
+    pub elapsed: Duration,
+    /// Whether the result was read from a `ResultCache` instead of the solver.
+    pub cached: bool,
+}
+
+fn answer(verifier: &GraphVerifier, query: &Query) -> QueryResult {
+    match query {
+        Query::Reachable(target) => {
+            QueryResult::Reachable(verifier.is_reachable(*target).map(|(path, _)| path))
+        }
+        Query::CanEventuallyReach(targets) => {
+            QueryResult::CanEventuallyReach(verifier.can_eventually_reach(targets))
+        }
+        Query::MinimumInputSet(target) => {
+            QueryResult::MinimumInputSet(verifier.minimum_input_set_for_reachable(*target).map(
+                |(mut keys, _)| {
+                    keys.sort();
+                    keys
+                },
+            ))
+        }
+        Query::Robust(targets, k) => QueryResult::Robust(verifier.is_robust(targets, *k)),
+        Query::UnhandledFailures => QueryResult::UnhandledFailures(verifier.unhandled_failures()),
+    }
+}
+
+/// Answer `queries` about `graph` on `threads` worker threads. The results are in the order of the queries.
+pub fn verify_batch(graph: &WorkflowGraph, queries: &[Query], threads: usize) -> Vec<TimedResult> {
+    let next = AtomicUsize::new(0);
+    let results = Mutex::new((0..queries.len()).map(|_| None).collect::<Vec<_>>());
+    std::thread::scope(|scope| {
+        (0..threads.max(1).min(queries.len())).for_each(|_| {
+            scope.spawn(|| {
+                let context = Context::new(&Config::default());
+                let verifier = GraphVerifier::new(graph, &context);
+                loop {
+                    let i = next.fetch_add(1, Ordering::Relaxed);
+                    let Some(query) = queries.get(i) else {
+                        break;
+                    };
+                    let start = Instant::now();
+                    let result = answer(&verifier, query);
+                    let elapsed = start.elapsed();
+                    results.lock().unwrap()[i] = Some(TimedResult {
+                        result,
+                        elapsed,
+                        cached: false,
+                    });
+                }
+            });
+        });
+    });
+    results
+        .into_inner()
+        .unwrap()
+        .into_iter()
+        .map(Option::unwrap)
+        .collect()
+}
@@ -0,0 +1,247 @@
+//! Verification results stored on disk, so that unchanged workflows are not verified again.
+//!
+//! Results are keyed by the canonical hash of the graph, the query and `VERIFIER_VERSION`. Queries and results
+//! refer to nodes by name, so a result stays valid when the nodes of the graph are reordered. Each graph has
+//! one JSON file in the cache directory, named after its hash. The file also holds the canonical text of the
+//! graph, so that a graph whose hash collides with another one is not answered with the results of the other.
+
+use std::collections::BTreeMap;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+use crate::workflow::hash::{canonical_hash, canonical_text};
+use crate::workflow::{NodeIdx, WorkflowGraph};
+
+use super::batch::{verify_batch, Query, QueryResult, TimedResult};
+use super::ExecutionModel;
+
+/// Results of an older verifier are ignored. Bump when the encoding changes what queries answer.
+pub const VERIFIER_VERSION: &str = concat!(env!("CARGO_PKG_VERSION"), "/1");
+
+/// The directory the `verify_cache` command uses unless `VERIFY_CACHE_DIR` is set.
+pub const DEFAULT_CACHE_DIR: &str = ".verify-cache";
+
+#[derive(Debug, Serialize, Deserialize)]
+enum StoredQuery {
+    Reachable(String),
+    /// The targets, sorted.
+    CanEventuallyReach(Vec<String>),
+    MinimumInputSet(String),
+    Robust(Vec<String>, usize),
+    UnhandledFailures,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct StoredStep {
+    node: String,
+    input_keys: Vec<String>,
+    output_keys: Vec<String>,
+    error: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+enum StoredResult {
+    Reachable(Option<Vec<StoredStep>>),
+    CanEventuallyReach(bool),
+    MinimumInputSet(Option<Vec<String>>),
+    Robust(bool),
+    UnhandledFailures(Vec<(String, String)>),
+}
+
+/// The cache file of one graph.
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct Entry {
+    version: String,
+    /// The `canonical_text` of the graph.
+    graph: String,
+    /// Results by the JSON of their `StoredQuery`.
+    results: BTreeMap<String, StoredResult>,
+}
+
+pub struct ResultCache {
+    dir: PathBuf,
+}
+
+impl ResultCache {
+    /// A cache stored in `dir`, which is created when the first result is stored.
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self { dir: dir.into() }
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    fn path(&self, hash: u64) -> PathBuf {
+        self.dir.join(format!("{hash:016x}.json"))
+    }
+
+    /// The stored results for the graph with `hash` and canonical `text`. A missing, unreadable or outdated file
+    /// is an empty entry, and so is the file of another graph with the same hash.
+    fn load(&self, hash: u64, text: &str) -> Entry {
+        std::fs::read_to_string(self.path(hash))
+            .ok()
+            .and_then(|json| serde_json::from_str::<Entry>(&json).ok())
+            .filter(|entry| entry.version == VERIFIER_VERSION && entry.graph == text)
+            .unwrap_or_default()
+    }
+
+    fn store(&self, hash: u64, entry: &Entry) -> io::Result<()> {
+        std::fs::create_dir_all(&self.dir)?;
+        // write then rename, so that a concurrent reader never sees half a file
+        let tmp = self
+            .path(hash)
+            .with_extension(format!("{}.tmp", std::process::id()));
+        std::fs::write(&tmp, serde_json::to_string_pretty(entry)?)?;
+        std::fs::rename(tmp, self.path(hash))
+    }
+
+    /// Like `verify_batch`, but answer the queries stored for `graph` from the cache, and store the others.
+    /// If every query is stored, the graph is not encoded at all. Failing to write the cache is not an error.
+    pub fn verify_batch(
+        &self,
+        graph: &WorkflowGraph,
+        queries: &[Query],
+        threads: usize,
+    ) -> Vec<TimedResult> {
+        let text = canonical_text(graph);
+        let hash = canonical_hash(graph);
+        let mut entry = self.load(hash, &text);
+        let keys = queries
+            .iter()
+            .map(|query| serde_json::to_string(&to_stored_query(graph, query)).unwrap())
+            .collect::<Vec<_>>();
+        let misses = queries
+            .iter()
+            .zip(&keys)
+            .filter(|(_, key)| !entry.results.contains_key(*key))
+            .map(|(query, _)| query.clone())
+            .collect::<Vec<_>>();
+        let mut computed = verify_batch(graph, &misses, threads).into_iter();
+
+        let results = keys
+            .iter()
+            .map(|key| match entry.results.get(key) {
+                Some(stored) => TimedResult {
+                    result: from_stored_result(graph, stored),
+                    elapsed: Default::default(),
+                    cached: true,
+                },
+                None => computed.next().unwrap(),
+            })
+            .collect::<Vec<_>>();
+        if !misses.is_empty() {
+            entry.version = VERIFIER_VERSION.to_string();
+            entry.graph = text;
+            for (key, timed) in keys.into_iter().zip(&results) {
+                if !timed.cached {
+                    entry
+                        .results
+                        .insert(key, to_stored_result(graph, &timed.result));
+                }
+            }
+            let _ = self.store(hash, &entry);
+        }
+        results
+    }
+
+    /// Remove the stored results of `graph`.
+    pub fn invalidate_graph(&self, graph: &WorkflowGraph) -> io::Result<()> {
+        match std::fs::remove_file(self.path(canonical_hash(graph))) {
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(()),
+            result => result,
+        }
+    }
+
+    /// Remove every stored result. Returns the number of graphs whose results were removed.
+    pub fn invalidate(&self) -> io::Result<usize> {
+        let entries = match std::fs::read_dir(&self.dir) {
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(0),
+            entries => entries?,
+        };
+        let mut removed = 0;
+        for entry in entries {
+            let path = entry?.path();
+            if path.extension().is_some_and(|ext| ext == "json") {
+                std::fs::remove_file(path)?;
+                removed += 1;
+            }
+        }
+        Ok(removed)
+    }
+}
+
+fn names(graph: &WorkflowGraph, nodes: &[NodeIdx]) -> Vec<String> {
+    let mut names = nodes
+        .iter()
+        .map(|node| graph.nodes[*node].name.clone())
+        .collect::<Vec<_>>();
+    names.sort();
+    names
+}
+
+fn to_stored_query(graph: &WorkflowGraph, query: &Query) -> StoredQuery {
+    let name = |node: &NodeIdx| graph.nodes[*node].name.clone();
+    match query {
+        Query::Reachable(target) => StoredQuery::Reachable(name(target)),
+        Query::CanEventuallyReach(targets) => {
+            StoredQuery::CanEventuallyReach(names(graph, targets))
+        }
+        Query::MinimumInputSet(target) => StoredQuery::MinimumInputSet(name(target)),
+        Query::Robust(targets, k) => StoredQuery::Robust(names(graph, targets), *k),
+        Query::UnhandledFailures => StoredQuery::UnhandledFailures,
+    }
+}
+
+fn to_stored_result(graph: &WorkflowGraph, result: &QueryResult) -> StoredResult {
+    let name = |node: NodeIdx| graph.nodes[node].name.clone();
+    match result {
+        QueryResult::Reachable(path) => StoredResult::Reachable(path.as_ref().map(|path| {
+            path.iter()
+                .map(|step| StoredStep {
+                    node: name(step.node_idx),
+                    input_keys: step.input_keys.clone(),
+                    output_keys: step.output_keys.clone(),
+                    error: step.error.clone(),
+                })
+                .collect()
+        })),
+        QueryResult::CanEventuallyReach(b) => StoredResult::CanEventuallyReach(*b),
+        QueryResult::MinimumInputSet(keys) => StoredResult::MinimumInputSet(keys.clone()),
+        QueryResult::Robust(b) => StoredResult::Robust(*b),
+        QueryResult::UnhandledFailures(failures) => StoredResult::UnhandledFailures(
+            failures
+                .iter()
+                .map(|(node, error)| (name(*node), error.clone()))
+                .collect(),
+        ),
+    }
+}
+
+fn from_stored_result(graph: &WorkflowGraph, result: &StoredResult) -> QueryResult {
+    // the graph has the same canonical text, so it has a node of every stored name
+    let idx = |name: &str| graph.nodes.iter().position(|n| n.name == name).unwrap();
+    match result {
+        StoredResult::Reachable(path) => QueryResult::Reachable(path.as_ref().map(|path| {
+            path.iter()
+                .map(|step| ExecutionModel {
+                    node_idx: idx(&step.node),
+                    input_keys: step.input_keys.clone(),
+                    output_keys: step.output_keys.clone(),
+                    error: step.error.clone(),
+                })
+                .collect()
+        })),
+        StoredResult::CanEventuallyReach(b) => QueryResult::CanEventuallyReach(*b),
+        StoredResult::MinimumInputSet(keys) => QueryResult::MinimumInputSet(keys.clone()),
+        StoredResult::Robust(b) => QueryResult::Robust(*b),
+        StoredResult::UnhandledFailures(failures) => QueryResult::UnhandledFailures(
+            failures
+                .iter()
+                .map(|(node, error)| (idx(node), error.clone()))
+                .collect(),
+        ),
+    }
+}
@@ -0,0 +1,165 @@
+use std::collections::HashMap;
+
+use z3::{ast::Bool, Config, Context, SatResult, Solver};
+
+use crate::workflow::contract::Contract;
+use crate::workflow::module::WorkflowModule;
+use crate::workflow::{Node, NodeIdx, NodeKind, WorkflowGraph};
+
+use super::ast::output_schema_constraints;
+use super::symbol::symbol;
+use super::{Abstraction, GraphVerifier};
+
+/// Why a node or a module does not satisfy its contract.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ContractViolation {
+    /// `node` requires `key`, which the contract does not assume.
+    UnassumedInput { node: NodeIdx, key: String },
+    /// `node` may complete without `key`, which the contract guarantees.
+    MissingGuarantee { node: NodeIdx, key: String },
+}
+
+/// Check the contract of every node of `graph` that has its own contract, each one on its own.
+/// A node running a module with a contract only has its required inputs checked; the module contract itself
+/// is checked by `check_module_contract`.
+pub fn check_contracts(graph: &WorkflowGraph) -> Vec<ContractViolation> {
+    graph
+        .nodes
+        .iter()
+        .flat_map(|node| match (&node.contract, node.effective_contract()) {
+            (Some(contract), _) => check_node_contract(node, contract),
+            (None, Some(contract)) => unassumed_inputs(node, contract),
+            (None, None) => vec![],
+        })
+        .collect()
+}
+
+/// Check that every completed run of `module` started with the assumed keys outputs the guaranteed keys.
+/// Violations refer to the nodes of `module.graph`.
+pub fn check_module_contract(module: &WorkflowModule) -> Vec<ContractViolation> {
+    let Some(contract) = &module.contract else {
+        return vec![];
+    };
+    let start = module.graph.start.unwrap();
+    let mut violations = unassumed_inputs(&module.graph.nodes[start], contract);
+
+    let context = Context::new(&Config::default());
+    let verifier = GraphVerifier::build(
+        &module.graph,
+        &context,
+        Some((module.end, &module.outputs)),
+        &[],
+        Abstraction::Inline,
+    );
+    // keys that are not assumed may or may not be passed
+    let (completes, _) = verifier.nested_run(
+        |s| {
+            if contract.assumes.iter().any(|a| a == s) {
+                Bool::from_bool(&context, true)
+            } else {
+                Bool::new_const(&context, symbol!())
+            }
+        },
+        module.end,
+    );
+    let end_ast = &verifier.node_asts[module.end];
+    contract.guarantees.iter().for_each(|key| {
+        let violated = match end_ast.output_keys.get(key.as_str()) {
+            Some(b) => {
+                let solver = Solver::new(&context);
+                solver.assert(&completes);
+                solver.assert(&b.not());
+                matches!(solver.check(), SatResult::Sat)
+            }
+            // not a declared output of the module
+            None => true,
+        };
+        if violated {
+            violations.push(ContractViolation::MissingGuarantee {
+                node: module.end,
+                key: key.clone(),
+            });
+        }
+    });
+    violations
+}
+
+fn unassumed_inputs(node: &Node, contract: &Contract) -> Vec<ContractViolation> {
+    node.required_inputs
+        .iter()
+        .filter(|s| !contract.assumes.contains(s))
+        .map(|s| ContractViolation::UnassumedInput {
+            node: node.id,
+            key: s.clone(),
+        })
+        .collect()
+}
+
+/// The outputs of the node only depend on its input and output schema, so its contract is checked without
+/// the rest of the graph.
+fn check_node_contract(node: &Node, contract: &Contract) -> Vec<ContractViolation> {
+    let mut violations = unassumed_inputs(node, contract);
+
+    let context = Context::new(&Config::default());
+    // keys that are not assumed may or may not be input
+    let mut input_keys = contract
+        .assumes
+        .iter()
+        .map(|s| (s.as_str(), Bool::from_bool(&context, true)))
+        .collect::<HashMap<_, _>>();
+    let output_keys = contract
+        .guarantees
+        .iter()
+        .map(|s| (s.as_str(), Bool::new_const(&context, symbol!())))
+        .collect::<HashMap<_, _>>();
+    // a map node outputs its results, and a sub-workflow node what its module guarantees for the assumed keys
+    let exported_keys = match &node.kind {
+        NodeKind::Map(map_spec) => HashMap::from([(
+            map_spec.results_key.as_str(),
+            Bool::from_bool(&context, true),
+        )]),
+        NodeKind::SubWorkflow(module) => module
+            .outputs
+            .iter()
+            .map(|s| {
+                let guaranteed = match &module.contract {
+                    Some(module_contract) => {
+                        module_contract.guarantees.contains(s)
+                            && module_contract
+                                .assumes
+                                .iter()
+                                .all(|a| contract.assumes.contains(a) && module.inputs.contains(a))
+                    }
+                    None => false,
+                };
+                let b = match guaranteed {
+                    true => Bool::from_bool(&context, true),
+                    false => Bool::new_const(&context, symbol!()),
+                };
+                (s.as_str(), b)
+            })
+            .collect(),
+        _ => HashMap::new(),
+    };
+    let schema_constraints = output_schema_constraints(
+        &context,
+        &node.output_schema,
+        &exported_keys,
+        &mut input_keys,
+        &HashMap::new(),
+        &output_keys,
+        &HashMap::new(),
+    );
+    contract.guarantees.iter().for_each(|key| {
+        let solver = Solver::new(&context);
+        schema_constraints.iter().for_each(|c| solver.assert(c));
+        solver.assert(&output_keys[key.as_str()].not());
+        if let SatResult::Sat = solver.check() {
+            violations.push(ContractViolation::MissingGuarantee {
+                node: node.id,
+                key: key.clone(),
+            });
+        }
+    });
+    violations
+}
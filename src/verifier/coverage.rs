@@ -0,0 +1,154 @@
+//! Start inputs that together take every edge of a workflow, as replayable test fixtures.
+
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+use z3::{
+    ast::{Bool, Int},
+    Config, Context, Optimize, SatResult,
+};
+
+use crate::workflow::value::Value;
+use crate::workflow::{NodeIdx, WorkflowGraph};
+
+use super::{topsort, Abstraction, GraphVerifier};
+
+/// One execution to replay: the start inputs, and the nodes and edges it is expected to go through.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TestCase {
+    /// Input keys given to the start node, with their values if their type is declared, and else `null`.
+    pub inputs: BTreeMap<String, serde_json::Value>,
+    /// Nodes entered by the execution, in topological order.
+    pub path: Vec<String>,
+    /// Success edges taken by the execution, as (source, target) names.
+    pub edges: Vec<(String, String)>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub struct TestSuite {
+    pub tests: Vec<TestCase>,
+    /// Edges that no execution takes.
+    pub uncovered_edges: Vec<(String, String)>,
+}
+
+impl TestSuite {
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).unwrap()
+    }
+
+    pub fn from_json(json: &str) -> serde_json::Result<Self> {
+        serde_json::from_str(json)
+    }
+}
+
+fn json(value: Value) -> serde_json::Value {
+    match value {
+        Value::String(s) => serde_json::Value::String(s),
+        Value::Int(n) => n.into(),
+        Value::Bool(b) => b.into(),
+        Value::Null => serde_json::Value::Null,
+    }
+}
+
+/// The number of `bools` that are true.
+fn count<'ctx>(ctx: &'ctx Context, bools: &[&Bool<'ctx>]) -> Int<'ctx> {
+    let one = Int::from_i64(ctx, 1);
+    let zero = Int::from_i64(ctx, 0);
+    let ints = bools.iter().map(|b| b.ite(&one, &zero)).collect::<Vec<_>>();
+    Int::add(ctx, &ints.iter().collect::<Vec<_>>())
+}
+
+/// Generate start inputs whose executions together take every edge in `adj_list` that can be taken.
+/// Each test case covers as many remaining edges as possible, with as few input keys as possible.
+pub fn covering_test_suite(graph: &WorkflowGraph) -> TestSuite {
+    let context = Context::new(&Config::default());
+    let verifier = GraphVerifier::build(graph, &context, None, &[], Abstraction::Inline);
+    let executions = verifier.get_reached_constraints(&[]);
+    let mut order = topsort::topological_sort_reversed(graph);
+    order.reverse();
+    let edge_name =
+        |src: NodeIdx, dst: NodeIdx| (graph.nodes[src].name.clone(), graph.nodes[dst].name.clone());
+    // (source, target, taken) for every success edge
+    let edges = graph
+        .adj_list
+        .iter()
+        .enumerate()
+        .flat_map(|(src, adj)| {
+            executions.taken[src]
+                .iter()
+                .take(adj.len())
+                .map(move |(dst, taken)| (src, *dst, taken))
+        })
+        .collect::<Vec<_>>();
+    let start_inputs = &verifier.node_asts[graph.start.unwrap()].input_keys;
+    let num_inputs = count(&context, &start_inputs.values().collect::<Vec<_>>());
+
+    let optimize = Optimize::new(&context);
+    optimize.assert(&verifier.schema_constraints());
+    optimize.assert(&verifier.maximal_execution_constraints(&executions));
+    let mut covered = vec![false; edges.len()];
+    let mut tests = vec![];
+    loop {
+        let uncovered = edges
+            .iter()
+            .zip(covered.iter())
+            .filter(|(_, covered)| !**covered)
+            .map(|((_, _, taken), _)| *taken)
+            .collect::<Vec<_>>();
+        if uncovered.is_empty() {
+            break;
+        }
+        optimize.push();
+        optimize.assert(&Bool::or(&context, &uncovered));
+        optimize.maximize(&count(&context, &uncovered));
+        optimize.minimize(&num_inputs);
+        let result = optimize.check(&[]);
+        let model = optimize.get_model();
+        optimize.pop();
+        let (SatResult::Sat, Some(model)) = (result, model) else {
+            break;
+        };
+        let holds = |b: &Bool| model.eval(b, true).unwrap().as_bool().unwrap();
+
+        let start_values = &verifier.node_asts[graph.start.unwrap()].input_values;
+        let inputs = start_inputs
+            .iter()
+            .filter(|(_, b)| holds(b))
+            .map(|(s, _)| {
+                let value = start_values
+                    .get(s)
+                    .map_or(serde_json::Value::Null, |v| json(v.eval(&model)));
+                (s.to_string(), value)
+            })
+            .collect();
+        let path = order
+            .iter()
+            .filter(|i| holds(&executions.entered[**i]))
+            .map(|i| graph.nodes[*i].name.clone())
+            .collect();
+        let taken_edges = edges
+            .iter()
+            .enumerate()
+            .filter(|(_, (_, _, taken))| holds(taken))
+            .map(|(i, (src, dst, _))| {
+                covered[i] = true;
+                edge_name(*src, *dst)
+            })
+            .collect();
+        tests.push(TestCase {
+            inputs,
+            path,
+            edges: taken_edges,
+        });
+    }
+    let uncovered_edges = edges
+        .iter()
+        .zip(covered.iter())
+        .filter(|(_, covered)| !**covered)
+        .map(|((src, dst, _), _)| edge_name(*src, *dst))
+        .collect();
+    TestSuite {
+        tests,
+        uncovered_edges,
+    }
+}
@@ -0,0 +1,86 @@
+//! What changes in the verification results when output schemas are edited.
+
+use z3::{Config, Context};
+
+use crate::workflow::schema::SchemaEdit;
+use crate::workflow::{NodeIdx, WorkflowGraph};
+
+use super::property::{check_property, Formula, UnknownNode};
+use super::GraphVerifier;
+
+/// A check whose result may be affected by an edit.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Check {
+    /// Some execution reaches the node.
+    Reachable(NodeIdx),
+    /// Every execution reaches the node.
+    EventuallyReached(NodeIdx),
+    /// The property at this index holds.
+    Property(usize),
+}
+
+/// A check whose result differs between the original graph and the edited graph.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ImpactChange {
+    pub check: Check,
+    pub before: bool,
+    pub after: bool,
+}
+
+/// Run every check on `graph`: reachability and eventual reachability of every node, and every property.
+pub fn verification_results(
+    graph: &WorkflowGraph,
+    properties: &[Formula],
+) -> Result<Vec<(Check, bool)>, UnknownNode> {
+    let ctx = Context::new(&Config::default());
+    let graph_verifier = GraphVerifier::new(graph, &ctx);
+    let nodes = 0..graph.nodes.len();
+    let reachable = nodes.clone().map(|node| {
+        (
+            Check::Reachable(node),
+            graph_verifier.is_reachable(node).is_some(),
+        )
+    });
+    let eventually_reached = nodes.map(|node| {
+        (
+            Check::EventuallyReached(node),
+            graph_verifier.can_eventually_reach(&[node]),
+        )
+    });
+    let properties = properties
+        .iter()
+        .enumerate()
+        .map(|(i, formula)| {
+            Ok((
+                Check::Property(i),
+                check_property(graph, formula)?.is_none(),
+            ))
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(reachable
+        .chain(eventually_reached)
+        .chain(properties)
+        .collect())
+}
+
+/// Apply `edits` to a copy of `graph`, as (node, edit) pairs, and report the checks whose results change.
+pub fn impact_of_edits(
+    graph: &WorkflowGraph,
+    edits: &[(NodeIdx, SchemaEdit)],
+    properties: &[Formula],
+) -> Result<Vec<ImpactChange>, UnknownNode> {
+    let mut edited = graph.clone();
+    edits.iter().for_each(|(node, edit)| {
+        edited.edit_schema(*node, edit);
+    });
+    Ok(verification_results(graph, properties)?
+        .into_iter()
+        .zip(verification_results(&edited, properties)?)
+        .filter(|((_, before), (_, after))| before != after)
+        .map(|((check, before), (_, after))| ImpactChange {
+            check,
+            before,
+            after,
+        })
+        .collect())
+}
@@ -1,39 +1,130 @@
-use std::collections::{hash_map::RandomState, HashMap, HashSet, VecDeque};
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet, VecDeque};
 
 use z3::{
     ast::{Ast, Bool, Int},
-    Context, Model, SatResult, Solver,
+    Config, Context, Model, SatResult, Solver,
 };
 
-use crate::workflow::{Node, NodeIdx, WorkflowGraph};
+use crate::workflow::module::{ModuleSummary, WorkflowModule};
+use crate::workflow::schema::{InputCond, KeyRule, OutputSchema};
+use crate::workflow::{NodeIdx, NodeKind, WorkflowGraph};
 
 use self::ast::NodeAST;
+use self::symbol::symbol;
+use self::value::ValueAST;
 
 pub mod ast;
+pub mod batch;
+pub mod cache;
+pub mod contract;
+pub mod coverage;
+pub mod impact;
+pub mod property;
+pub mod provenance;
+pub mod saga;
 pub mod symbol;
 pub mod topsort;
+pub mod value;
 
 pub struct GraphVerifier<'ctx, 'g> {
     context: &'ctx Context,
     pub graph: &'g WorkflowGraph,
     pub node_asts: Vec<NodeAST<'ctx, 'g>>,
+    node_constraints: Vec<Bool<'ctx>>, // node_constraints[i] is satisfied iff node i can complete once all its incoming edges are taken
+    node_failures: Vec<Bool<'ctx>>, // node_failures[i] is satisfied iff node i can fail once all its incoming edges are taken
+}
+
+/// Encoding of the executions from the start node.
+struct Executions<'ctx> {
+    entered: Vec<Bool<'ctx>>, // entered[i] is true iff node i is started
+    reached: Vec<Bool<'ctx>>, // reached[i] is true iff node i is started and completes, successfully or with a failure
+    taken: Vec<Vec<(NodeIdx, Bool<'ctx>)>>, // taken[i] lists the targets of the success edges and then the catch edges of node i, with whether each one is taken
+}
+
+/// How a node is encoded when it has a contract, or runs another workflow.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Abstraction {
+    Inline,
+    ModuleSummaries,
+    Contracts,
+}
+
+/// Modules with more declared inputs than this are inlined even with `Abstraction::ModuleSummaries`, since
+/// their summary takes 2^n solver calls for n declared inputs.
+pub const MAX_SUMMARIZED_INPUTS: usize = 8;
+
+impl Abstraction {
+    fn summarizes(self, module: &WorkflowModule) -> bool {
+        self == Abstraction::ModuleSummaries && module.inputs.len() <= MAX_SUMMARIZED_INPUTS
+    }
 }
 
 #[derive(Debug)]
 pub struct ExecutionModel {
-    node_idx: NodeIdx,
-    input_keys: Vec<String>,
-    output_keys: Vec<String>,
+    pub node_idx: NodeIdx,
+    pub input_keys: Vec<String>,
+    pub output_keys: Vec<String>,
+    pub error: Option<String>, // the failure of the node, in which case output_keys are the failure outputs
 }
 
 impl<'ctx, 'g> GraphVerifier<'ctx, 'g> {
+    /// Panics if `graph` has a cycle among the nodes reachable from its start node.
     pub fn new(graph: &'g WorkflowGraph, context: &'ctx Context) -> Self {
+        Self::build(graph, context, None, &[], Abstraction::Inline)
+    }
+
+    /// Like `new`, but a sub-workflow node uses the summary of its module instead of inlining the module.
+    /// Summaries are computed once per module and shared by all nodes and verifiers that use the module.
+    /// Modules with more than `MAX_SUMMARIZED_INPUTS` declared inputs are still inlined.
+    pub fn with_module_summaries(graph: &'g WorkflowGraph, context: &'ctx Context) -> Self {
+        Self::build(graph, context, None, &[], Abstraction::ModuleSummaries)
+    }
+
+    /// Like `new`, but a node with a contract, or running a module with a contract, is encoded by the contract
+    /// alone. Each contract should be checked once with `contract::check_contracts`; then the size of the
+    /// encoding no longer depends on the schemas and nested workflows of those nodes.
+    pub fn with_contracts(graph: &'g WorkflowGraph, context: &'ctx Context) -> Self {
+        Self::build(graph, context, None, &[], Abstraction::Contracts)
+    }
+
+    /// `exports` is a node whose given output keys are used outside of `graph`.
+    /// `observed_keys` are output keys of every node that are used outside of `graph`.
+    /// Panics if `graph` has a cycle, since executions are encoded along a topological order.
+    fn build(
+        graph: &'g WorkflowGraph,
+        context: &'ctx Context,
+        exports: Option<(NodeIdx, &'g [String])>,
+        observed_keys: &[&'g str],
+        abstraction: Abstraction,
+    ) -> Self {
+        if let Some(cycle) = topsort::find_cycle(graph) {
+            let names = cycle
+                .iter()
+                .chain(cycle.first())
+                .map(|i| graph.nodes[*i].name.as_str())
+                .collect::<Vec<_>>();
+            panic!(
+                "the workflow has a cycle {}, but only acyclic workflows can be verified",
+                names.join(" -> ")
+            );
+        }
         // construct node_asts (tests/workflow_graph.rs)
         let mut node_idx_to_ast = HashMap::new();
+        let mut nested_verifiers = HashMap::new();
         topsort::topological_sort_reversed(graph)
             .into_iter()
             .for_each(|node_idx| {
-                let node_ast = NodeAST::new(
+                let mut observed_outputs = observed_keys.to_vec();
+                if let Some((exporting_node, keys)) = exports {
+                    if exporting_node == node_idx {
+                        observed_outputs.extend(keys.iter().map(|s| s.as_str()));
+                    }
+                }
+                let contract = match abstraction {
+                    Abstraction::Contracts => graph.nodes[node_idx].effective_contract(),
+                    _ => None,
+                };
+                let mut node_ast = NodeAST::build(
                     context,
                     &graph.nodes[node_idx],
                     graph,
@@ -41,21 +132,237 @@ impl<'ctx, 'g> GraphVerifier<'ctx, 'g> {
                         .iter()
                         .map(|(child_idx, _)| node_idx_to_ast.get(child_idx).unwrap())
                         .collect::<Vec<_>>(),
+                    &graph.catch_list[node_idx]
+                        .iter()
+                        .map(|(handler_idx, _)| node_idx_to_ast.get(handler_idx).unwrap())
+                        .collect::<Vec<_>>(),
+                    &observed_outputs,
+                    contract,
                 );
+                // the input keys of a nested workflow come from the input of the node.
+                // This must be done before the parents are built, so that they provide these keys.
+                let nested_input_keys = match &graph.nodes[node_idx].kind {
+                    _ if contract.is_some() => vec![],
+                    NodeKind::Map(map_spec) => {
+                        // every input key of an iteration, except the item itself
+                        let iterator = GraphVerifier::build(
+                            &map_spec.iterator,
+                            context,
+                            None,
+                            &[],
+                            abstraction,
+                        );
+                        let keys = iterator.node_asts[map_spec.iterator.start.unwrap()]
+                            .input_keys
+                            .keys()
+                            .filter(|s| **s != map_spec.item_key)
+                            .copied()
+                            .collect();
+                        nested_verifiers.insert(node_idx, iterator);
+                        keys
+                    }
+                    NodeKind::SubWorkflow(module) if abstraction.summarizes(module) => {
+                        module.inputs.iter().map(|s| s.as_str()).collect()
+                    }
+                    NodeKind::SubWorkflow(module) => {
+                        // the declared inputs used by the module
+                        let inlined = GraphVerifier::build(
+                            &module.graph,
+                            context,
+                            Some((module.end, &module.outputs)),
+                            &[],
+                            abstraction,
+                        );
+                        let keys = inlined.node_asts[module.graph.start.unwrap()]
+                            .input_keys
+                            .keys()
+                            .filter(|s| module.inputs.iter().any(|input| input == *s))
+                            .copied()
+                            .collect();
+                        nested_verifiers.insert(node_idx, inlined);
+                        keys
+                    }
+                    _ => vec![],
+                };
+                nested_input_keys.into_iter().for_each(|s| {
+                    node_ast
+                        .input_keys
+                        .entry(s)
+                        .or_insert_with(|| Bool::new_const(context, symbol!()));
+                });
                 node_idx_to_ast.insert(node_idx, node_ast);
             });
-        let node_asts = graph
+        let node_asts: Vec<NodeAST> = graph
             .nodes
             .iter()
             .map(|node| node_idx_to_ast.remove(&node.id).unwrap())
             .collect();
+
+        let (node_constraints, node_failures) = graph
+            .nodes
+            .iter()
+            .map(|node| match &node.kind {
+                // the contract is already encoded by the node AST
+                _ if abstraction == Abstraction::Contracts
+                    && node.effective_contract().is_some() =>
+                {
+                    (
+                        Bool::from_bool(context, true),
+                        Bool::from_bool(context, false),
+                    )
+                }
+                NodeKind::Join => {
+                    // the inputs of a join are the union of the outputs of the incoming branches,
+                    // and a typed input has the value of one of the branches that output it
+                    let predecessors = graph.predecessors(node.id);
+                    let node_ast = &node_asts[node.id];
+                    let union = node_ast
+                        .input_keys
+                        .iter()
+                        .map(|(s, b_in)| {
+                            let producers = predecessors
+                                .iter()
+                                .filter_map(|p| node_asts[*p].output_keys.get(s))
+                                .collect::<Vec<_>>();
+                            let has_value = match node_ast.input_values.get(s) {
+                                Some(value) => {
+                                    let producers = predecessors
+                                        .iter()
+                                        .filter_map(|p| {
+                                            let b_out = node_asts[*p].output_keys.get(s)?;
+                                            let v_out = node_asts[*p].output_values.get(s)?;
+                                            Some(Bool::and(
+                                                context,
+                                                &[b_out, &value.same_as(v_out)],
+                                            ))
+                                        })
+                                        .collect::<Vec<_>>();
+                                    b_in.implies(&Bool::or(
+                                        context,
+                                        &producers.iter().collect::<Vec<_>>(),
+                                    ))
+                                }
+                                None => Bool::from_bool(context, true),
+                            };
+                            Bool::and(
+                                context,
+                                &[&b_in._eq(&Bool::or(context, &producers)), &has_value],
+                            )
+                        })
+                        .collect::<Vec<_>>();
+                    let union = Bool::and(context, &union.iter().collect::<Vec<_>>());
+                    (union.clone(), union.not())
+                }
+                NodeKind::Map(map_spec) => {
+                    // a single iteration stands for every element, since all elements have the same keys
+                    let node_ast = &node_asts[node.id];
+                    nested_verifiers[&node.id].nested_run(
+                        |s| {
+                            if s == map_spec.item_key {
+                                Bool::from_bool(context, true)
+                            } else {
+                                node_ast.input_keys[s].clone()
+                            }
+                        },
+                        map_spec.iterator_end,
+                    )
+                }
+                NodeKind::SubWorkflow(module) if abstraction.summarizes(module) => {
+                    let node_ast = &node_asts[node.id];
+                    let summary = module.summary(summarize_module);
+                    let with_inputs = |inputs: &BTreeSet<String>| {
+                        let matches = module
+                            .inputs
+                            .iter()
+                            .map(|s| {
+                                node_ast.input_keys[s.as_str()]
+                                    ._eq(&Bool::from_bool(context, inputs.contains(s)))
+                            })
+                            .collect::<Vec<_>>();
+                        Bool::and(context, &matches.iter().collect::<Vec<_>>())
+                    };
+                    let completions = summary
+                        .completions
+                        .iter()
+                        .map(|(inputs, outputs)| {
+                            let mut matches = module
+                                .outputs
+                                .iter()
+                                .map(|s| {
+                                    node_ast.exported_keys[s.as_str()]
+                                        ._eq(&Bool::from_bool(context, outputs.contains(s)))
+                                })
+                                .collect::<Vec<_>>();
+                            matches.push(with_inputs(inputs));
+                            Bool::and(context, &matches.iter().collect::<Vec<_>>())
+                        })
+                        .collect::<Vec<_>>();
+                    let failures = summary
+                        .failing_inputs
+                        .iter()
+                        .map(with_inputs)
+                        .collect::<Vec<_>>();
+                    (
+                        Bool::or(context, &completions.iter().collect::<Vec<_>>()),
+                        Bool::or(context, &failures.iter().collect::<Vec<_>>()),
+                    )
+                }
+                NodeKind::SubWorkflow(module) => {
+                    let node_ast = &node_asts[node.id];
+                    let inlined = &nested_verifiers[&node.id];
+                    let end_ast = &inlined.node_asts[module.end];
+                    // keys that are not declared inputs are not passed to the module
+                    let (run_completes, run_fails) = inlined.nested_run(
+                        |s| match node_ast.input_keys.get(s) {
+                            Some(b_in) if module.inputs.iter().any(|input| input == s) => {
+                                b_in.clone()
+                            }
+                            _ => Bool::from_bool(context, false),
+                        },
+                        module.end,
+                    );
+                    let exports = module
+                        .outputs
+                        .iter()
+                        .map(|s| {
+                            node_ast.exported_keys[s.as_str()]._eq(&end_ast.output_keys[s.as_str()])
+                        })
+                        .collect::<Vec<_>>();
+                    let run_completes = Bool::and(
+                        context,
+                        &[
+                            &run_completes,
+                            &Bool::and(context, &exports.iter().collect::<Vec<_>>()),
+                        ],
+                    );
+                    (run_completes, run_fails)
+                }
+                NodeKind::Task | NodeKind::Fork => (
+                    Bool::from_bool(context, true),
+                    Bool::from_bool(context, false),
+                ),
+            })
+            .unzip();
+
         Self {
             context,
             graph,
             node_asts,
+            node_constraints,
+            node_failures,
         }
     }
 
+    /// All schema constraints, which should always be satisfied.
+    fn schema_constraints(&self) -> Bool<'ctx> {
+        let constraints = self
+            .node_asts
+            .iter()
+            .map(|node_ast| Self::aggregate_schema_constraints(node_ast, self.context))
+            .collect::<Vec<_>>();
+        Bool::and(self.context, &constraints.iter().collect::<Vec<_>>())
+    }
+
     fn aggregate_schema_constraints(
         node_ast: &NodeAST<'ctx, '_>,
         context: &'ctx Context,
@@ -66,70 +373,253 @@ impl<'ctx, 'g> GraphVerifier<'ctx, 'g> {
         )
     }
 
-    /// return value: Key: Node Index, Value: (incoming constraint bool, outgoing constraint bool)
-    fn get_in_out_transition_constraints(&self) -> HashMap<NodeIdx, (Bool<'ctx>, Bool<'ctx>)> {
-        // value: (incoming constraints, outgoing constraints)
-        let mut in_out_transition_constraints: HashMap<
-            usize,
-            (Vec<&Bool<'_>>, Vec<&Bool<'_>>),
-            RandomState,
-        > = HashMap::from_iter(
-            self.graph
-                .nodes
-                .iter()
-                .map(|node| (node.id, (vec![], vec![]))),
+    /// Encode a run of this nested workflow until `end`, where `passed(s)` is whether the caller passes key `s`.
+    /// Returns whether the run may complete, and whether it may stop before `end`.
+    /// A run stops right away if the start node misses a required input.
+    fn nested_run(
+        &self,
+        passed: impl Fn(&str) -> Bool<'ctx>,
+        end: NodeIdx,
+    ) -> (Bool<'ctx>, Bool<'ctx>) {
+        let start = self.graph.start.unwrap();
+        let required_inputs = &self.graph.nodes[start].required_inputs;
+        let mut binding = vec![self.schema_constraints()];
+        let mut ready = vec![];
+        self.node_asts[start].input_keys.iter().for_each(|(s, b)| {
+            // required inputs of the start node are always true in its encoding
+            if required_inputs.iter().any(|r| r == s) {
+                ready.push(passed(s));
+            } else {
+                binding.push(b._eq(&passed(s)));
+            }
+        });
+        let binding = Bool::and(self.context, &binding.iter().collect::<Vec<_>>());
+        let ready = Bool::and(self.context, &ready.iter().collect::<Vec<_>>());
+        let completes = Bool::and(
+            self.context,
+            &[
+                &binding,
+                &ready,
+                &self.get_reached_constraints(&[]).reached[end],
+            ],
         );
+        let fails = Bool::and(
+            self.context,
+            &[
+                &binding,
+                &Bool::or(
+                    self.context,
+                    &[&ready.not(), &self.avoidance_constraints(&[end])],
+                ),
+            ],
+        );
+        (completes, fails)
+    }
 
-        self.node_asts.iter().for_each(|node_ast| {
-            node_ast
-                .transition_constraints
-                .iter()
-                .enumerate()
-                .for_each(|(child_idx, bool)| {
-                    let child_id = self.graph.adj_list[node_ast.node.id][child_idx].0;
-                    // edge direction: node_ast.node.id -> child_id
-                    in_out_transition_constraints
-                        .get_mut(&child_id)
-                        .unwrap()
-                        .0 // incoming
-                        .push(bool);
-                    in_out_transition_constraints
-                        .get_mut(&node_ast.node.id)
-                        .unwrap()
-                        .1 // outgoing
-                        .push(bool);
-                })
-        });
+    /// Encode all executions from the start node.
+    /// A join is entered if all of its incoming edges are taken, any other node if one of them is taken.
+    /// A fork takes all of its outgoing edges, any other node takes the one chosen by a fresh variable.
+    /// A node that fails takes the catch edge of its failure instead.
+    /// Nodes in `avoided_nodes` are never entered, so executions stop right before them.
+    fn get_reached_constraints(&self, avoided_nodes: &[NodeIdx]) -> Executions<'ctx> {
+        self.get_reached_constraints_with_broken(avoided_nodes, &[])
+    }
 
-        let constraint_bools_iter =
-            in_out_transition_constraints
-                .iter()
-                .map(|(&node_idx, (incoming, outgoing))| {
-                    let incoming_constraint = Bool::or(self.context, &incoming);
-                    let outgoing_constraint = Bool::or(self.context, &outgoing);
-                    (node_idx, (incoming_constraint, outgoing_constraint))
+    /// Like `get_reached_constraints`, but node i completes whenever `broken[i]` is true, whatever its nested
+    /// workflow does. `broken` is either empty or has one boolean per node.
+    fn get_reached_constraints_with_broken(
+        &self,
+        avoided_nodes: &[NodeIdx],
+        broken: &[Bool<'ctx>],
+    ) -> Executions<'ctx> {
+        let start_node = self.graph.start.unwrap();
+        let mut entered = vec![Bool::from_bool(self.context, false); self.graph.nodes.len()];
+        let mut reached = vec![Bool::from_bool(self.context, false); self.graph.nodes.len()];
+        let mut taken = vec![vec![]; self.graph.nodes.len()];
+        let mut taken_incoming_edges = vec![vec![]; self.graph.nodes.len()];
+        topsort::topological_sort_reversed(self.graph)
+            .into_iter()
+            .rev()
+            .for_each(|node_idx| {
+                let incoming = taken_incoming_edges[node_idx].iter().collect::<Vec<_>>();
+                let node_entered = if avoided_nodes.contains(&node_idx) {
+                    Bool::from_bool(self.context, false)
+                } else if node_idx == start_node {
+                    Bool::from_bool(self.context, true)
+                } else {
+                    match self.graph.nodes[node_idx].kind {
+                        NodeKind::Join => Bool::and(self.context, &incoming),
+                        _ => Bool::or(self.context, &incoming),
+                    }
+                };
+                let completes = match broken.get(node_idx) {
+                    Some(b) => Bool::or(self.context, &[&self.node_constraints[node_idx], b]),
+                    None => self.node_constraints[node_idx].clone(),
+                };
+                let node_reached = Bool::and(self.context, &[&node_entered, &completes]);
+                let node_ast = &self.node_asts[node_idx];
+                let succeeds = Bool::and(self.context, &[&node_reached, &node_ast.fails().not()]);
+                let choice = Int::new_const(self.context, symbol!());
+                // edge direction: node_idx -> child_id
+                let success_edges =
+                    node_ast
+                        .transition_constraints
+                        .iter()
+                        .enumerate()
+                        .map(|(child_idx, bool)| {
+                            let chosen = match self.graph.nodes[node_idx].kind {
+                                NodeKind::Fork => Bool::from_bool(self.context, true),
+                                _ => choice._eq(&Int::from_u64(self.context, child_idx as u64)),
+                            };
+                            let child_id = self.graph.adj_list[node_idx][child_idx].0;
+                            (
+                                child_id,
+                                Bool::and(self.context, &[&succeeds, bool, &chosen]),
+                            )
+                        });
+                let catch_edges =
+                    node_ast
+                        .catch_constraints
+                        .iter()
+                        .enumerate()
+                        .map(|(catch_idx, bool)| {
+                            let handler_id = self.graph.catch_list[node_idx][catch_idx].0;
+                            (handler_id, Bool::and(self.context, &[&node_reached, bool]))
+                        });
+                taken[node_idx] = success_edges.chain(catch_edges).collect::<Vec<_>>();
+                taken[node_idx].iter().for_each(|(child_id, bool)| {
+                    taken_incoming_edges[*child_id].push(bool.clone());
                 });
+                entered[node_idx] = node_entered;
+                reached[node_idx] = node_reached;
+            });
+        Executions {
+            entered,
+            reached,
+            taken,
+        }
+    }
 
-        HashMap::from_iter(constraint_bools_iter)
+    /// Whether a node with outputs `output_keys` can move on to `child_id` along an edge with `conds`,
+    /// i.e., the outputs contain all required inputs of the child and satisfy every condition.
+    /// Conditions on the values of typed keys are checked against `output_values`.
+    /// A branch can always finish into a join; whether the join can run is decided by the join itself.
+    fn can_take_edge(
+        &self,
+        output_keys: &HashMap<&'g str, Bool<'ctx>>,
+        output_values: &HashMap<&'g str, ValueAST<'ctx>>,
+        child_id: NodeIdx,
+        conds: &[InputCond],
+    ) -> Bool<'ctx> {
+        let required_inputs = match self.graph.nodes[child_id].kind {
+            NodeKind::Join => &[][..],
+            _ => &self.graph.nodes[child_id].required_inputs[..],
+        };
+        let cond_keys = conds.iter().filter_map(|cond| match cond {
+            InputCond::Always => None,
+            InputCond::MatchesKey(s)
+            | InputCond::MatchesKeyValue(s, _)
+            | InputCond::Compare(s, _, _) => Some(s),
+        });
+        let value_conds = conds.iter().filter_map(|cond| match cond {
+            InputCond::MatchesKeyValue(s, value) => {
+                output_values.get(s.as_str()).map(|v| v.matches(value))
+            }
+            InputCond::Compare(s, op, value) => {
+                output_values.get(s.as_str()).map(|v| v.compare(*op, value))
+            }
+            _ => None,
+        });
+        let needed = required_inputs
+            .iter()
+            .chain(cond_keys)
+            .map(|s| output_keys[s.as_str()].clone())
+            .chain(value_conds)
+            .collect::<Vec<_>>();
+        Bool::and(self.context, &needed.iter().collect::<Vec<_>>())
+    }
+
+    /// Whether the `failure_idx`-th failure of `node_idx` is caught by a handler that can run.
+    fn is_handled(&self, node_idx: NodeIdx, failure_idx: usize) -> Bool<'ctx> {
+        let error = &self.graph.nodes[node_idx].failures[failure_idx].error;
+        match self.graph.catch_for(node_idx, error) {
+            Some(catch_idx) => self.can_take_edge(
+                &self.node_asts[node_idx].failure_output_keys[failure_idx],
+                &self.node_asts[node_idx].failure_output_values[failure_idx],
+                self.graph.catch_list[node_idx][catch_idx].0,
+                &[],
+            ),
+            None => Bool::from_bool(self.context, false),
+        }
+    }
+
+    /// Whether an execution that enters `node_idx` stops there.
+    /// A fork stops if any of its branches cannot start, any other node stops if it cannot take any outgoing edge.
+    /// A node that fails stops if its failure is not handled.
+    fn is_stuck(&self, node_idx: NodeIdx) -> Bool<'ctx> {
+        self.is_stuck_with_broken(node_idx, None)
+    }
+
+    /// Like `is_stuck`, but the nested workflow of the node does not run if `broken` is true.
+    fn is_stuck_with_broken(&self, node_idx: NodeIdx, broken: Option<&Bool<'ctx>>) -> Bool<'ctx> {
+        let node_ast = &self.node_asts[node_idx];
+        let cannot_take_edges = self.graph.adj_list[node_idx]
+            .iter()
+            .map(|(child_id, conds)| {
+                self.can_take_edge(
+                    &node_ast.output_keys,
+                    &node_ast.output_values,
+                    *child_id,
+                    conds,
+                )
+                .not()
+            })
+            .collect::<Vec<_>>();
+        let cannot_take_edges = cannot_take_edges.iter().collect::<Vec<_>>();
+        let cannot_continue = match self.graph.nodes[node_idx].kind {
+            NodeKind::Fork if !cannot_take_edges.is_empty() => {
+                Bool::or(self.context, &cannot_take_edges)
+            }
+            _ => Bool::and(self.context, &cannot_take_edges),
+        };
+        let cannot_continue = Bool::and(self.context, &[&node_ast.fails().not(), &cannot_continue]);
+        let unhandled_failures = node_ast
+            .failures
+            .iter()
+            .enumerate()
+            .map(|(failure_idx, fails)| {
+                Bool::and(
+                    self.context,
+                    &[fails, &self.is_handled(node_idx, failure_idx).not()],
+                )
+            })
+            .collect::<Vec<_>>();
+        Bool::or(
+            self.context,
+            &[
+                &match broken {
+                    Some(b) => Bool::and(self.context, &[&self.node_failures[node_idx], &b.not()]),
+                    None => self.node_failures[node_idx].clone(),
+                },
+                &cannot_continue,
+                &Bool::or(self.context, &unhandled_failures.iter().collect::<Vec<_>>()),
+            ],
+        )
     }
 
-    /// result[i] contains all children j of i s.t. the transition from i to j is true
-    fn build_graph_from_model(&self, model: &Model<'ctx>) -> Vec<Vec<NodeIdx>> {
-        self.node_asts
+    /// result[i] contains all children j of i s.t. the transition from i to j is taken
+    fn build_graph_from_model(
+        &self,
+        model: &Model<'ctx>,
+        taken: &[Vec<(NodeIdx, Bool<'ctx>)>],
+    ) -> Vec<Vec<NodeIdx>> {
+        taken
             .iter()
-            .map(|node_ast| {
-                node_ast
-                    .transition_constraints
+            .map(|edges| {
+                edges
                     .iter()
-                    .enumerate()
-                    .map(|(child_idx, bool)| {
-                        (
-                            self.graph.adj_list[node_ast.node.id][child_idx].0,
-                            model.eval(bool, true).unwrap().as_bool().unwrap(),
-                        )
-                    })
-                    .filter(|(_, bool)| *bool)
-                    .map(|(child_id, _)| child_id)
+                    .filter(|(_, bool)| model.eval(bool, true).unwrap().as_bool().unwrap())
+                    .map(|(child_id, _)| *child_id)
                     .collect()
             })
             .collect()
@@ -137,7 +627,7 @@ impl<'ctx, 'g> GraphVerifier<'ctx, 'g> {
 
     fn find_path_by_bfs(
         &self,
-        graph: &Vec<Vec<NodeIdx>>,
+        graph: &[Vec<NodeIdx>],
         target_node: NodeIdx,
     ) -> Option<Vec<NodeIdx>> {
         let mut visited = HashSet::new();
@@ -174,119 +664,181 @@ impl<'ctx, 'g> GraphVerifier<'ctx, 'g> {
         }
     }
 
-    pub fn is_reachable(&self, target_node: NodeIdx) -> Option<(Vec<ExecutionModel>, Model)> {
-        let solver = Solver::new(&self.context);
-        self.is_reachable_with_solver(target_node, &solver)
-    }
-
-    pub fn is_reachable_with_solver(
+    /// Assert that `target_node` is reached by an execution from the start node.
+    fn assert_reachability_constraints(
         &self,
         target_node: NodeIdx,
         solver: &Solver<'ctx>,
-    ) -> Option<(Vec<ExecutionModel>, Model)> {
+    ) -> Executions<'ctx> {
         // enforce all schema constraints
-        self.node_asts.iter().for_each(|node_ast| {
-            solver.assert(&Self::aggregate_schema_constraints(node_ast, self.context))
-        });
+        solver.assert(&self.schema_constraints());
 
         // enforce all transition constraints
-        let mut node_idx_to_transition_constraints = self.get_in_out_transition_constraints();
-        node_idx_to_transition_constraints
-            .get_mut(&target_node)
-            .unwrap()
-            .1 = Bool::from_bool(self.context, true); // clear the outgoing constraint for target node
-        node_idx_to_transition_constraints
-            .get_mut(&self.graph.start.unwrap())
-            .unwrap()
-            .0 = Bool::from_bool(self.context, true);
-        let transition_constraits_bools = node_idx_to_transition_constraints
+        let executions = self.get_reached_constraints(&[]);
+        solver.assert(&executions.reached[target_node]);
+        executions
+    }
+
+    /// Satisfied iff the executions only stop at nodes that cannot continue.
+    fn maximal_execution_constraints(&self, executions: &Executions<'ctx>) -> Bool<'ctx> {
+        let constraints = (0..self.graph.nodes.len())
+            .map(|i| {
+                let taken = executions.taken[i]
+                    .iter()
+                    .map(|(_, taken)| taken)
+                    .collect::<Vec<_>>();
+                let continues = Bool::or(self.context, &taken);
+                executions.entered[i]
+                    .implies(&Bool::or(self.context, &[&continues, &self.is_stuck(i)]))
+            })
+            .collect::<Vec<_>>();
+        Bool::and(self.context, &constraints.iter().collect::<Vec<_>>())
+    }
+
+    /// Satisfied iff an execution from the start node stops without reaching any of `target_nodes`.
+    fn avoidance_constraints(&self, target_nodes: &[NodeIdx]) -> Bool<'ctx> {
+        self.avoidance_constraints_with_broken(target_nodes, &[])
+    }
+
+    /// Like `avoidance_constraints`, with the nodes in `broken` as in `get_reached_constraints_with_broken`.
+    fn avoidance_constraints_with_broken(
+        &self,
+        target_nodes: &[NodeIdx],
+        broken: &[Bool<'ctx>],
+    ) -> Bool<'ctx> {
+        // enforce all transition constraints, cut at the target nodes
+        let entered = self
+            .get_reached_constraints_with_broken(target_nodes, broken)
+            .entered;
+        let stuck = self
+            .graph
+            .nodes
             .iter()
-            .map(|(_, (incoming, outgoing))| incoming.implies(outgoing))
+            .filter(|node| !target_nodes.contains(&node.id))
+            .map(|node| {
+                Bool::and(
+                    self.context,
+                    &[
+                        &entered[node.id],
+                        &self.is_stuck_with_broken(node.id, broken.get(node.id)),
+                    ],
+                )
+            })
             .collect::<Vec<_>>();
-        let transition_constraits_bools = transition_constraits_bools.iter().collect::<Vec<_>>();
-        solver.assert(&Bool::and(self.context, &transition_constraits_bools));
-        solver.assert(
-            &node_idx_to_transition_constraints
-                .get(&target_node)
-                .unwrap()
-                .0, // incoming
-        );
+        Bool::or(self.context, &stuck.iter().collect::<Vec<_>>())
+    }
+
+    /// Assert that an execution from the start node stops without reaching any of `target_nodes`.
+    fn assert_avoidance_constraints(&self, target_nodes: &[NodeIdx], solver: &Solver<'ctx>) {
+        // enforce all schema constraints
+        solver.assert(&self.schema_constraints());
+
+        solver.assert(&self.avoidance_constraints(target_nodes));
+    }
+
+    pub fn is_reachable(&self, target_node: NodeIdx) -> Option<(Vec<ExecutionModel>, Model<'ctx>)> {
+        let solver = Solver::new(self.context);
+        self.is_reachable_with_solver(target_node, &solver)
+    }
 
-        let res = solver.check();
+    pub fn is_reachable_with_solver(
+        &self,
+        target_node: NodeIdx,
+        solver: &Solver<'ctx>,
+    ) -> Option<(Vec<ExecutionModel>, Model<'ctx>)> {
+        let executions = self.assert_reachability_constraints(target_node, solver);
+        self.reachable_model(target_node, &executions, solver)
+    }
 
-        match res {
+    /// An execution reaching `target_node`, if the constraints on `solver`, including those of `executions`,
+    /// can be satisfied.
+    fn reachable_model(
+        &self,
+        target_node: NodeIdx,
+        executions: &Executions<'ctx>,
+        solver: &Solver<'ctx>,
+    ) -> Option<(Vec<ExecutionModel>, Model<'ctx>)> {
+        match solver.check() {
             SatResult::Sat => {
                 let model = solver.get_model().unwrap();
-                let reachable_graph = self.build_graph_from_model(&model);
+                let reachable_graph = self.build_graph_from_model(&model, &executions.taken);
                 let execution_path_by_idx = self
                     .find_path_by_bfs(&reachable_graph, target_node)
                     .unwrap();
-                let execution_path_by_name = execution_path_by_idx
+                let execution_models = execution_path_by_idx
                     .iter()
-                    .map(|idx| &self.graph.nodes[*idx].name)
-                    .collect::<Vec<_>>();
-                // println!("Execution path: {:?}", execution_path_by_name);
-                Some((vec![], model))
+                    .map(|idx| self.execution_model(*idx, &model))
+                    .collect();
+                Some((execution_models, model))
             }
             SatResult::Unsat => None,
             SatResult::Unknown => panic!("unknown!"),
         }
     }
 
-    fn count_input_set(&self) -> Int<'ctx> {
-        let input_as_int = self.node_asts[self.graph.start.unwrap()]
-            .input_keys
+    fn execution_model(&self, node_idx: NodeIdx, model: &Model<'ctx>) -> ExecutionModel {
+        let true_keys = |keys: HashMap<String, bool>| {
+            let mut keys = keys
+                .into_iter()
+                .filter(|(_, v)| *v)
+                .map(|(k, _)| k)
+                .collect::<Vec<_>>();
+            keys.sort();
+            keys
+        };
+        let node_ast = &self.node_asts[node_idx];
+        let failure_idx = node_ast
+            .failures
             .iter()
-            .map(|(_, v)| {
-                v.ite(
-                    &Int::from_i64(self.context, 1),
-                    &Int::from_i64(self.context, 0),
-                )
-            })
-            .collect::<Vec<_>>();
+            .position(|b| model.eval(b, true).unwrap().as_bool().unwrap());
+        let output_keys = match failure_idx {
+            Some(i) => NodeAST::eval_keys(node_ast.failure_output_keys[i].clone(), model),
+            None => node_ast.eval_output_keys(model),
+        };
+        ExecutionModel {
+            node_idx,
+            input_keys: true_keys(node_ast.eval_input_keys(model)),
+            output_keys: true_keys(output_keys),
+            error: failure_idx.map(|i| self.graph.nodes[node_idx].failures[i].error.clone()),
+        }
+    }
+
+    fn count_input_set(&self) -> Int<'ctx> {
+        // the sum starts at zero, since z3 cannot add an empty list
+        let mut input_as_int = vec![Int::from_i64(self.context, 0)];
+        input_as_int.extend(
+            self.node_asts[self.graph.start.unwrap()]
+                .input_keys
+                .values()
+                .map(|v| {
+                    v.ite(
+                        &Int::from_i64(self.context, 1),
+                        &Int::from_i64(self.context, 0),
+                    )
+                }),
+        );
         Int::add(self.context, &input_as_int.iter().collect::<Vec<_>>())
     }
 
+    /// Number of input keys of the start node, including its required inputs.
+    fn num_start_input_keys(&self) -> usize {
+        self.node_asts[self.graph.start.unwrap()].input_keys.len()
+    }
+
     fn try_minimum_input_set_for_reachable(
         &self,
         target_node: NodeIdx,
-        input_set_size: usize,
+        max_input_set_size: usize,
     ) -> Option<(Vec<String>, Vec<ExecutionModel>)> {
-        let solver = Solver::new(&self.context);
+        let solver = Solver::new(self.context);
 
         // enforce input set size
-        solver.assert(&self.count_input_set()._eq(&Int::from_i64(
+        solver.assert(&self.count_input_set().le(&Int::from_i64(
             self.context,
-            input_set_size.try_into().unwrap(),
+            max_input_set_size.try_into().unwrap(),
         )));
 
-        // enforce all schema constraints
-        self.node_asts.iter().for_each(|node_ast| {
-            solver.assert(&Self::aggregate_schema_constraints(node_ast, self.context))
-        });
-
-        // enforce all transition constraints
-        let mut node_idx_to_transition_constraints = self.get_in_out_transition_constraints();
-        node_idx_to_transition_constraints
-            .get_mut(&target_node)
-            .unwrap()
-            .1 = Bool::from_bool(self.context, true); // clear the outgoing constraint for target node
-        node_idx_to_transition_constraints
-            .get_mut(&self.graph.start.unwrap())
-            .unwrap()
-            .0 = Bool::from_bool(self.context, true);
-        let transition_constraits_bools = node_idx_to_transition_constraints
-            .iter()
-            .map(|(_, (incoming, outgoing))| incoming.implies(outgoing))
-            .collect::<Vec<_>>();
-        let transition_constraits_bools = transition_constraits_bools.iter().collect::<Vec<_>>();
-        solver.assert(&Bool::and(self.context, &transition_constraits_bools));
-        solver.assert(
-            &node_idx_to_transition_constraints
-                .get(&target_node)
-                .unwrap()
-                .0, // incoming
-        );
+        self.assert_reachability_constraints(target_node, &solver);
 
         match solver.check() {
             SatResult::Sat => {
@@ -317,154 +869,355 @@ impl<'ctx, 'g> GraphVerifier<'ctx, 'g> {
 
         // binary search
         let mut left = 0;
-        let mut right = self.node_asts[self.graph.start.unwrap()].input_keys.len();
-        let mut cur_res = None;
+        let mut right = self.num_start_input_keys();
+        let mut cur_res = self.try_minimum_input_set_for_reachable(target_node, right)?;
         while left < right {
-            let mid = (left + right) - 1;
+            let mid = (left + right) / 2;
             if let Some(res) = self.try_minimum_input_set_for_reachable(target_node, mid) {
                 right = mid;
-                cur_res = Some(res);
+                cur_res = res;
             } else {
                 left = mid + 1;
             }
         }
-        cur_res
+        Some(cur_res)
     }
 
     /// Check whether we can start from the start node and can eventually reach any of the target_node in all scenarios.
     pub fn can_eventually_reach(&self, target_nodes: &[NodeIdx]) -> bool {
-        let mut conjunctive_clauses = vec![];
+        // look for an execution that stops without reaching any target
+        let solver = Solver::new(self.context);
+        self.assert_avoidance_constraints(target_nodes, &solver);
 
-        // enforce all schema constraints
-        self.node_asts.iter().for_each(|node_ast| {
-            conjunctive_clauses.push(Self::aggregate_schema_constraints(node_ast, self.context));
-        });
+        match solver.check() {
+            SatResult::Sat => false,
+            SatResult::Unsat => true,
+            SatResult::Unknown => panic!("unknown!"),
+        }
+    }
 
-        // enforce all transition constraints
-        let mut node_idx_to_transition_constraints = self.get_in_out_transition_constraints();
-        target_nodes.iter().for_each(|target_node| {
-            node_idx_to_transition_constraints
-                .get_mut(target_node)
-                .unwrap()
-                .1 = Bool::from_bool(self.context, true); // clear the outgoing constraint for target node
-        });
-        node_idx_to_transition_constraints
-            .get_mut(&self.graph.start.unwrap())
-            .unwrap()
-            .0 = Bool::from_bool(self.context, true);
-        let mut transition_constraits_bools = node_idx_to_transition_constraints
+    /// Constraints of a skipped node: it does not fail, and only outputs the input keys that its output schema
+    /// carries, under the same conditions.
+    fn skip_constraints(&self, node_idx: NodeIdx) -> Bool<'ctx> {
+        let node_ast = &self.node_asts[node_idx];
+        let output_schema = &self.graph.nodes[node_idx].output_schema;
+        let carried = OutputSchema {
+            fixed_keys: vec![],
+            may_keys: vec![],
+            dynamic_keys: output_schema
+                .dynamic_keys
+                .iter()
+                .filter(|(rule, _)| !matches!(rule, KeyRule::Fixed(_)))
+                .cloned()
+                .collect(),
+            value_specs: BTreeMap::new(),
+        };
+        let mut input_keys = node_ast
+            .input_keys
             .iter()
-            .map(|(_, (incoming, outgoing))| incoming.implies(outgoing))
-            .collect::<Vec<_>>();
-        conjunctive_clauses.append(&mut transition_constraits_bools);
+            .map(|(s, b)| (*s, b.clone()))
+            .collect::<HashMap<&str, _>>();
+        let output_keys = node_ast
+            .output_keys
+            .iter()
+            .map(|(s, b)| (*s, b.clone()))
+            .collect::<HashMap<&str, _>>();
+        let mut constraints = ast::output_schema_constraints(
+            self.context,
+            &carried,
+            &HashMap::new(),
+            &mut input_keys,
+            &node_ast.input_values,
+            &output_keys,
+            &node_ast.output_values,
+        );
+        constraints.push(node_ast.fails().not());
+        Bool::and(self.context, &constraints.iter().collect::<Vec<_>>())
+    }
+
+    /// The nodes that may fail, so that they are skipped:
+    /// every node except the start node and `target_nodes`, in the order of `nodes`.
+    fn breakable_nodes(&self, target_nodes: &[NodeIdx]) -> Vec<NodeIdx> {
+        (0..self.graph.nodes.len())
+            .filter(|i| Some(*i) != self.graph.start && !target_nodes.contains(i))
+            .collect()
+    }
 
-        let reach_target = target_nodes
+    /// Look for at most `k` breakable nodes such that, when they are skipped, an execution may stop without
+    /// reaching any of `target_nodes`.
+    fn try_failure_set(&self, target_nodes: &[NodeIdx], k: usize) -> Option<Vec<NodeIdx>> {
+        let breakable = self.breakable_nodes(target_nodes);
+        let broken = (0..self.graph.nodes.len())
+            .map(|i| match breakable.contains(&i) {
+                true => Bool::new_const(self.context, symbol!()),
+                false => Bool::from_bool(self.context, false),
+            })
+            .collect::<Vec<_>>();
+        let num_broken = broken
             .iter()
-            .map(|target_node| {
-                &node_idx_to_transition_constraints
-                    .get(target_node)
-                    .unwrap()
-                    .0
+            .map(|b| {
+                b.ite(
+                    &Int::from_i64(self.context, 1),
+                    &Int::from_i64(self.context, 0),
+                )
             })
             .collect::<Vec<_>>();
-        conjunctive_clauses.push(Bool::or(self.context, &reach_target));
-
-        let solver = Solver::new(&self.context);
-        solver.assert(
-            &Bool::and(
-                self.context,
-                &conjunctive_clauses.iter().collect::<Vec<_>>(),
-            )
-            .not(),
-        );
+        let num_broken = Int::add(self.context, &num_broken.iter().collect::<Vec<_>>());
 
+        let solver = Solver::new(self.context);
+        broken.iter().enumerate().for_each(|(i, b)| {
+            let node_constraints =
+                Self::aggregate_schema_constraints(&self.node_asts[i], self.context);
+            solver.assert(&b.ite(&self.skip_constraints(i), &node_constraints));
+        });
+        solver.assert(&self.avoidance_constraints_with_broken(target_nodes, &broken));
+        solver.assert(&num_broken.le(&Int::from_u64(self.context, k as u64)));
         match solver.check() {
-            SatResult::Sat => false,
-            SatResult::Unsat => true,
+            SatResult::Sat => {
+                let model = solver.get_model().unwrap();
+                let failed = breakable
+                    .into_iter()
+                    .filter(|i| model.eval(&broken[*i], true).unwrap().as_bool().unwrap())
+                    .collect();
+                Some(failed)
+            }
+            SatResult::Unsat => None,
             SatResult::Unknown => panic!("unknown!"),
         }
     }
 
-    fn try_minimum_input_set_for_can_eventually_reach(
+    /// Check whether `target_nodes` can still eventually be reached in all scenarios when any `k` nodes,
+    /// other than the start node and the targets, fail. A failed node is skipped: the execution goes on, but the
+    /// node only outputs the input keys it carries, and none of the keys it produces.
+    pub fn is_robust(&self, target_nodes: &[NodeIdx], k: usize) -> bool {
+        self.try_failure_set(target_nodes, k).is_none()
+    }
+
+    /// Smallest set of nodes, other than the start node and the targets, whose failure (as in `is_robust`) breaks
+    /// eventual reachability of `target_nodes`. It is empty if eventual reachability already does not hold, and `None` if
+    /// no failures break it.
+    pub fn minimum_failure_set(&self, target_nodes: &[NodeIdx]) -> Option<Vec<NodeIdx>> {
+        let max_size = self.breakable_nodes(target_nodes).len();
+        (0..=max_size).find_map(|k| self.try_failure_set(target_nodes, k))
+    }
+
+    /// Minimum number of user provided input keys with which every execution eventually reaches one of
+    /// `target_nodes`, or `None` if no input set does.
+    pub fn minimum_input_set_for_can_eventually_reach(
         &self,
         target_nodes: &[NodeIdx],
-        input_set_size: usize,
-    ) -> bool {
-        let mut conjunctive_clauses = vec![];
+    ) -> Option<usize> {
+        // candidate input sets are tried from the smallest ones, and a candidate is ruled out for good as soon as
+        // one of its executions avoids the targets
+        let input_keys = &self.node_asts[self.graph.start.unwrap()].input_keys;
+        let candidates = Solver::new(self.context);
+        let solver = Solver::new(self.context);
+        self.assert_avoidance_constraints(target_nodes, &solver);
+        (0..=self.num_start_input_keys()).find(|input_set_size| {
+            let has_size = Bool::new_const(self.context, symbol!());
+            candidates.assert(
+                &has_size.implies(&self.count_input_set()._eq(&Int::from_i64(
+                    self.context,
+                    (*input_set_size).try_into().unwrap(),
+                ))),
+            );
+            loop {
+                match candidates.check_assumptions(std::slice::from_ref(&has_size)) {
+                    SatResult::Sat => {}
+                    SatResult::Unsat => return false,
+                    SatResult::Unknown => panic!("unknown!"),
+                }
+                let model = candidates.get_model().unwrap();
+                let candidate = input_keys
+                    .values()
+                    .map(|b| b._eq(&model.eval(b, true).unwrap()))
+                    .collect::<Vec<_>>();
+                let candidate = Bool::and(self.context, &candidate.iter().collect::<Vec<_>>());
+                solver.push();
+                solver.assert(&candidate);
+                let avoided = solver.check();
+                solver.pop(1);
+                match avoided {
+                    SatResult::Sat => candidates.assert(&candidate.not()),
+                    SatResult::Unsat => return true,
+                    SatResult::Unknown => panic!("unknown!"),
+                }
+            }
+        })
+    }
 
-        // enforce all schema constraints
-        self.node_asts.iter().for_each(|node_ast| {
-            conjunctive_clauses.push(Self::aggregate_schema_constraints(node_ast, self.context));
-        });
+    /// Input keys of `join_node` that may be produced by more than one of its incoming branches
+    /// in an execution that reaches `join_node`. The merged value of such a key is ambiguous.
+    pub fn join_conflicts(&self, join_node: NodeIdx) -> Vec<String> {
+        let predecessors = self.graph.predecessors(join_node);
+        let mut conflicts = self.node_asts[join_node]
+            .input_keys
+            .keys()
+            .filter(|s| {
+                let producers = predecessors
+                    .iter()
+                    .filter_map(|p| self.node_asts[*p].output_keys.get(*s))
+                    .collect::<Vec<_>>();
+                let produced_twice = producers
+                    .iter()
+                    .enumerate()
+                    .flat_map(|(i, b1)| {
+                        producers[i + 1..]
+                            .iter()
+                            .map(|b2| Bool::and(self.context, &[*b1, *b2]))
+                    })
+                    .collect::<Vec<_>>();
+                if produced_twice.is_empty() {
+                    return false;
+                }
+                let solver = Solver::new(self.context);
+                self.assert_reachability_constraints(join_node, &solver);
+                solver.assert(&Bool::or(
+                    self.context,
+                    &produced_twice.iter().collect::<Vec<_>>(),
+                ));
+                match solver.check() {
+                    SatResult::Sat => true,
+                    SatResult::Unsat => false,
+                    SatResult::Unknown => panic!("unknown!"),
+                }
+            })
+            .map(|s| s.to_string())
+            .collect::<Vec<_>>();
+        conflicts.sort();
+        conflicts
+    }
 
-        // enforce all transition constraints
-        let mut node_idx_to_transition_constraints = self.get_in_out_transition_constraints();
-        target_nodes.iter().for_each(|target_node| {
-            node_idx_to_transition_constraints
-                .get_mut(target_node)
-                .unwrap()
-                .1 = Bool::from_bool(self.context, true); // clear the outgoing constraint for target node
-        });
-        node_idx_to_transition_constraints
-            .get_mut(&self.graph.start.unwrap())
-            .unwrap()
-            .0 = Bool::from_bool(self.context, true);
-        let mut transition_constraits_bools = node_idx_to_transition_constraints
+    /// Check whether `target_node` can be reached by an execution in which `failing_node` fails
+    /// with `error`, or with any of its failures if `error` is `None`.
+    pub fn is_reachable_on_failure(
+        &self,
+        target_node: NodeIdx,
+        failing_node: NodeIdx,
+        error: Option<&str>,
+    ) -> Option<(Vec<ExecutionModel>, Model<'ctx>)> {
+        let solver = Solver::new(self.context);
+        let node_ast = &self.node_asts[failing_node];
+        let failures = node_ast
+            .failures
             .iter()
-            .map(|(_, (incoming, outgoing))| incoming.implies(outgoing))
+            .zip(&self.graph.nodes[failing_node].failures)
+            .filter(|(_, failure)| match error {
+                Some(e) => failure.error == e,
+                None => true,
+            })
+            .map(|(b, _)| b)
             .collect::<Vec<_>>();
-        conjunctive_clauses.append(&mut transition_constraits_bools);
+        // the failure and the target must be reached by the same execution
+        let executions = self.assert_reachability_constraints(target_node, &solver);
+        solver.assert(&Bool::or(self.context, &failures));
+        solver.assert(&executions.reached[failing_node]);
+        self.reachable_model(target_node, &executions, &solver)
+    }
 
-        let reach_target = target_nodes
+    /// Failures that can happen and are not caught by a handler that can run, as (node, error) pairs.
+    /// An execution that hits one of them stops with an error.
+    pub fn unhandled_failures(&self) -> Vec<(NodeIdx, String)> {
+        let reached = self.get_reached_constraints(&[]).reached;
+        self.graph
+            .nodes
             .iter()
-            .map(|target_node| {
-                &node_idx_to_transition_constraints
-                    .get(target_node)
-                    .unwrap()
-                    .0
+            .flat_map(|node| {
+                node.failures
+                    .iter()
+                    .enumerate()
+                    .map(move |(failure_idx, failure)| (node.id, failure_idx, failure))
             })
-            .collect::<Vec<_>>();
-        conjunctive_clauses.push(Bool::or(self.context, &reach_target));
-
-        let solver = Solver::new(&self.context);
-        solver.assert(
-            &Bool::and(
-                self.context,
-                &conjunctive_clauses.iter().collect::<Vec<_>>(),
-            )
-            .not(),
-        );
+            .filter(|(node_idx, failure_idx, _)| {
+                let solver = Solver::new(self.context);
+                solver.assert(&self.schema_constraints());
+                solver.assert(&reached[*node_idx]);
+                solver.assert(&self.node_asts[*node_idx].failures[*failure_idx]);
+                solver.assert(&self.is_handled(*node_idx, *failure_idx).not());
+                match solver.check() {
+                    SatResult::Sat => true,
+                    SatResult::Unsat => false,
+                    SatResult::Unknown => panic!("unknown!"),
+                }
+            })
+            .map(|(node_idx, _, failure)| (node_idx, failure.error.clone()))
+            .collect()
+    }
+}
 
-        // enforce input set size
-        solver.assert(&self.count_input_set()._eq(&Int::from_i64(
-            self.context,
-            input_set_size.try_into().unwrap(),
-        )));
+/// Run every subset of the declared inputs of `module` through it, and record the possible outcomes.
+/// This takes 2^n solver calls for n declared inputs, plus one per distinct set of outputs.
+///
+/// Panics if the module has more than `MAX_SUMMARIZED_INPUTS` declared inputs.
+pub fn summarize_module(module: &WorkflowModule) -> ModuleSummary {
+    assert!(
+        module.inputs.len() <= MAX_SUMMARIZED_INPUTS,
+        "module {} has {} declared inputs, more than the {MAX_SUMMARIZED_INPUTS} that can be summarized",
+        module.name,
+        module.inputs.len()
+    );
+    let context = Context::new(&Config::default());
+    let verifier = GraphVerifier::build(
+        &module.graph,
+        &context,
+        Some((module.end, &module.outputs)),
+        &[],
+        Abstraction::ModuleSummaries,
+    );
+    let end_ast = &verifier.node_asts[module.end];
 
-        match solver.check() {
-            SatResult::Sat => false,
-            SatResult::Unsat => true,
-            SatResult::Unknown => panic!("unknown!"),
+    let mut completions = vec![];
+    let mut failing_inputs = vec![];
+    (0..1usize << module.inputs.len()).for_each(|mask| {
+        let inputs = module
+            .inputs
+            .iter()
+            .enumerate()
+            .filter(|(i, _)| mask & (1 << i) != 0)
+            .map(|(_, s)| s.clone())
+            .collect::<BTreeSet<_>>();
+        let (completes, fails) = verifier.nested_run(
+            |s| Bool::from_bool(&context, inputs.contains(s)),
+            module.end,
+        );
+
+        let solver = Solver::new(&context);
+        solver.assert(&completes);
+        while let SatResult::Sat = solver.check() {
+            let model = solver.get_model().unwrap();
+            let outputs = module
+                .outputs
+                .iter()
+                .filter(|s| {
+                    model
+                        .eval(&end_ast.output_keys[s.as_str()], true)
+                        .unwrap()
+                        .as_bool()
+                        .unwrap()
+                })
+                .cloned()
+                .collect::<BTreeSet<_>>();
+            // look for another set of outputs
+            let same_outputs = module
+                .outputs
+                .iter()
+                .map(|s| {
+                    end_ast.output_keys[s.as_str()]
+                        ._eq(&Bool::from_bool(&context, outputs.contains(s)))
+                })
+                .collect::<Vec<_>>();
+            solver.assert(&Bool::and(&context, &same_outputs.iter().collect::<Vec<_>>()).not());
+            completions.push((inputs.clone(), outputs));
         }
-    }
 
-    pub fn minimum_input_set_for_can_eventually_reach(
-        &self,
-        target_nodes: &[NodeIdx],
-    ) -> Option<usize> {
-        // binary search
-        let mut left = 0;
-        let mut right = self.node_asts[self.graph.start.unwrap()].input_keys.len();
-        let mut cur_res = None;
-        while left < right {
-            let mid = (left + right) - 1;
-            if self.try_minimum_input_set_for_can_eventually_reach(target_nodes, mid) {
-                right = mid;
-                cur_res = Some(mid);
-            } else {
-                left = mid + 1;
-            }
+        let solver = Solver::new(&context);
+        solver.assert(&fails);
+        if let SatResult::Sat = solver.check() {
+            failing_inputs.push(inputs);
         }
-        cur_res
+    });
+    ModuleSummary {
+        completions,
+        failing_inputs,
     }
 }
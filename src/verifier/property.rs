@@ -0,0 +1,569 @@
+//! Temporal properties of workflow executions.
+//!
+//! A formula is evaluated at a node of an execution, and a property holds if its formula holds at the start
+//! node of every execution. Temporal operators range over the nodes that the execution enters after (or
+//! before) the current one, following the edges that it takes; after a fork, that includes every branch.
+//! An execution only stops at a node that cannot continue.
+//!
+//! ```text
+//! formula := unary ('U' unary)* ('&' ...)* ('|' ...)* ('->' formula)?
+//! unary   := '!' unary | 'X' unary | 'F' unary | 'G' unary | 'O' unary | 'H' unary
+//!          | 'true' | 'false' | 'key(' name ')' | name | '(' formula ')'
+//! name    := [A-Za-z0-9_.*\[\]]+ | '"' [^"]* '"'
+//! ```
+//!
+//! For example, `G (charge_card -> O validate_order)` says that `charge_card` is always preceded by
+//! `validate_order`, and `G (key(fraud_flag) -> F manual_review)` says that once a node outputs `fraud_flag`,
+//! `manual_review` eventually runs.
+
+use std::fmt;
+use std::str::FromStr;
+
+use z3::{
+    ast::{Ast, Bool},
+    Config, Context, SatResult, Solver,
+};
+
+use crate::workflow::{NodeIdx, WorkflowGraph};
+
+use super::{topsort, Abstraction, ExecutionModel, Executions, GraphVerifier};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Formula {
+    True,
+    False,
+    /// The current node is the given node.
+    Node(String),
+    /// The current node completes successfully and outputs the given key.
+    Key(String),
+    Not(Box<Formula>),
+    And(Box<Formula>, Box<Formula>),
+    Or(Box<Formula>, Box<Formula>),
+    Implies(Box<Formula>, Box<Formula>),
+    /// Holds at some node entered right after the current one.
+    Next(Box<Formula>),
+    /// Holds at the current node or some later node.
+    Eventually(Box<Formula>),
+    /// Holds at the current node and every later node.
+    Always(Box<Formula>),
+    /// The second formula eventually holds, and the first one holds at every node until then.
+    Until(Box<Formula>, Box<Formula>),
+    /// Holds at the current node or some earlier node.
+    Once(Box<Formula>),
+    /// Holds at the current node and every earlier node.
+    Historically(Box<Formula>),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseError {
+    pub position: usize, // byte offset into the parsed string
+    pub message: String,
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} at position {}", self.message, self.position)
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Token {
+    LParen,
+    RParen,
+    Not,
+    And,
+    Or,
+    Implies,
+    Name(String),
+    Quoted(String),
+}
+
+fn tokenize(s: &str) -> Result<Vec<(usize, Token)>, ParseError> {
+    let is_name_char = |c: char| c.is_ascii_alphanumeric() || "_.*[]".contains(c);
+    let mut tokens = vec![];
+    let mut chars = s.char_indices().peekable();
+    while let Some((i, c)) = chars.next() {
+        let token = match c {
+            c if c.is_whitespace() => continue,
+            '(' => Token::LParen,
+            ')' => Token::RParen,
+            '!' => Token::Not,
+            '&' => Token::And,
+            '|' => Token::Or,
+            '-' if matches!(chars.peek(), Some((_, '>'))) => {
+                chars.next();
+                Token::Implies
+            }
+            '"' => {
+                let mut name = String::new();
+                loop {
+                    match chars.next() {
+                        Some((_, '"')) => break,
+                        Some((_, c)) => name.push(c),
+                        None => {
+                            return Err(ParseError {
+                                position: i,
+                                message: "unterminated quoted name".to_string(),
+                            })
+                        }
+                    }
+                }
+                Token::Quoted(name)
+            }
+            c if is_name_char(c) => {
+                let mut name = c.to_string();
+                while let Some((_, c)) = chars.next_if(|(_, c)| is_name_char(*c)) {
+                    name.push(c);
+                }
+                Token::Name(name)
+            }
+            c => {
+                return Err(ParseError {
+                    position: i,
+                    message: format!("unexpected character `{}`", c),
+                })
+            }
+        };
+        tokens.push((i, token));
+    }
+    Ok(tokens)
+}
+
+struct Parser {
+    tokens: Vec<(usize, Token)>,
+    pos: usize,
+    end: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos).map(|(_, t)| t)
+    }
+
+    fn error(&self, message: &str) -> ParseError {
+        ParseError {
+            position: self.tokens.get(self.pos).map_or(self.end, |(i, _)| *i),
+            message: message.to_string(),
+        }
+    }
+
+    fn expect(&mut self, token: Token) -> Result<(), ParseError> {
+        match self.peek() {
+            Some(t) if *t == token => {
+                self.pos += 1;
+                Ok(())
+            }
+            _ => Err(self.error(&format!("expected {:?}", token))),
+        }
+    }
+
+    fn implication(&mut self) -> Result<Formula, ParseError> {
+        let lhs = self.disjunction()?;
+        match self.peek() {
+            Some(Token::Implies) => {
+                self.pos += 1;
+                Ok(Formula::Implies(
+                    Box::new(lhs),
+                    Box::new(self.implication()?),
+                ))
+            }
+            _ => Ok(lhs),
+        }
+    }
+
+    fn disjunction(&mut self) -> Result<Formula, ParseError> {
+        let mut lhs = self.conjunction()?;
+        while let Some(Token::Or) = self.peek() {
+            self.pos += 1;
+            lhs = Formula::Or(Box::new(lhs), Box::new(self.conjunction()?));
+        }
+        Ok(lhs)
+    }
+
+    fn conjunction(&mut self) -> Result<Formula, ParseError> {
+        let mut lhs = self.until()?;
+        while let Some(Token::And) = self.peek() {
+            self.pos += 1;
+            lhs = Formula::And(Box::new(lhs), Box::new(self.until()?));
+        }
+        Ok(lhs)
+    }
+
+    fn until(&mut self) -> Result<Formula, ParseError> {
+        let lhs = self.unary()?;
+        match self.peek() {
+            Some(Token::Name(name)) if name == "U" => {
+                self.pos += 1;
+                Ok(Formula::Until(Box::new(lhs), Box::new(self.until()?)))
+            }
+            _ => Ok(lhs),
+        }
+    }
+
+    fn unary(&mut self) -> Result<Formula, ParseError> {
+        let Some(token) = self.peek().cloned() else {
+            return Err(self.error("expected a formula"));
+        };
+        self.pos += 1;
+        let formula = match token {
+            Token::Not => Formula::Not(Box::new(self.unary()?)),
+            Token::LParen => {
+                let formula = self.implication()?;
+                self.expect(Token::RParen)?;
+                formula
+            }
+            Token::Quoted(name) => Formula::Node(name),
+            Token::Name(name) => match name.as_str() {
+                "X" => Formula::Next(Box::new(self.unary()?)),
+                "F" => Formula::Eventually(Box::new(self.unary()?)),
+                "G" => Formula::Always(Box::new(self.unary()?)),
+                "O" => Formula::Once(Box::new(self.unary()?)),
+                "H" => Formula::Historically(Box::new(self.unary()?)),
+                "true" => Formula::True,
+                "false" => Formula::False,
+                "key" if self.peek() == Some(&Token::LParen) => {
+                    self.pos += 1;
+                    let key = match self.peek().cloned() {
+                        Some(Token::Name(key)) | Some(Token::Quoted(key)) => key,
+                        _ => return Err(self.error("expected a key")),
+                    };
+                    self.pos += 1;
+                    self.expect(Token::RParen)?;
+                    Formula::Key(key)
+                }
+                _ => Formula::Node(name),
+            },
+            _ => {
+                self.pos -= 1;
+                return Err(self.error("expected a formula"));
+            }
+        };
+        Ok(formula)
+    }
+}
+
+impl FromStr for Formula {
+    type Err = ParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut parser = Parser {
+            tokens: tokenize(s)?,
+            pos: 0,
+            end: s.len(),
+        };
+        let formula = parser.implication()?;
+        match parser.peek() {
+            None => Ok(formula),
+            Some(_) => Err(parser.error("unexpected token")),
+        }
+    }
+}
+
+impl fmt::Display for Formula {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Formula::True => write!(f, "true"),
+            Formula::False => write!(f, "false"),
+            Formula::Node(name) => write!(f, "\"{}\"", name),
+            Formula::Key(key) => write!(f, "key(\"{}\")", key),
+            Formula::Not(p) => write!(f, "!{}", p),
+            Formula::And(p, q) => write!(f, "({} & {})", p, q),
+            Formula::Or(p, q) => write!(f, "({} | {})", p, q),
+            Formula::Implies(p, q) => write!(f, "({} -> {})", p, q),
+            Formula::Next(p) => write!(f, "X {}", p),
+            Formula::Eventually(p) => write!(f, "F {}", p),
+            Formula::Always(p) => write!(f, "G {}", p),
+            Formula::Until(p, q) => write!(f, "({} U {})", p, q),
+            Formula::Once(p) => write!(f, "O {}", p),
+            Formula::Historically(p) => write!(f, "H {}", p),
+        }
+    }
+}
+
+impl Formula {
+    /// All `Node` and `Key` atoms.
+    fn atoms(&self) -> Vec<&Formula> {
+        match self {
+            Formula::True | Formula::False => vec![],
+            Formula::Node(_) | Formula::Key(_) => vec![self],
+            Formula::Not(p)
+            | Formula::Next(p)
+            | Formula::Eventually(p)
+            | Formula::Always(p)
+            | Formula::Once(p)
+            | Formula::Historically(p) => p.atoms(),
+            Formula::And(p, q)
+            | Formula::Or(p, q)
+            | Formula::Implies(p, q)
+            | Formula::Until(p, q) => {
+                let mut atoms = p.atoms();
+                atoms.extend(q.atoms());
+                atoms
+            }
+        }
+    }
+
+    /// All keys used by `key(..)` atoms.
+    fn keys(&self) -> Vec<&str> {
+        self.atoms()
+            .into_iter()
+            .filter_map(|atom| match atom {
+                Formula::Key(key) => Some(key.as_str()),
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// All node names used by the formula.
+    fn nodes(&self) -> Vec<&str> {
+        self.atoms()
+            .into_iter()
+            .filter_map(|atom| match atom {
+                Formula::Node(name) => Some(name.as_str()),
+                _ => None,
+            })
+            .collect()
+    }
+}
+
+/// A formula names a node that is not in the graph.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UnknownNode(pub String);
+
+impl fmt::Display for UnknownNode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "unknown node {:?}", self.0)
+    }
+}
+
+impl std::error::Error for UnknownNode {}
+
+/// Encoding of a formula at each node of the executions encoded by `executions`.
+struct FormulaEncoder<'a, 'ctx, 'g> {
+    verifier: &'a GraphVerifier<'ctx, 'g>,
+    executions: &'a Executions<'ctx>,
+    order: Vec<NodeIdx>, // the nodes reachable from the start node, in topological order
+    taken_incoming: Vec<Vec<(NodeIdx, Bool<'ctx>)>>, // taken_incoming[i] lists the sources of the edges into node i, with whether each one is taken
+}
+
+impl<'a, 'ctx, 'g> FormulaEncoder<'a, 'ctx, 'g> {
+    fn new(verifier: &'a GraphVerifier<'ctx, 'g>, executions: &'a Executions<'ctx>) -> Self {
+        let mut order = topsort::topological_sort_reversed(verifier.graph);
+        order.reverse();
+        let mut taken_incoming = vec![vec![]; verifier.graph.nodes.len()];
+        executions
+            .taken
+            .iter()
+            .enumerate()
+            .for_each(|(src, edges)| {
+                edges.iter().for_each(|(dst, taken)| {
+                    taken_incoming[*dst].push((src, taken.clone()));
+                })
+            });
+        Self {
+            verifier,
+            executions,
+            order,
+            taken_incoming,
+        }
+    }
+
+    fn constant(&self, value: bool) -> Vec<Bool<'ctx>> {
+        vec![Bool::from_bool(self.verifier.context, value); self.verifier.graph.nodes.len()]
+    }
+
+    /// `encode(formula)[i]` is true iff `formula` holds at node i, assuming node i is entered.
+    fn encode(&self, formula: &Formula) -> Vec<Bool<'ctx>> {
+        let ctx = self.verifier.context;
+        let pointwise =
+            |p: &Formula, q: &Formula, op: fn(&Bool<'ctx>, &Bool<'ctx>) -> Bool<'ctx>| {
+                self.encode(p)
+                    .iter()
+                    .zip(self.encode(q).iter())
+                    .map(|(p, q)| op(p, q))
+                    .collect()
+            };
+        match formula {
+            Formula::True => self.constant(true),
+            Formula::False => self.constant(false),
+            Formula::Node(name) => {
+                let node = self
+                    .verifier
+                    .graph
+                    .nodes
+                    .iter()
+                    .position(|node| node.name == *name)
+                    .unwrap(); // checked by `check_property`
+                (0..self.verifier.graph.nodes.len())
+                    .map(|i| Bool::from_bool(ctx, i == node))
+                    .collect()
+            }
+            Formula::Key(key) => self
+                .verifier
+                .node_asts
+                .iter()
+                .map(|node_ast| match node_ast.output_keys.get(key.as_str()) {
+                    Some(b) => Bool::and(ctx, &[b, &node_ast.fails().not()]),
+                    None => Bool::from_bool(ctx, false),
+                })
+                .collect(),
+            Formula::Not(p) => self.encode(p).iter().map(|p| p.not()).collect(),
+            Formula::And(p, q) => pointwise(p, q, |p, q| Bool::and(p.get_ctx(), &[p, q])),
+            Formula::Or(p, q) => pointwise(p, q, |p, q| Bool::or(p.get_ctx(), &[p, q])),
+            Formula::Implies(p, q) => pointwise(p, q, |p, q| p.implies(q)),
+            Formula::Next(p) => {
+                let p = self.encode(p);
+                self.executions
+                    .taken
+                    .iter()
+                    .map(|edges| {
+                        let next = edges
+                            .iter()
+                            .map(|(dst, taken)| Bool::and(ctx, &[taken, &p[*dst]]))
+                            .collect::<Vec<_>>();
+                        Bool::or(ctx, &next.iter().collect::<Vec<_>>())
+                    })
+                    .collect()
+            }
+            Formula::Eventually(p) => self.encode_future(p, |now, later| {
+                // some taken edge leads to a node where it eventually holds
+                let later = later
+                    .iter()
+                    .map(|(taken, holds)| Bool::and(ctx, &[taken, holds]))
+                    .collect::<Vec<_>>();
+                Bool::or(
+                    ctx,
+                    &[now, &Bool::or(ctx, &later.iter().collect::<Vec<_>>())],
+                )
+            }),
+            Formula::Always(p) => self.encode_future(p, |now, later| {
+                // every taken edge leads to a node where it always holds
+                let later = later
+                    .iter()
+                    .map(|(taken, holds)| taken.implies(holds))
+                    .collect::<Vec<_>>();
+                Bool::and(
+                    ctx,
+                    &[now, &Bool::and(ctx, &later.iter().collect::<Vec<_>>())],
+                )
+            }),
+            Formula::Until(p, q) => {
+                let p = self.encode(p);
+                let mut holds = self.encode(q);
+                self.order.iter().rev().for_each(|i| {
+                    let later = self.executions.taken[*i]
+                        .iter()
+                        .map(|(dst, taken)| Bool::and(ctx, &[taken, &holds[*dst]]))
+                        .collect::<Vec<_>>();
+                    let later = Bool::or(ctx, &later.iter().collect::<Vec<_>>());
+                    holds[*i] = Bool::or(ctx, &[&holds[*i], &Bool::and(ctx, &[&p[*i], &later])]);
+                });
+                holds
+            }
+            Formula::Once(p) => self.encode_past(p, |now, earlier| {
+                let earlier = earlier
+                    .iter()
+                    .map(|(taken, holds)| Bool::and(ctx, &[taken, holds]))
+                    .collect::<Vec<_>>();
+                Bool::or(
+                    ctx,
+                    &[now, &Bool::or(ctx, &earlier.iter().collect::<Vec<_>>())],
+                )
+            }),
+            Formula::Historically(p) => self.encode_past(p, |now, earlier| {
+                let earlier = earlier
+                    .iter()
+                    .map(|(taken, holds)| taken.implies(holds))
+                    .collect::<Vec<_>>();
+                Bool::and(
+                    ctx,
+                    &[now, &Bool::and(ctx, &earlier.iter().collect::<Vec<_>>())],
+                )
+            }),
+        }
+    }
+
+    /// Combine whether `p` holds at each node with the result at the targets of its taken edges,
+    /// from the last nodes backwards.
+    fn encode_future(
+        &self,
+        p: &Formula,
+        combine: impl Fn(&Bool<'ctx>, &[(Bool<'ctx>, Bool<'ctx>)]) -> Bool<'ctx>,
+    ) -> Vec<Bool<'ctx>> {
+        let mut holds = self.encode(p);
+        self.order.iter().rev().for_each(|i| {
+            let later = self.executions.taken[*i]
+                .iter()
+                .map(|(dst, taken)| (taken.clone(), holds[*dst].clone()))
+                .collect::<Vec<_>>();
+            holds[*i] = combine(&holds[*i], &later);
+        });
+        holds
+    }
+
+    /// Combine whether `p` holds at each node with the result at the sources of its taken incoming edges,
+    /// from the start node forwards.
+    fn encode_past(
+        &self,
+        p: &Formula,
+        combine: impl Fn(&Bool<'ctx>, &[(Bool<'ctx>, Bool<'ctx>)]) -> Bool<'ctx>,
+    ) -> Vec<Bool<'ctx>> {
+        let mut holds = self.encode(p);
+        self.order.iter().for_each(|i| {
+            let earlier = self.taken_incoming[*i]
+                .iter()
+                .map(|(src, taken)| (taken.clone(), holds[*src].clone()))
+                .collect::<Vec<_>>();
+            holds[*i] = combine(&holds[*i], &earlier);
+        });
+        holds
+    }
+}
+
+/// Check that `formula` holds at the start node of every execution of `graph`, whatever the user input.
+/// Otherwise, return a counterexample: the nodes entered by a violating execution, in topological order.
+pub fn check_property(
+    graph: &WorkflowGraph,
+    formula: &Formula,
+) -> Result<Option<Vec<ExecutionModel>>, UnknownNode> {
+    if let Some(name) = formula
+        .nodes()
+        .into_iter()
+        .find(|name| graph.nodes.iter().all(|node| node.name != *name))
+    {
+        return Err(UnknownNode(name.to_string()));
+    }
+    let context = Context::new(&Config::default());
+    let keys = formula.keys();
+    let verifier = GraphVerifier::build(graph, &context, None, &keys, Abstraction::Inline);
+    let executions = verifier.get_reached_constraints(&[]);
+    let encoder = FormulaEncoder::new(&verifier, &executions);
+    let holds = encoder.encode(formula);
+
+    let solver = Solver::new(&context);
+    solver.assert(&verifier.schema_constraints());
+    solver.assert(&verifier.maximal_execution_constraints(&executions));
+    solver.assert(&holds[graph.start.unwrap()].not());
+    match solver.check() {
+        SatResult::Sat => {
+            let model = solver.get_model().unwrap();
+            let counterexample = encoder
+                .order
+                .iter()
+                .filter(|i| {
+                    model
+                        .eval(&executions.entered[**i], true)
+                        .unwrap()
+                        .as_bool()
+                        .unwrap()
+                })
+                .map(|i| verifier.execution_model(*i, &model))
+                .collect();
+            Ok(Some(counterexample))
+        }
+        SatResult::Unsat => Ok(None),
+        SatResult::Unknown => panic!("unknown!"),
+    }
+}
@@ -0,0 +1,261 @@
+//! Where the keys seen by a node come from.
+
+use z3::{ast::Bool, Config, Context, Model, SatResult, Solver};
+
+use crate::workflow::schema::{InputCond, KeyRule, OutputSchema};
+use crate::workflow::{NodeIdx, NodeKind, WorkflowGraph};
+
+use super::{Abstraction, Executions, GraphVerifier};
+
+/// A node that outputs a key on its way to the queried node.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ProvenanceStep {
+    pub node_idx: NodeIdx,
+    pub output_key: String,
+    /// The rule of the node's output schema that outputs the key. The results of a map node and the outputs of
+    /// a sub-workflow node are reported as `Fixed`.
+    pub rule: KeyRule,
+}
+
+/// How a key reaches the input of a node in one execution.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Provenance {
+    /// The key given by the user to the start node, if the chain starts there.
+    pub user_input: Option<String>,
+    /// The nodes that output the key, from the first one to a parent of the queried node.
+    pub steps: Vec<ProvenanceStep>,
+}
+
+struct ProvenanceTracer<'a, 'ctx, 'g> {
+    verifier: &'a GraphVerifier<'ctx, 'g>,
+    executions: &'a Executions<'ctx>,
+}
+
+impl<'a, 'ctx, 'g> ProvenanceTracer<'a, 'ctx, 'g> {
+    /// Whether `key` is output by `src` along its `edge_pos`-th taken edge, which may be a catch edge.
+    fn output_along_edge(&self, src: NodeIdx, edge_pos: usize, key: &str) -> Bool<'ctx> {
+        let ctx = self.verifier.context;
+        let node_ast = &self.verifier.node_asts[src];
+        let num_success_edges = self.verifier.graph.adj_list[src].len();
+        if edge_pos < num_success_edges {
+            return match node_ast.output_keys.get(key) {
+                Some(b) => b.clone(),
+                None => Bool::from_bool(ctx, false),
+            };
+        }
+        let catch_idx = edge_pos - num_success_edges;
+        let outputs = self.verifier.graph.nodes[src]
+            .failures
+            .iter()
+            .enumerate()
+            .filter(|(_, failure)| {
+                self.verifier.graph.catch_for(src, &failure.error) == Some(catch_idx)
+            })
+            .filter_map(|(i, _)| {
+                node_ast.failure_output_keys[i]
+                    .get(key)
+                    .map(|b| Bool::and(ctx, &[&node_ast.failures[i], b]))
+            })
+            .collect::<Vec<_>>();
+        Bool::or(ctx, &outputs.iter().collect::<Vec<_>>())
+    }
+
+    /// The taken edges into `dst`, as (source, position in `taken[source]`).
+    fn incoming(&self, dst: NodeIdx) -> Vec<(NodeIdx, usize)> {
+        self.executions
+            .taken
+            .iter()
+            .enumerate()
+            .flat_map(|(src, edges)| {
+                edges
+                    .iter()
+                    .enumerate()
+                    .filter(move |(_, (edge_dst, _))| *edge_dst == dst)
+                    .map(move |(edge_pos, _)| (src, edge_pos))
+            })
+            .collect()
+    }
+
+    /// Whether `key` is in the input of `node`.
+    fn seen(&self, node: NodeIdx, key: &str) -> Bool<'ctx> {
+        let ctx = self.verifier.context;
+        if Some(node) == self.verifier.graph.start {
+            return match self.verifier.node_asts[node].input_keys.get(key) {
+                Some(b) => b.clone(),
+                None => Bool::from_bool(ctx, false),
+            };
+        }
+        let sources = self
+            .incoming(node)
+            .into_iter()
+            .map(|(src, edge_pos)| {
+                Bool::and(
+                    ctx,
+                    &[
+                        &self.executions.taken[src][edge_pos].1,
+                        &self.output_along_edge(src, edge_pos, key),
+                    ],
+                )
+            })
+            .collect::<Vec<_>>();
+        Bool::or(ctx, &sources.iter().collect::<Vec<_>>())
+    }
+
+    /// Follow `key` back from the input of `node` in `model`.
+    /// Also returns the facts of the model that the chain relies on.
+    fn trace(
+        &self,
+        model: &Model<'ctx>,
+        node: NodeIdx,
+        key: &str,
+    ) -> (Provenance, Vec<Bool<'ctx>>) {
+        let holds = |b: &Bool<'ctx>| model.eval(b, true).unwrap().as_bool().unwrap();
+        let mut facts = vec![];
+        let mut steps = vec![];
+        let (mut node, mut key) = (node, key.to_string());
+        let user_input = loop {
+            if Some(node) == self.verifier.graph.start {
+                break Some(key);
+            }
+            let Some((src, edge_pos)) = self.incoming(node).into_iter().find(|(src, edge_pos)| {
+                holds(&self.executions.taken[*src][*edge_pos].1)
+                    && holds(&self.output_along_edge(*src, *edge_pos, &key))
+            }) else {
+                break None;
+            };
+            facts.push(self.executions.taken[src][edge_pos].1.clone());
+            let node_ast = &self.verifier.node_asts[src];
+            let src_node = &self.verifier.graph.nodes[src];
+            let failure_idx = node_ast.failures.iter().position(&holds);
+            let output_schema = match failure_idx {
+                Some(i) => {
+                    facts.push(node_ast.failures[i].clone());
+                    &src_node.failures[i].output_schema
+                }
+                None => &src_node.output_schema,
+            };
+            let has_input = |s: &str| match node_ast.input_keys.get(s) {
+                Some(b) if holds(b) => Some(b.clone()),
+                _ => None,
+            };
+            let exported = failure_idx.is_none()
+                && match &src_node.kind {
+                    NodeKind::Map(_) | NodeKind::SubWorkflow(_) => {
+                        node_ast.exported_keys.get(key.as_str()).is_some_and(holds)
+                    }
+                    _ => false,
+                };
+            let (rule, input) = match rule_for(output_schema, &key, has_input) {
+                Some(found) if !exported => found,
+                _ => (KeyRule::Fixed(key.clone()), None),
+            };
+            steps.push(ProvenanceStep {
+                node_idx: src,
+                output_key: key.clone(),
+                rule,
+            });
+            match input {
+                Some((input_key, b)) => {
+                    facts.push(b);
+                    node = src;
+                    key = input_key;
+                }
+                None => break None,
+            }
+        };
+        steps.reverse();
+        (Provenance { user_input, steps }, facts)
+    }
+}
+
+/// The rule of `output_schema` that outputs `key`, preferring fixed and may keys, and the input key it carries,
+/// if any. `has_input(s)` returns the boolean of input key `s` if it is present.
+fn rule_for<'ctx>(
+    output_schema: &OutputSchema,
+    key: &str,
+    has_input: impl Fn(&str) -> Option<Bool<'ctx>>,
+) -> Option<(KeyRule, Option<(String, Bool<'ctx>)>)> {
+    if output_schema
+        .fixed_keys()
+        .chain(output_schema.may_keys())
+        .any(|s| s == key)
+    {
+        return Some((KeyRule::Fixed(key.to_string()), None));
+    }
+    let cond_holds = |cond: &InputCond, expected: Option<&str>| match cond {
+        InputCond::Always => true,
+        InputCond::MatchesKey(s)
+        | InputCond::MatchesKeyValue(s, _)
+        | InputCond::Compare(s, _, _) => {
+            let expected = match expected {
+                Some(e) => e == s,
+                None => true,
+            };
+            expected && has_input(s).is_some()
+        }
+    };
+    let fixed = output_schema.dynamic_keys().iter().find(|(rule, cond)| {
+        matches!(rule, KeyRule::Fixed(s) if s == key) && cond_holds(cond, None)
+    });
+    if let Some((rule, _)) = fixed {
+        return Some((rule.clone(), None));
+    }
+    output_schema
+        .dynamic_keys()
+        .iter()
+        .find_map(|(rule, cond)| {
+            let input_key = rule.carried_key(key)?;
+            if !cond_holds(cond, Some(input_key)) {
+                return None;
+            }
+            let b = has_input(input_key)?;
+            Some((rule.clone(), Some((input_key.to_string(), b))))
+        })
+}
+
+fn enumerate_provenances(
+    graph: &WorkflowGraph,
+    node: NodeIdx,
+    key: &str,
+    limit: Option<usize>,
+) -> Vec<Provenance> {
+    let context = Context::new(&Config::default());
+    let verifier = GraphVerifier::build(graph, &context, None, &[key], Abstraction::Inline);
+    let executions = verifier.get_reached_constraints(&[]);
+    let tracer = ProvenanceTracer {
+        verifier: &verifier,
+        executions: &executions,
+    };
+
+    let solver = Solver::new(&context);
+    solver.assert(&verifier.schema_constraints());
+    solver.assert(&executions.entered[node]);
+    solver.assert(&tracer.seen(node, key));
+    let mut provenances = vec![];
+    while match limit {
+        Some(limit) => provenances.len() < limit,
+        None => true,
+    } {
+        let SatResult::Sat = solver.check() else {
+            break;
+        };
+        let model = solver.get_model().unwrap();
+        let (provenance, facts) = tracer.trace(&model, node, key);
+        // look for a chain that differs in an edge or a rule
+        solver.assert(&Bool::and(&context, &facts.iter().collect::<Vec<_>>()).not());
+        if !provenances.contains(&provenance) {
+            provenances.push(provenance);
+        }
+    }
+    provenances
+}
+
+/// How `key` may reach the input of `node` in some execution, or `None` if it never does.
+pub fn key_provenance(graph: &WorkflowGraph, node: NodeIdx, key: &str) -> Option<Provenance> {
+    enumerate_provenances(graph, node, key, Some(1)).pop()
+}
+
+/// Every way `key` may reach the input of `node`, over all executions.
+pub fn all_key_provenances(graph: &WorkflowGraph, node: NodeIdx, key: &str) -> Vec<Provenance> {
+    enumerate_provenances(graph, node, key, None)
+}
@@ -0,0 +1,238 @@
+//! Compensations of sagas: when a node fails, the steps that completed before it are undone in reverse order.
+
+use z3::{ast::Bool, Config, Context, Model, SatResult, Solver};
+
+use crate::workflow::{NodeIdx, WorkflowGraph};
+
+use super::{topsort, ExecutionModel, Executions, GraphVerifier};
+
+/// What goes wrong with the compensations after a failure.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CompensationIssue {
+    /// `step` completed, but its compensation does not run.
+    NotCompensated {
+        step: NodeIdx,
+        compensation: NodeIdx,
+    },
+    /// `step` completed, but the execution stops right before its compensation, which misses `missing` inputs.
+    MissingInputs {
+        step: NodeIdx,
+        compensation: NodeIdx,
+        missing: Vec<String>,
+    },
+    /// `earlier` completed before `later`, but its compensation does not run after the one of `later`.
+    OutOfOrder { earlier: NodeIdx, later: NodeIdx },
+}
+
+#[derive(Debug)]
+pub struct CompensationViolation {
+    pub failing_node: NodeIdx,
+    pub error: String,
+    pub issue: CompensationIssue,
+    /// The nodes entered by a violating execution, in topological order.
+    pub counterexample: Vec<ExecutionModel>,
+}
+
+/// `paths[a][b]` is true iff there is a path of success and catch edges from `a` to `b`.
+fn paths(graph: &WorkflowGraph) -> Vec<Vec<bool>> {
+    let mut paths = vec![vec![false; graph.nodes.len()]; graph.nodes.len()];
+    topsort::topological_sort_reversed(graph)
+        .into_iter()
+        .for_each(|src| {
+            let children = graph.adj_list[src]
+                .iter()
+                .map(|(dst, _)| *dst)
+                .chain(graph.catch_list[src].iter().map(|(dst, _)| *dst))
+                .collect::<Vec<_>>();
+            children.into_iter().for_each(|dst| {
+                let reachable_from_dst = paths[dst].clone();
+                paths[src][dst] = true;
+                paths[src]
+                    .iter_mut()
+                    .zip(reachable_from_dst)
+                    .for_each(|(p, reachable)| *p |= reachable);
+            });
+        });
+    paths
+}
+
+struct SagaChecker<'a, 'ctx, 'g> {
+    verifier: &'a GraphVerifier<'ctx, 'g>,
+    executions: &'a Executions<'ctx>,
+    order: Vec<NodeIdx>,
+}
+
+impl<'a, 'ctx, 'g> SagaChecker<'a, 'ctx, 'g> {
+    /// Whether `step` is reached and succeeds.
+    fn completed(&self, step: NodeIdx) -> Bool<'ctx> {
+        let fails = self.verifier.node_asts[step].fails();
+        Bool::and(
+            self.verifier.context,
+            &[&self.executions.reached[step], &fails.not()],
+        )
+    }
+
+    fn counterexample(&self, model: &Model<'ctx>) -> Vec<ExecutionModel> {
+        self.order
+            .iter()
+            .filter(|i| {
+                model
+                    .eval(&self.executions.entered[**i], true)
+                    .unwrap()
+                    .as_bool()
+                    .unwrap()
+            })
+            .map(|i| self.verifier.execution_model(*i, model))
+            .collect()
+    }
+
+    /// The required inputs of `compensation` missing from the outputs of an entered node that would move on to it.
+    fn missing_inputs(
+        &self,
+        compensation: NodeIdx,
+        counterexample: &[ExecutionModel],
+    ) -> Vec<String> {
+        let graph = self.verifier.graph;
+        counterexample
+            .iter()
+            .filter(|step| match &step.error {
+                None => graph.adj_list[step.node_idx]
+                    .iter()
+                    .any(|(dst, _)| *dst == compensation),
+                Some(error) => graph
+                    .catch_for(step.node_idx, error)
+                    .is_some_and(|i| graph.catch_list[step.node_idx][i].0 == compensation),
+            })
+            .find_map(|step| {
+                let missing = graph.nodes[compensation]
+                    .required_inputs
+                    .iter()
+                    .filter(|s| !step.output_keys.contains(s))
+                    .cloned()
+                    .collect::<Vec<_>>();
+                (!missing.is_empty()).then_some(missing)
+            })
+            .unwrap_or_default()
+    }
+
+    /// The nodes entered by an execution that also satisfies `constraints`, if there is one.
+    fn find(
+        &self,
+        solver: &Solver<'ctx>,
+        constraints: &[&Bool<'ctx>],
+    ) -> Option<Vec<ExecutionModel>> {
+        solver.push();
+        constraints.iter().for_each(|c| solver.assert(c));
+        let result = match solver.check() {
+            SatResult::Sat => Some(self.counterexample(&solver.get_model().unwrap())),
+            SatResult::Unsat => None,
+            SatResult::Unknown => panic!("unknown!"),
+        };
+        solver.pop(1);
+        result
+    }
+}
+
+/// Check the compensations declared with `WorkflowGraph::set_compensation`. Whenever a node fails, in any
+/// execution, the compensation of every step that completed before it must run, with its required inputs, and
+/// the compensation of a later step must lead to the compensation of an earlier one.
+/// Steps that completed before a failure are the steps with a path to the failing node.
+pub fn check_compensations(graph: &WorkflowGraph) -> Vec<CompensationViolation> {
+    let context = Context::new(&Config::default());
+    let verifier = GraphVerifier::new(graph, &context);
+    let executions = verifier.get_reached_constraints(&[]);
+    let checker = SagaChecker {
+        verifier: &verifier,
+        executions: &executions,
+        order: topsort::topological_sort_reversed(graph)
+            .into_iter()
+            .rev()
+            .collect(),
+    };
+    let paths = paths(graph);
+    let steps = graph
+        .nodes
+        .iter()
+        .filter_map(|node| node.compensation.map(|c| (node.id, c)))
+        .collect::<Vec<_>>();
+
+    let solver = Solver::new(&context);
+    solver.assert(&verifier.schema_constraints());
+    solver.assert(&verifier.maximal_execution_constraints(&executions));
+    let mut violations = vec![];
+    for failing_node in checker.order.iter().copied() {
+        let earlier_steps = steps
+            .iter()
+            .filter(|(step, _)| paths[*step][failing_node])
+            .copied()
+            .collect::<Vec<_>>();
+        if earlier_steps.is_empty() {
+            continue;
+        }
+        for (failure_idx, failure) in graph.nodes[failing_node].failures.iter().enumerate() {
+            let fails = Bool::and(
+                &context,
+                &[
+                    &executions.reached[failing_node],
+                    &verifier.node_asts[failing_node].failures[failure_idx],
+                ],
+            );
+            let mut report = |issue, counterexample| {
+                violations.push(CompensationViolation {
+                    failing_node,
+                    error: failure.error.clone(),
+                    issue,
+                    counterexample,
+                })
+            };
+            for (step, compensation) in &earlier_steps {
+                let not_compensated = executions.reached[*compensation].not();
+                let completed = checker.completed(*step);
+                if let Some(counterexample) =
+                    checker.find(&solver, &[&fails, &completed, &not_compensated])
+                {
+                    let missing = checker.missing_inputs(*compensation, &counterexample);
+                    let issue = match missing.is_empty() {
+                        true => CompensationIssue::NotCompensated {
+                            step: *step,
+                            compensation: *compensation,
+                        },
+                        false => CompensationIssue::MissingInputs {
+                            step: *step,
+                            compensation: *compensation,
+                            missing,
+                        },
+                    };
+                    report(issue, counterexample);
+                }
+            }
+            for (earlier, earlier_compensation) in &earlier_steps {
+                for (later, later_compensation) in &earlier_steps {
+                    if !paths[*earlier][*later]
+                        || earlier_compensation == later_compensation
+                        || paths[*later_compensation][*earlier_compensation]
+                    {
+                        continue;
+                    }
+                    let both_compensated = [
+                        fails.clone(),
+                        checker.completed(*earlier),
+                        checker.completed(*later),
+                        executions.reached[*earlier_compensation].clone(),
+                        executions.reached[*later_compensation].clone(),
+                    ];
+                    if let Some(counterexample) =
+                        checker.find(&solver, &both_compensated.iter().collect::<Vec<_>>())
+                    {
+                        let issue = CompensationIssue::OutOfOrder {
+                            earlier: *earlier,
+                            later: *later,
+                        };
+                        report(issue, counterexample);
+                    }
+                }
+            }
+        }
+    }
+    violations
+}
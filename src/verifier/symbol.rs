@@ -1,6 +1,10 @@
-use std::sync::Mutex;
+//! Fresh symbols for z3 constants, with the place that created them.
+//!
+//! Each thread has its own factory, so that verifiers on different threads do not contend for it. Symbols are
+//! only unique per thread, which is enough since a z3 `Context` cannot leave the thread that created it.
+
+use std::cell::RefCell;
 
-use lazy_static::lazy_static;
 use z3::Symbol;
 
 pub struct SymbolFactory {
@@ -23,34 +27,34 @@ impl SymbolFactory {
     }
 }
 
-lazy_static! {
-    pub static ref SYMBOL_FACTORY: Mutex<SymbolFactory> = Mutex::new(SymbolFactory::new(0));
+thread_local! {
+    pub static SYMBOL_FACTORY: RefCell<SymbolFactory> = RefCell::new(SymbolFactory::new(0));
 }
 
+/// Where a symbol created on this thread comes from, as (filename, line, comment).
 pub fn get_reason(sym: &Symbol) -> (&'static str, u32, Option<String>) {
-    // (filename, line, comment)
-    let factory = SYMBOL_FACTORY.lock().unwrap();
     let idx = if let Symbol::Int(idx) = sym {
         *idx as usize
     } else {
         panic!("symbol is not an integer")
     };
-    factory.trace.get(idx).unwrap().clone()
+    SYMBOL_FACTORY.with(|factory| factory.borrow().trace.get(idx).unwrap().clone())
 }
 
+/// Number of symbols created on this thread.
 pub fn get_symbol_count() -> usize {
-    let factory = SYMBOL_FACTORY.lock().unwrap();
-    factory.ctr
+    SYMBOL_FACTORY.with(|factory| factory.borrow().ctr)
 }
 
 macro_rules! symbol {
     ($comment:expr) => {{
-        let mut factory = crate::verifier::symbol::SYMBOL_FACTORY.lock().unwrap();
-        factory.add_symbol(file!(), line!(), Some($comment.to_string()))
+        let comment = Some($comment.to_string());
+        crate::verifier::symbol::SYMBOL_FACTORY
+            .with(|factory| factory.borrow_mut().add_symbol(file!(), line!(), comment))
     }};
     () => {{
-        let mut factory = crate::verifier::symbol::SYMBOL_FACTORY.lock().unwrap();
-        factory.add_symbol(file!(), line!(), None)
+        crate::verifier::symbol::SYMBOL_FACTORY
+            .with(|factory| factory.borrow_mut().add_symbol(file!(), line!(), None))
     }};
 }
 
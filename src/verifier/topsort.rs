@@ -9,7 +9,9 @@ fn dfs(
     post_order: &mut Vec<NodeIdx>,
 ) {
     visited[node] = true;
-    for (dst, _) in &graph.adj_list[node] {
+    let success_children = graph.adj_list[node].iter().map(|(dst, _)| dst);
+    let catch_children = graph.catch_list[node].iter().map(|(dst, _)| dst);
+    for dst in success_children.chain(catch_children) {
         if !visited[*dst] {
             dfs(graph, *dst, visited, post_order);
         }
@@ -26,3 +28,38 @@ pub fn topological_sort_reversed(graph: &WorkflowGraph) -> Vec<NodeIdx> {
 
     post_order
 }
+
+/// A cycle among the nodes reachable from the start node, following success and catch edges, if there is one.
+/// The verifier rejects graphs that have one.
+pub fn find_cycle(graph: &WorkflowGraph) -> Option<Vec<NodeIdx>> {
+    fn visit(
+        graph: &WorkflowGraph,
+        node: NodeIdx,
+        visited: &mut Vec<bool>,
+        stack: &mut Vec<NodeIdx>,
+    ) -> Option<Vec<NodeIdx>> {
+        if let Some(pos) = stack.iter().position(|n| *n == node) {
+            return Some(stack[pos..].to_vec());
+        }
+        if visited[node] {
+            return None;
+        }
+        visited[node] = true;
+        stack.push(node);
+        let success_children = graph.adj_list[node].iter().map(|(dst, _)| dst);
+        let catch_children = graph.catch_list[node].iter().map(|(dst, _)| dst);
+        let cycle = success_children
+            .chain(catch_children)
+            .find_map(|dst| visit(graph, *dst, visited, stack));
+        stack.pop();
+        cycle
+    }
+
+    let start = graph.start.expect("start node is not set");
+    visit(
+        graph,
+        start,
+        &mut vec![false; graph.nodes.len()],
+        &mut vec![],
+    )
+}
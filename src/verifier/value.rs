@@ -0,0 +1,215 @@
+//! Encoding of the values of typed keys.
+
+use std::collections::HashMap;
+
+use z3::{
+    ast::{Ast, Bool, Dynamic, Int, String as Str},
+    Context, FuncDecl, Model, Sort, Symbol,
+};
+
+use crate::workflow::value::{CmpOp, Expr, Value, ValueType};
+
+use super::symbol::symbol;
+
+/// The sort of the non-null values of `ty`. Enum sorts are named after their variants, so that equal enum
+/// types have the same sort.
+fn sort<'ctx>(ctx: &'ctx Context, ty: &ValueType) -> Sort<'ctx> {
+    match ty.non_null() {
+        ValueType::String => Sort::string(ctx),
+        ValueType::Int => Sort::int(ctx),
+        ValueType::Bool => Sort::bool(ctx),
+        ValueType::Enum(variants) => enum_sort(ctx, variants).0,
+        ValueType::Nullable(_) => unreachable!(),
+    }
+}
+
+fn enum_sort<'ctx>(ctx: &'ctx Context, variants: &[String]) -> (Sort<'ctx>, Vec<FuncDecl<'ctx>>) {
+    let name = format!("enum{{{}}}", variants.join(","));
+    let constructors = variants
+        .iter()
+        .map(|v| Symbol::String(format!("{name}::{v}")))
+        .collect::<Vec<_>>();
+    let (sort, constructors, _) = Sort::enumeration(ctx, Symbol::String(name), &constructors);
+    (sort, constructors)
+}
+
+/// The value of a typed key: null, or a term of the sort of its type.
+#[derive(Debug, Clone)]
+pub struct ValueAST<'ctx> {
+    pub ctx: &'ctx Context,
+    pub ty: ValueType,
+    /// Always false if the type is not nullable.
+    pub null: Bool<'ctx>,
+    pub value: Dynamic<'ctx>,
+}
+
+impl<'ctx> ValueAST<'ctx> {
+    pub fn new_const(ctx: &'ctx Context, ty: &ValueType) -> Self {
+        let null = match ty {
+            ValueType::Nullable(_) => Bool::new_const(ctx, symbol!()),
+            _ => Bool::from_bool(ctx, false),
+        };
+        Self {
+            ctx,
+            ty: ty.clone(),
+            null,
+            value: FuncDecl::new(ctx, symbol!(), &[], &sort(ctx, ty)).apply(&[]),
+        }
+    }
+
+    /// The term of a non-null literal, or `None` if it is not a value of the type.
+    fn literal(&self, value: &Value) -> Option<Dynamic<'ctx>> {
+        match (self.ty.non_null(), value) {
+            (ValueType::String, Value::String(s)) => {
+                Some(Dynamic::from_ast(&Str::from_str(self.ctx, s).unwrap()))
+            }
+            (ValueType::Int, Value::Int(n)) => {
+                Some(Dynamic::from_ast(&Int::from_i64(self.ctx, *n)))
+            }
+            (ValueType::Bool, Value::Bool(b)) => {
+                Some(Dynamic::from_ast(&Bool::from_bool(self.ctx, *b)))
+            }
+            (ValueType::Enum(variants), Value::String(s)) => {
+                let (_, constructors) = enum_sort(self.ctx, variants);
+                let i = variants.iter().position(|v| v == s)?;
+                Some(constructors[i].apply(&[]))
+            }
+            _ => None,
+        }
+    }
+
+    /// Whether both values are null, or both are the same value.
+    pub fn same_as(&self, other: &ValueAST<'ctx>) -> Bool<'ctx> {
+        Bool::and(
+            self.ctx,
+            &[
+                &self.null._eq(&other.null),
+                &Bool::or(self.ctx, &[&self.null, &self.value._eq(&other.value)]),
+            ],
+        )
+    }
+
+    /// Whether the value compares to `value` with `op`. A literal that is not a value of the type is never
+    /// equal to the value. Panics on an ordering of values that are not integers.
+    pub fn compare(&self, op: CmpOp, value: &Value) -> Bool<'ctx> {
+        let equal = match value {
+            Value::Null => self.null.clone(),
+            _ => match self.literal(value) {
+                Some(literal) => {
+                    Bool::and(self.ctx, &[&self.null.not(), &self.value._eq(&literal)])
+                }
+                None => Bool::from_bool(self.ctx, false),
+            },
+        };
+        match op {
+            CmpOp::Eq => equal,
+            CmpOp::Ne => equal.not(),
+            _ => {
+                let (Some(literal), Value::Int(_)) = (self.literal(value), value) else {
+                    panic!("only integers are ordered, not {value}")
+                };
+                let (actual, expected) = (self.value.as_int().unwrap(), literal.as_int().unwrap());
+                let holds = match op {
+                    CmpOp::Lt => actual.lt(&expected),
+                    CmpOp::Le => actual.le(&expected),
+                    CmpOp::Gt => actual.gt(&expected),
+                    _ => actual.ge(&expected),
+                };
+                Bool::and(self.ctx, &[&self.null.not(), &holds])
+            }
+        }
+    }
+
+    /// Whether the written value `s`, as in `InputCond::MatchesKeyValue`, is the value.
+    pub fn matches(&self, s: &str) -> Bool<'ctx> {
+        match Value::parse(&self.ty, s) {
+            Some(value) => self.compare(CmpOp::Eq, &value),
+            None => Bool::from_bool(self.ctx, false),
+        }
+    }
+
+    /// Whether the value is the value of `expr`, if no key in it is null. `None` if a key in it is untyped.
+    /// Panics if the expression is ill-typed, or of another type than the value.
+    pub fn is_expr(
+        &self,
+        expr: &Expr,
+        values: &HashMap<&str, ValueAST<'ctx>>,
+    ) -> Option<Bool<'ctx>> {
+        let mut not_null = vec![];
+        let term = expr_term(self.ctx, expr, values, &mut not_null)?;
+        if term.get_sort() != self.value.get_sort() {
+            panic!(
+                "expression of sort {} for a key of type {:?}",
+                term.get_sort(),
+                self.ty
+            );
+        }
+        let is_term = Bool::and(self.ctx, &[&self.null.not(), &self.value._eq(&term)]);
+        Some(Bool::and(self.ctx, &not_null.iter().collect::<Vec<_>>()).implies(&is_term))
+    }
+
+    pub fn eval(&self, model: &Model) -> Value {
+        if model.eval(&self.null, true).unwrap().as_bool().unwrap() {
+            return Value::Null;
+        }
+        let value = model.eval(&self.value, true).unwrap();
+        match self.ty.non_null() {
+            ValueType::String => Value::String(value.as_string().unwrap().as_string().unwrap()),
+            ValueType::Int => Value::Int(value.as_int().unwrap().as_i64().unwrap()),
+            ValueType::Bool => Value::Bool(value.as_bool().unwrap().as_bool().unwrap()),
+            ValueType::Enum(variants) => {
+                let (_, constructors) = enum_sort(self.ctx, variants);
+                let i = constructors
+                    .iter()
+                    .position(|c| c.apply(&[]) == value)
+                    .unwrap();
+                Value::String(variants[i].clone())
+            }
+            ValueType::Nullable(_) => unreachable!(),
+        }
+    }
+}
+
+/// The term of `expr`, adding to `not_null` that its keys are not null.
+fn expr_term<'ctx>(
+    ctx: &'ctx Context,
+    expr: &Expr,
+    values: &HashMap<&str, ValueAST<'ctx>>,
+    not_null: &mut Vec<Bool<'ctx>>,
+) -> Option<Dynamic<'ctx>> {
+    let mut term = |e: &Expr| expr_term(ctx, e, values, not_null);
+    let ints = |a: Dynamic<'ctx>, b: Dynamic<'ctx>| match (a.as_int(), b.as_int()) {
+        (Some(a), Some(b)) => (a, b),
+        _ => panic!("arithmetic on values that are not integers: {expr:?}"),
+    };
+    Some(match expr {
+        Expr::Key(s) => {
+            let value = values.get(s.as_str())?;
+            not_null.push(value.null.not());
+            value.value.clone()
+        }
+        Expr::Lit(Value::Int(n)) => Dynamic::from_ast(&Int::from_i64(ctx, *n)),
+        Expr::Lit(Value::String(s)) => Dynamic::from_ast(&Str::from_str(ctx, s).unwrap()),
+        Expr::Lit(Value::Bool(b)) => Dynamic::from_ast(&Bool::from_bool(ctx, *b)),
+        Expr::Lit(Value::Null) => panic!("null in an expression"),
+        Expr::Add(a, b) => {
+            let (a, b) = ints(term(a)?, term(b)?);
+            Dynamic::from_ast(&Int::add(ctx, &[&a, &b]))
+        }
+        Expr::Sub(a, b) => {
+            let (a, b) = ints(term(a)?, term(b)?);
+            Dynamic::from_ast(&Int::sub(ctx, &[&a, &b]))
+        }
+        Expr::Mul(a, b) => {
+            let (a, b) = ints(term(a)?, term(b)?);
+            Dynamic::from_ast(&Int::mul(ctx, &[&a, &b]))
+        }
+        Expr::Concat(a, b) => {
+            let (a, b) = match (term(a)?.as_string(), term(b)?.as_string()) {
+                (Some(a), Some(b)) => (a, b),
+                _ => panic!("concatenation of values that are not strings: {expr:?}"),
+            };
+            Dynamic::from_ast(&Str::concat(ctx, &[&a, &b]))
+        }
+    })
+}
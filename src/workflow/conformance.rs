@@ -0,0 +1,272 @@
+//! Check that a logged execution of a workflow is allowed by its model.
+
+use std::collections::BTreeSet;
+
+use serde::{Deserialize, Serialize};
+
+use super::schema::{InputCond, OutputSchema};
+use super::simulate::{apply_schema, Values};
+use super::{NodeIdx, NodeKind, WorkflowGraph};
+
+/// One state transition of a logged execution. Values are not logged, only keys.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LoggedStep {
+    pub node: String,
+    pub input_keys: Vec<String>,
+    pub output_keys: Vec<String>,
+    /// The error the node failed with, in which case `output_keys` are its failure outputs.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+impl LoggedStep {
+    /// Parse a trace with one JSON object per line. Blank lines are skipped.
+    pub fn parse_lines(lines: &str) -> serde_json::Result<Vec<LoggedStep>> {
+        lines
+            .lines()
+            .filter(|line| !line.trim().is_empty())
+            .map(serde_json::from_str)
+            .collect()
+    }
+}
+
+/// How a logged step differs from the model.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Divergence {
+    UnknownNode(String),
+    /// The first step is not the start node.
+    WrongStart,
+    /// No earlier step that has not moved on yet can move on to the node, or a join runs before all of its
+    /// branches finish.
+    IllegalTransition,
+    /// A required input is not in the input keys.
+    MissingInput(String),
+    /// An input key is not output by the step that moved on to the node.
+    UnexpectedInput(String),
+    /// The node failed with an error it does not declare.
+    UnknownError(String),
+    /// The output schema outputs a key that is not in the output keys.
+    MissingOutput(String),
+    /// An output key is not output by the output schema.
+    UnexpectedOutput(String),
+}
+
+/// The first step of a trace that differs from the model.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Nonconformance {
+    /// Index of the step in the trace.
+    pub step: usize,
+    pub divergence: Divergence,
+}
+
+/// Keys with the empty value, since logged values are unknown.
+fn to_values(keys: &[String]) -> Values {
+    keys.iter().map(|s| (s.clone(), String::new())).collect()
+}
+
+/// The keys that `output_schema` surely outputs and may output given `input_keys`.
+/// A condition on the value of a key may hold or not, since logged values are unknown, and so may a may-key be
+/// output or not.
+fn output_bounds(output_schema: &OutputSchema, input_keys: &[String]) -> (Values, Values) {
+    let with_value_conds = |keep: bool| OutputSchema {
+        fixed_keys: match keep {
+            true => output_schema
+                .fixed_keys()
+                .chain(output_schema.may_keys())
+                .map(|s| s.to_string())
+                .collect(),
+            false => output_schema.fixed_keys.clone(),
+        },
+        may_keys: vec![],
+        dynamic_keys: output_schema
+            .dynamic_keys()
+            .iter()
+            .filter_map(|(rule, cond)| match cond {
+                InputCond::MatchesKeyValue(s, _) | InputCond::Compare(s, _, _) if keep => {
+                    Some((rule.clone(), InputCond::MatchesKey(s.clone())))
+                }
+                InputCond::MatchesKeyValue(..) | InputCond::Compare(..) => None,
+                _ => Some((rule.clone(), cond.clone())),
+            })
+            .collect(),
+        value_specs: output_schema.value_specs.clone(),
+    };
+    let inputs = to_values(input_keys);
+    (
+        apply_schema(&with_value_conds(false), &inputs),
+        apply_schema(&with_value_conds(true), &inputs),
+    )
+}
+
+struct Checker<'a> {
+    graph: &'a WorkflowGraph,
+    trace: &'a [LoggedStep],
+    nodes: Vec<NodeIdx>,
+    /// The nodes each step has moved on to so far.
+    fed: Vec<BTreeSet<NodeIdx>>,
+}
+
+impl<'a> Checker<'a> {
+    /// Whether the `pos`-th step has not moved on to `dst` yet. A fork moves on to each of its branches once,
+    /// any other node to one node.
+    fn is_free(&self, pos: usize, dst: NodeIdx) -> bool {
+        match self.graph.nodes[self.nodes[pos]].kind {
+            NodeKind::Fork if self.trace[pos].error.is_none() => !self.fed[pos].contains(&dst),
+            _ => self.fed[pos].is_empty(),
+        }
+    }
+
+    /// Whether the `pos`-th step can move on to `dst`.
+    fn moves_on_to(&self, pos: usize, dst: NodeIdx) -> bool {
+        let src = self.nodes[pos];
+        let step = &self.trace[pos];
+        let outputs = step.output_keys.iter().collect::<BTreeSet<_>>();
+        let has_inputs = match self.graph.nodes[dst].kind {
+            NodeKind::Join => true,
+            _ => self.graph.nodes[dst]
+                .required_inputs
+                .iter()
+                .all(|s| outputs.contains(s)),
+        };
+        let cond_holds = |cond: &InputCond| match cond {
+            InputCond::Always => true,
+            InputCond::MatchesKey(s)
+            | InputCond::MatchesKeyValue(s, _)
+            | InputCond::Compare(s, _, _) => outputs.contains(s),
+        };
+        let takes_edge = match &step.error {
+            None => self.graph.adj_list[src]
+                .iter()
+                .any(|(child, conds)| *child == dst && conds.iter().all(cond_holds)),
+            Some(error) => match self.graph.catch_for(src, error) {
+                Some(catch_idx) => self.graph.catch_list[src][catch_idx].0 == dst,
+                None => false,
+            },
+        };
+        takes_edge && has_inputs
+    }
+
+    /// The earlier steps whose outputs are the inputs of the `pos`-th step, or `None` if the step cannot be
+    /// entered. A join needs the last step of every incoming branch, any other node the last step that can
+    /// move on to it. Only steps that are still free to move on to the node count.
+    fn sources(&self, pos: usize) -> Option<Vec<usize>> {
+        let node = self.nodes[pos];
+        let last_moving_on = |src: Option<NodeIdx>| {
+            (0..pos)
+                .rev()
+                .filter(|p| match src {
+                    Some(src) => self.nodes[*p] == src,
+                    None => true,
+                })
+                .find(|p| self.is_free(*p, node) && self.moves_on_to(*p, node))
+        };
+        match self.graph.nodes[node].kind {
+            NodeKind::Join => {
+                let branches =
+                    self.graph
+                        .predecessors(node)
+                        .into_iter()
+                        .chain((0..self.graph.nodes.len()).filter(|src| {
+                            self.graph.catch_list[*src].iter().any(|(h, _)| *h == node)
+                        }))
+                        .collect::<BTreeSet<_>>();
+                branches
+                    .into_iter()
+                    .map(|src| last_moving_on(Some(src)))
+                    .collect()
+            }
+            _ => last_moving_on(None).map(|p| vec![p]),
+        }
+    }
+
+    fn check_step(&mut self, pos: usize) -> Option<Divergence> {
+        let step = &self.trace[pos];
+        let node = &self.graph.nodes[self.nodes[pos]];
+        if pos == 0 && self.graph.start != Some(node.id) {
+            return Some(Divergence::WrongStart);
+        }
+        if pos > 0 {
+            let Some(sources) = self.sources(pos) else {
+                return Some(Divergence::IllegalTransition);
+            };
+            sources.iter().for_each(|p| {
+                self.fed[*p].insert(node.id);
+            });
+            let passed = sources
+                .iter()
+                .flat_map(|p| self.trace[*p].output_keys.iter())
+                .collect::<BTreeSet<_>>();
+            if let Some(s) = step.input_keys.iter().find(|s| !passed.contains(s)) {
+                return Some(Divergence::UnexpectedInput(s.clone()));
+            }
+        }
+        if let Some(s) = node
+            .required_inputs
+            .iter()
+            .find(|s| !step.input_keys.contains(s))
+        {
+            return Some(Divergence::MissingInput(s.clone()));
+        }
+
+        let output_schema = match &step.error {
+            None => &node.output_schema,
+            Some(error) => match node.failures.iter().find(|f| &f.error == error) {
+                Some(failure) => &failure.output_schema,
+                None => return Some(Divergence::UnknownError(error.clone())),
+            },
+        };
+        let (mut surely, mut maybe) = output_bounds(output_schema, &step.input_keys);
+        // a map node also outputs its results, and a sub-workflow node what its module exports
+        match &node.kind {
+            NodeKind::Map(map_spec) if step.error.is_none() => {
+                surely.insert(map_spec.results_key.clone(), String::new());
+                maybe.insert(map_spec.results_key.clone(), String::new());
+            }
+            NodeKind::SubWorkflow(module) if step.error.is_none() => {
+                maybe.extend(module.outputs.iter().map(|s| (s.clone(), String::new())));
+            }
+            _ => {}
+        }
+        if let Some(s) = surely.keys().find(|s| !step.output_keys.contains(s)) {
+            return Some(Divergence::MissingOutput(s.clone()));
+        }
+        step.output_keys
+            .iter()
+            .find(|s| !maybe.contains_key(*s))
+            .map(|s| Divergence::UnexpectedOutput(s.clone()))
+    }
+}
+
+/// Check `trace` against `graph`, step by step, and report the first divergence, if any.
+///
+/// The first step must be the start node, and every later step must be entered along an edge from an earlier
+/// step, whose outputs contain its input keys. Each step moves on once, except that a fork moves on to each of
+/// its branches. A step needs the required inputs of its node, and outputs what the output schema, or the
+/// schema of its failure, outputs.
+pub fn check_conformance(graph: &WorkflowGraph, trace: &[LoggedStep]) -> Option<Nonconformance> {
+    let nodes = trace
+        .iter()
+        .map_while(|step| graph.nodes.iter().find(|node| node.name == step.node))
+        .map(|node| node.id)
+        .collect::<Vec<_>>();
+    let known = nodes.len();
+    let mut checker = Checker {
+        graph,
+        trace: &trace[..known],
+        nodes,
+        fed: vec![BTreeSet::new(); known],
+    };
+    (0..known)
+        .find_map(|pos| {
+            checker.check_step(pos).map(|divergence| Nonconformance {
+                step: pos,
+                divergence,
+            })
+        })
+        .or_else(|| {
+            trace.get(known).map(|step| Nonconformance {
+                step: known,
+                divergence: Divergence::UnknownNode(step.node.clone()),
+            })
+        })
+}
@@ -0,0 +1,18 @@
+/// An assume-guarantee contract of a node or a module.
+///
+/// Whenever the input has every key in `assumes`, a successful run outputs every key in `guarantees`.
+/// A contract says nothing about failure outcomes.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Contract {
+    pub assumes: Vec<String>,
+    pub guarantees: Vec<String>,
+}
+
+impl Contract {
+    pub fn new(assumes: Vec<String>, guarantees: Vec<String>) -> Self {
+        Self {
+            assumes,
+            guarantees,
+        }
+    }
+}
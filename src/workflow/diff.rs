@@ -0,0 +1,782 @@
+//! Differences between two versions of a workflow, whose nodes are matched by name.
+
+use std::collections::BTreeMap;
+use std::fmt;
+
+use z3::{Config, Context};
+
+use crate::verifier::GraphVerifier;
+
+use super::contract::Contract;
+use super::failure::ErrorMatch;
+use super::hash::canonical_text;
+use super::schema::{InputCond, KeyRule, OutputSchema};
+use super::value::{ValueSpec, ValueType};
+use super::{Node, NodeKind, WorkflowGraph};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum StructuralChange {
+    NodeAdded(String),
+    NodeRemoved(String),
+    KindChanged {
+        node: String,
+        before: String,
+        after: String,
+    },
+    RequiredInputsChanged {
+        node: String,
+        added: Vec<String>,
+        removed: Vec<String>,
+    },
+    FixedKeysChanged {
+        node: String,
+        added: Vec<String>,
+        removed: Vec<String>,
+    },
+    MayKeysChanged {
+        node: String,
+        added: Vec<String>,
+        removed: Vec<String>,
+    },
+    RulesChanged {
+        node: String,
+        added: Vec<(KeyRule, InputCond)>,
+        removed: Vec<(KeyRule, InputCond)>,
+    },
+    FailuresChanged {
+        node: String,
+        added: Vec<String>,
+        removed: Vec<String>,
+    },
+    /// The outputs of a failure that both versions have changed.
+    FailureOutputsChanged {
+        node: String,
+        error: String,
+    },
+    ValueSpecChanged {
+        node: String,
+        key: String,
+        before: Option<ValueSpec>,
+        after: Option<ValueSpec>,
+    },
+    ContractChanged {
+        node: String,
+        before: Option<Contract>,
+        after: Option<Contract>,
+    },
+    /// A part of the map spec or of the module of a node changed: a key, the end node, the declared inputs or
+    /// outputs, or the nested graph, which is compared by its `canonical_text`.
+    NestedChanged {
+        node: String,
+        part: String,
+        before: String,
+        after: String,
+    },
+    /// The contract of the module run by a sub-workflow node changed.
+    ModuleContractChanged {
+        node: String,
+        before: Option<Contract>,
+        after: Option<Contract>,
+    },
+    CompensationChanged {
+        node: String,
+        before: Option<String>,
+        after: Option<String>,
+    },
+    EdgeAdded {
+        src: String,
+        dst: String,
+    },
+    EdgeRemoved {
+        src: String,
+        dst: String,
+    },
+    EdgeConditionsChanged {
+        src: String,
+        dst: String,
+        before: Vec<InputCond>,
+        after: Vec<InputCond>,
+    },
+    CatchEdgeAdded {
+        src: String,
+        dst: String,
+    },
+    CatchEdgeRemoved {
+        src: String,
+        dst: String,
+    },
+    CatchErrorsChanged {
+        src: String,
+        dst: String,
+        before: ErrorMatch,
+        after: ErrorMatch,
+    },
+    KeyTypeChanged {
+        key: String,
+        before: Option<ValueType>,
+        after: Option<ValueType>,
+    },
+    StartChanged {
+        before: Option<String>,
+        after: Option<String>,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SemanticChange {
+    BecameReachable(String),
+    BecameUnreachable(String),
+    /// The minimum input set of a node that is reachable in both versions changed size.
+    MinimumInputsChanged {
+        node: String,
+        before: Vec<String>,
+        after: Vec<String>,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct WorkflowDiff {
+    pub structural: Vec<StructuralChange>,
+    pub semantic: Vec<SemanticChange>,
+}
+
+impl WorkflowDiff {
+    pub fn is_empty(&self) -> bool {
+        self.structural.is_empty() && self.semantic.is_empty()
+    }
+}
+
+fn kind_name(kind: &NodeKind) -> String {
+    match kind {
+        NodeKind::Task => "task".to_string(),
+        NodeKind::Fork => "fork".to_string(),
+        NodeKind::Join => "join".to_string(),
+        NodeKind::Map(_) => "map".to_string(),
+        NodeKind::SubWorkflow(module) => format!("sub-workflow {}", module.name),
+    }
+}
+
+/// (elements of `after` not in `before`, elements of `before` not in `after`), in their original order.
+fn added_removed<T: PartialEq + Clone>(before: &[T], after: &[T]) -> (Vec<T>, Vec<T>) {
+    let added = after
+        .iter()
+        .filter(|x| !before.contains(x))
+        .cloned()
+        .collect();
+    let removed = before
+        .iter()
+        .filter(|x| !after.contains(x))
+        .cloned()
+        .collect();
+    (added, removed)
+}
+
+/// Whether two schemas output the same keys in the same way, whatever the order of their rules.
+fn same_schema(before: &OutputSchema, after: &OutputSchema) -> bool {
+    let (added, removed) = added_removed(&before.dynamic_keys, &after.dynamic_keys);
+    before.fixed_keys == after.fixed_keys
+        && before.may_keys == after.may_keys
+        && added.is_empty()
+        && removed.is_empty()
+        && before.value_specs == after.value_specs
+}
+
+/// The keys whose entries differ between `before` and `after`, with both entries.
+fn changed_entries<'a, T: PartialEq + Clone>(
+    before: &'a BTreeMap<String, T>,
+    after: &'a BTreeMap<String, T>,
+) -> Vec<(String, Option<T>, Option<T>)> {
+    before
+        .keys()
+        .chain(after.keys().filter(|s| !before.contains_key(*s)))
+        .filter(|s| before.get(*s) != after.get(*s))
+        .map(|s| (s.clone(), before.get(s).cloned(), after.get(s).cloned()))
+        .collect()
+}
+
+fn sorted_list(keys: &[String]) -> String {
+    let mut keys = keys.to_vec();
+    keys.sort();
+    format!("[{}]", keys.join(", "))
+}
+
+/// The parts of the map spec or module of `node` that its canonical hash covers, as (part, value).
+fn nested_parts(node: &Node) -> Vec<(&'static str, String)> {
+    match &node.kind {
+        NodeKind::Map(spec) => vec![
+            ("items key", spec.items_key.clone()),
+            ("item key", spec.item_key.clone()),
+            ("results key", spec.results_key.clone()),
+            (
+                "iterator end",
+                spec.iterator.nodes[spec.iterator_end].name.clone(),
+            ),
+            ("iterator graph", canonical_text(&spec.iterator)),
+        ],
+        NodeKind::SubWorkflow(module) => vec![
+            ("module inputs", sorted_list(&module.inputs)),
+            ("module outputs", sorted_list(&module.outputs)),
+            ("module end", module.graph.nodes[module.end].name.clone()),
+            ("module graph", canonical_text(&module.graph)),
+        ],
+        _ => vec![],
+    }
+}
+
+fn node_changes(
+    before_graph: &WorkflowGraph,
+    before: &Node,
+    after_graph: &WorkflowGraph,
+    after: &Node,
+) -> Vec<StructuralChange> {
+    let node = before.name.clone();
+    let mut changes = vec![];
+    let (kind_before, kind_after) = (kind_name(&before.kind), kind_name(&after.kind));
+    if kind_before != kind_after {
+        changes.push(StructuralChange::KindChanged {
+            node: node.clone(),
+            before: kind_before,
+            after: kind_after,
+        });
+    }
+    let (added, removed) = added_removed(&before.required_inputs, &after.required_inputs);
+    if !added.is_empty() || !removed.is_empty() {
+        changes.push(StructuralChange::RequiredInputsChanged {
+            node: node.clone(),
+            added,
+            removed,
+        });
+    }
+    let (added, removed) = added_removed(
+        &before.output_schema.fixed_keys,
+        &after.output_schema.fixed_keys,
+    );
+    if !added.is_empty() || !removed.is_empty() {
+        changes.push(StructuralChange::FixedKeysChanged {
+            node: node.clone(),
+            added,
+            removed,
+        });
+    }
+    let (added, removed) = added_removed(
+        &before.output_schema.may_keys,
+        &after.output_schema.may_keys,
+    );
+    if !added.is_empty() || !removed.is_empty() {
+        changes.push(StructuralChange::MayKeysChanged {
+            node: node.clone(),
+            added,
+            removed,
+        });
+    }
+    let (added, removed) = added_removed(
+        &before.output_schema.dynamic_keys,
+        &after.output_schema.dynamic_keys,
+    );
+    if !added.is_empty() || !removed.is_empty() {
+        changes.push(StructuralChange::RulesChanged {
+            node: node.clone(),
+            added,
+            removed,
+        });
+    }
+    let errors = |node: &Node| {
+        node.failures
+            .iter()
+            .map(|failure| failure.error.clone())
+            .collect::<Vec<_>>()
+    };
+    let (added, removed) = added_removed(&errors(before), &errors(after));
+    if !added.is_empty() || !removed.is_empty() {
+        changes.push(StructuralChange::FailuresChanged {
+            node: node.clone(),
+            added,
+            removed,
+        });
+    }
+    before.failures.iter().for_each(|failure| {
+        let Some(f) = after.failures.iter().find(|f| f.error == failure.error) else {
+            return;
+        };
+        if !same_schema(&failure.output_schema, &f.output_schema) {
+            changes.push(StructuralChange::FailureOutputsChanged {
+                node: node.clone(),
+                error: failure.error.clone(),
+            });
+        }
+    });
+    changed_entries(
+        &before.output_schema.value_specs,
+        &after.output_schema.value_specs,
+    )
+    .into_iter()
+    .for_each(|(key, b, a)| {
+        changes.push(StructuralChange::ValueSpecChanged {
+            node: node.clone(),
+            key,
+            before: b,
+            after: a,
+        })
+    });
+    if before.contract != after.contract {
+        changes.push(StructuralChange::ContractChanged {
+            node: node.clone(),
+            before: before.contract.clone(),
+            after: after.contract.clone(),
+        });
+    }
+    let module_contract = |node: &Node| match &node.kind {
+        NodeKind::SubWorkflow(module) => module.contract.clone(),
+        _ => None,
+    };
+    // a change of module is already a change of kind
+    if kind_name(&before.kind) == kind_name(&after.kind) {
+        nested_parts(before)
+            .into_iter()
+            .zip(nested_parts(after))
+            .filter(|((_, b), (_, a))| b != a)
+            .for_each(|((part, b), (_, a))| {
+                changes.push(StructuralChange::NestedChanged {
+                    node: node.clone(),
+                    part: part.to_string(),
+                    before: b,
+                    after: a,
+                })
+            });
+        if module_contract(before) != module_contract(after) {
+            changes.push(StructuralChange::ModuleContractChanged {
+                node: node.clone(),
+                before: module_contract(before),
+                after: module_contract(after),
+            });
+        }
+    }
+    let (compensation_before, compensation_after) = (
+        before
+            .compensation
+            .map(|i| before_graph.nodes[i].name.clone()),
+        after
+            .compensation
+            .map(|i| after_graph.nodes[i].name.clone()),
+    );
+    if compensation_before != compensation_after {
+        changes.push(StructuralChange::CompensationChanged {
+            node,
+            before: compensation_before,
+            after: compensation_after,
+        });
+    }
+    changes
+}
+
+/// A success edge by the names of its endpoints and its conditions as a set, as in `canonical_text`.
+type EdgeKey<'a> = (&'a str, &'a str, Vec<String>);
+
+/// The success edges of `graph` by their keys, with their conditions. Parallel edges with the same key are
+/// all kept.
+fn named_edges(graph: &WorkflowGraph) -> BTreeMap<EdgeKey<'_>, Vec<&[InputCond]>> {
+    let mut edges = BTreeMap::<_, Vec<_>>::new();
+    graph.adj_list.iter().enumerate().for_each(|(src, adj)| {
+        adj.iter().for_each(|(dst, conditions)| {
+            let mut set = conditions
+                .iter()
+                .map(|cond| format!("{cond:?}"))
+                .collect::<Vec<_>>();
+            set.sort();
+            set.dedup();
+            let key = (
+                graph.nodes[src].name.as_str(),
+                graph.nodes[*dst].name.as_str(),
+                set,
+            );
+            edges.entry(key).or_default().push(conditions.as_slice());
+        })
+    });
+    edges
+}
+
+/// The edges of `edges` that `other` does not have as many times, by the names of their endpoints.
+fn unmatched_edges<'a>(
+    edges: &BTreeMap<EdgeKey<'a>, Vec<&'a [InputCond]>>,
+    other: &BTreeMap<EdgeKey<'a>, Vec<&'a [InputCond]>>,
+) -> BTreeMap<(&'a str, &'a str), Vec<&'a [InputCond]>> {
+    let mut unmatched = BTreeMap::<_, Vec<_>>::new();
+    edges.iter().for_each(|(key, conditions)| {
+        let matched = other.get(key).map_or(0, Vec::len);
+        unmatched
+            .entry((key.0, key.1))
+            .or_default()
+            .extend(conditions.iter().skip(matched).copied());
+    });
+    unmatched.retain(|_, conditions| !conditions.is_empty());
+    unmatched
+}
+
+/// The catch edges of `graph` by the names of their endpoints, with the errors they catch.
+fn named_catch_edges(graph: &WorkflowGraph) -> BTreeMap<(&str, &str), &ErrorMatch> {
+    graph
+        .catch_list
+        .iter()
+        .enumerate()
+        .flat_map(|(src, edges)| {
+            edges.iter().map(move |(dst, errors)| {
+                (
+                    (
+                        graph.nodes[src].name.as_str(),
+                        graph.nodes[*dst].name.as_str(),
+                    ),
+                    errors,
+                )
+            })
+        })
+        .collect()
+}
+
+fn structural_changes(before: &WorkflowGraph, after: &WorkflowGraph) -> Vec<StructuralChange> {
+    let mut changes = vec![];
+    let start_name = |graph: &WorkflowGraph| graph.start.map(|i| graph.nodes[i].name.clone());
+    if start_name(before) != start_name(after) {
+        changes.push(StructuralChange::StartChanged {
+            before: start_name(before),
+            after: start_name(after),
+        });
+    }
+    changed_entries(&before.key_types, &after.key_types)
+        .into_iter()
+        .for_each(|(key, b, a)| {
+            changes.push(StructuralChange::KeyTypeChanged {
+                key,
+                before: b,
+                after: a,
+            })
+        });
+    before.nodes.iter().for_each(
+        |node| match after.nodes.iter().find(|n| n.name == node.name) {
+            Some(n) => changes.extend(node_changes(before, node, after, n)),
+            None => changes.push(StructuralChange::NodeRemoved(node.name.clone())),
+        },
+    );
+    after
+        .nodes
+        .iter()
+        .filter(|node| !before.nodes.iter().any(|n| n.name == node.name))
+        .for_each(|node| changes.push(StructuralChange::NodeAdded(node.name.clone())));
+
+    // an edge that is only in one version is paired with one between the same nodes in the other version, if
+    // any, as a change of conditions
+    let (edges_before, edges_after) = (named_edges(before), named_edges(after));
+    let removed = unmatched_edges(&edges_before, &edges_after);
+    let mut added = unmatched_edges(&edges_after, &edges_before);
+    removed.into_iter().for_each(|((src, dst), conditions)| {
+        let mut paired = added.remove(&(src, dst)).unwrap_or_default().into_iter();
+        conditions
+            .into_iter()
+            .for_each(|conditions| match paired.next() {
+                Some(c) => changes.push(StructuralChange::EdgeConditionsChanged {
+                    src: src.to_string(),
+                    dst: dst.to_string(),
+                    before: conditions.to_vec(),
+                    after: c.to_vec(),
+                }),
+                None => changes.push(StructuralChange::EdgeRemoved {
+                    src: src.to_string(),
+                    dst: dst.to_string(),
+                }),
+            });
+        paired.for_each(|_| {
+            changes.push(StructuralChange::EdgeAdded {
+                src: src.to_string(),
+                dst: dst.to_string(),
+            })
+        });
+    });
+    added.into_iter().for_each(|((src, dst), conditions)| {
+        conditions.iter().for_each(|_| {
+            changes.push(StructuralChange::EdgeAdded {
+                src: src.to_string(),
+                dst: dst.to_string(),
+            })
+        })
+    });
+
+    let (catch_before, catch_after) = (named_catch_edges(before), named_catch_edges(after));
+    catch_before
+        .iter()
+        .for_each(|((src, dst), errors)| match catch_after.get(&(src, dst)) {
+            Some(e) if e != errors => changes.push(StructuralChange::CatchErrorsChanged {
+                src: src.to_string(),
+                dst: dst.to_string(),
+                before: (*errors).clone(),
+                after: (*e).clone(),
+            }),
+            Some(_) => {}
+            None => changes.push(StructuralChange::CatchEdgeRemoved {
+                src: src.to_string(),
+                dst: dst.to_string(),
+            }),
+        });
+    catch_after
+        .keys()
+        .filter(|edge| !catch_before.contains_key(edge))
+        .for_each(|(src, dst)| {
+            changes.push(StructuralChange::CatchEdgeAdded {
+                src: src.to_string(),
+                dst: dst.to_string(),
+            })
+        });
+    changes
+}
+
+/// For every node name, whether it is reachable and its minimum input set, if it is.
+fn reachability(graph: &WorkflowGraph) -> BTreeMap<&str, Option<Vec<String>>> {
+    if graph.start.is_none() {
+        return graph
+            .nodes
+            .iter()
+            .map(|n| (n.name.as_str(), None))
+            .collect();
+    }
+    let ctx = Context::new(&Config::default());
+    let graph_verifier = GraphVerifier::new(graph, &ctx);
+    graph
+        .nodes
+        .iter()
+        .map(|node| {
+            let min_inputs =
+                graph_verifier
+                    .minimum_input_set_for_reachable(node.id)
+                    .map(|(mut keys, _)| {
+                        keys.sort();
+                        keys
+                    });
+            (node.name.as_str(), min_inputs)
+        })
+        .collect()
+}
+
+fn semantic_changes(before: &WorkflowGraph, after: &WorkflowGraph) -> Vec<SemanticChange> {
+    let (reach_before, reach_after) = (reachability(before), reachability(after));
+    let mut changes = vec![];
+    // nodes that only exist in one version are reported as structural changes
+    reach_before.iter().for_each(|(node, min_before)| {
+        let Some(min_after) = reach_after.get(node) else {
+            return;
+        };
+        let node = node.to_string();
+        match (min_before, min_after) {
+            (None, Some(_)) => changes.push(SemanticChange::BecameReachable(node)),
+            (Some(_), None) => changes.push(SemanticChange::BecameUnreachable(node)),
+            (Some(b), Some(a)) if b.len() != a.len() => {
+                changes.push(SemanticChange::MinimumInputsChanged {
+                    node,
+                    before: b.clone(),
+                    after: a.clone(),
+                })
+            }
+            _ => {}
+        }
+    });
+    changes
+}
+
+/// Compare two versions of a workflow, matching nodes by name.
+pub fn diff(before: &WorkflowGraph, after: &WorkflowGraph) -> WorkflowDiff {
+    WorkflowDiff {
+        structural: structural_changes(before, after),
+        semantic: semantic_changes(before, after),
+    }
+}
+
+impl fmt::Display for StructuralChange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let list = |keys: &[String]| keys.join(", ");
+        let rules = |rules: &[(KeyRule, InputCond)]| {
+            rules
+                .iter()
+                .map(|(rule, cond)| format!("{:?} if {:?}", rule, cond))
+                .collect::<Vec<_>>()
+                .join(", ")
+        };
+        match self {
+            StructuralChange::NodeAdded(node) => write!(f, "+ node {}", node),
+            StructuralChange::NodeRemoved(node) => write!(f, "- node {}", node),
+            StructuralChange::KindChanged {
+                node,
+                before,
+                after,
+            } => write!(f, "~ node {}: {} -> {}", node, before, after),
+            StructuralChange::RequiredInputsChanged {
+                node,
+                added,
+                removed,
+            } => write!(
+                f,
+                "~ node {}: required inputs +[{}] -[{}]",
+                node,
+                list(added),
+                list(removed)
+            ),
+            StructuralChange::FixedKeysChanged {
+                node,
+                added,
+                removed,
+            } => write!(
+                f,
+                "~ node {}: fixed keys +[{}] -[{}]",
+                node,
+                list(added),
+                list(removed)
+            ),
+            StructuralChange::MayKeysChanged {
+                node,
+                added,
+                removed,
+            } => write!(
+                f,
+                "~ node {}: may keys +[{}] -[{}]",
+                node,
+                list(added),
+                list(removed)
+            ),
+            StructuralChange::RulesChanged {
+                node,
+                added,
+                removed,
+            } => write!(
+                f,
+                "~ node {}: rules +[{}] -[{}]",
+                node,
+                rules(added),
+                rules(removed)
+            ),
+            StructuralChange::FailuresChanged {
+                node,
+                added,
+                removed,
+            } => write!(
+                f,
+                "~ node {}: failures +[{}] -[{}]",
+                node,
+                list(added),
+                list(removed)
+            ),
+            StructuralChange::FailureOutputsChanged { node, error } => {
+                write!(f, "~ node {}: outputs on failure {}", node, error)
+            }
+            StructuralChange::ValueSpecChanged {
+                node,
+                key,
+                before,
+                after,
+            } => write!(
+                f,
+                "~ node {}: value of {} {:?} -> {:?}",
+                node, key, before, after
+            ),
+            StructuralChange::ContractChanged {
+                node,
+                before,
+                after,
+            } => write!(f, "~ node {}: contract {:?} -> {:?}", node, before, after),
+            StructuralChange::NestedChanged { node, part, .. } if part.ends_with("graph") => {
+                write!(f, "~ node {}: {} changed", node, part)
+            }
+            StructuralChange::NestedChanged {
+                node,
+                part,
+                before,
+                after,
+            } => write!(f, "~ node {}: {} {} -> {}", node, part, before, after),
+            StructuralChange::ModuleContractChanged {
+                node,
+                before,
+                after,
+            } => write!(
+                f,
+                "~ node {}: module contract {:?} -> {:?}",
+                node, before, after
+            ),
+            StructuralChange::CompensationChanged {
+                node,
+                before,
+                after,
+            } => write!(
+                f,
+                "~ node {}: compensation {:?} -> {:?}",
+                node, before, after
+            ),
+            StructuralChange::EdgeAdded { src, dst } => write!(f, "+ edge {} -> {}", src, dst),
+            StructuralChange::EdgeRemoved { src, dst } => write!(f, "- edge {} -> {}", src, dst),
+            StructuralChange::EdgeConditionsChanged {
+                src,
+                dst,
+                before,
+                after,
+            } => write!(
+                f,
+                "~ edge {} -> {}: conditions {:?} -> {:?}",
+                src, dst, before, after
+            ),
+            StructuralChange::CatchEdgeAdded { src, dst } => {
+                write!(f, "+ catch edge {} -> {}", src, dst)
+            }
+            StructuralChange::CatchEdgeRemoved { src, dst } => {
+                write!(f, "- catch edge {} -> {}", src, dst)
+            }
+            StructuralChange::CatchErrorsChanged {
+                src,
+                dst,
+                before,
+                after,
+            } => write!(
+                f,
+                "~ catch edge {} -> {}: errors {:?} -> {:?}",
+                src, dst, before, after
+            ),
+            StructuralChange::KeyTypeChanged { key, before, after } => {
+                write!(f, "~ type of {}: {:?} -> {:?}", key, before, after)
+            }
+            StructuralChange::StartChanged { before, after } => {
+                write!(f, "~ start: {:?} -> {:?}", before, after)
+            }
+        }
+    }
+}
+
+impl fmt::Display for SemanticChange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SemanticChange::BecameReachable(node) => write!(f, "+ {} is now reachable", node),
+            SemanticChange::BecameUnreachable(node) => {
+                write!(f, "- {} is no longer reachable", node)
+            }
+            SemanticChange::MinimumInputsChanged {
+                node,
+                before,
+                after,
+            } => write!(
+                f,
+                "~ {} needs [{}] instead of [{}]",
+                node,
+                after.join(", "),
+                before.join(", ")
+            ),
+        }
+    }
+}
+
+impl fmt::Display for WorkflowDiff {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.is_empty() {
+            return writeln!(f, "no changes");
+        }
+        writeln!(f, "structural changes:")?;
+        self.structural
+            .iter()
+            .try_for_each(|change| writeln!(f, "  {}", change))?;
+        writeln!(f, "semantic changes:")?;
+        self.semantic
+            .iter()
+            .try_for_each(|change| writeln!(f, "  {}", change))
+    }
+}
@@ -0,0 +1,34 @@
+use super::schema::OutputSchema;
+
+/// Which errors a catch edge or a retry policy applies to.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ErrorMatch {
+    All,
+    Named(Vec<String>),
+}
+
+impl ErrorMatch {
+    pub fn matches(&self, error: &str) -> bool {
+        match self {
+            ErrorMatch::All => true,
+            ErrorMatch::Named(errors) => errors.iter().any(|e| e == error),
+        }
+    }
+}
+
+/// A way a node can fail. On failure, the node outputs `output_schema` instead of its usual output schema.
+#[derive(Debug, Clone)]
+pub struct Failure {
+    pub error: String,
+    pub output_schema: OutputSchema,
+}
+
+/// Run the node again, at most `max_attempts` times, when it fails with a matching error.
+///
+/// Retries do not change which outcomes are possible: a node that may fail may still fail after the
+/// last attempt. So the policy is only metadata of the model, and the verifier and the simulator ignore it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RetryPolicy {
+    pub errors: ErrorMatch,
+    pub max_attempts: usize,
+}
@@ -0,0 +1,152 @@
+//! A hash of a workflow that does not depend on the order nodes, edges, keys and rules were added in.
+//!
+//! Nodes are identified by name, as in `diff`. The hash is stable across runs and builds, so it can key results
+//! stored on disk.
+
+use std::fmt::Write;
+
+use super::contract::Contract;
+use super::schema::OutputSchema;
+use super::{NodeIdx, NodeKind, WorkflowGraph};
+
+/// The canonical hash of `graph`. Graphs that only differ in the order of their nodes, edges, keys, rules or
+/// conditions have the same hash. The order of catch edges and retry policies of a node matters, since the
+/// first matching one applies.
+pub fn canonical_hash(graph: &WorkflowGraph) -> u64 {
+    // FNV-1a
+    canonical_text(graph)
+        .bytes()
+        .fold(0xcbf29ce484222325, |hash, byte| {
+            (hash ^ byte as u64).wrapping_mul(0x100000001b3)
+        })
+}
+
+/// The canonical description of `graph` that `canonical_hash` hashes. Two graphs have the same text iff they
+/// only differ in the order of their nodes, edges, keys, rules or conditions.
+pub fn canonical_text(graph: &WorkflowGraph) -> String {
+    let mut text = String::new();
+    write_graph(&mut text, graph);
+    text
+}
+
+fn sorted<T: Ord>(items: impl IntoIterator<Item = T>) -> Vec<T> {
+    let mut items = items.into_iter().collect::<Vec<_>>();
+    items.sort();
+    items
+}
+
+/// Write a canonical description of `graph` to `out`. Strings are written with `Debug`, so they are quoted and
+/// escaped and cannot run into each other.
+fn write_graph(out: &mut String, graph: &WorkflowGraph) {
+    let name = |node: NodeIdx| graph.nodes[node].name.as_str();
+    let mut nodes = graph.nodes.iter().collect::<Vec<_>>();
+    nodes.sort_by(|a, b| a.name.cmp(&b.name));
+    for node in nodes {
+        write!(out, "node {:?} ", node.name).unwrap();
+        match &node.kind {
+            NodeKind::Task => out.push_str("task"),
+            NodeKind::Fork => out.push_str("fork"),
+            NodeKind::Join => out.push_str("join"),
+            NodeKind::Map(spec) => {
+                write!(
+                    out,
+                    "map {:?} {:?} {:?} end {:?} {{",
+                    spec.items_key,
+                    spec.item_key,
+                    spec.results_key,
+                    spec.iterator.nodes[spec.iterator_end].name
+                )
+                .unwrap();
+                write_graph(out, &spec.iterator);
+                out.push('}');
+            }
+            NodeKind::SubWorkflow(module) => {
+                write!(
+                    out,
+                    "module {:?} {:?} {:?} end {:?} ",
+                    module.name,
+                    sorted(&module.inputs),
+                    sorted(&module.outputs),
+                    module.graph.nodes[module.end].name
+                )
+                .unwrap();
+                write_contract(out, module.contract.as_ref());
+                out.push('{');
+                write_graph(out, &module.graph);
+                out.push('}');
+            }
+        }
+        write!(out, " inputs {:?} ", sorted(&node.required_inputs)).unwrap();
+        write_schema(out, &node.output_schema);
+        let mut failures = node.failures.iter().collect::<Vec<_>>();
+        failures.sort_by(|a, b| a.error.cmp(&b.error));
+        for failure in failures {
+            write!(out, " failure {:?} ", failure.error).unwrap();
+            write_schema(out, &failure.output_schema);
+        }
+        for retry in &node.retries {
+            write!(out, " retry {:?} {}", retry.errors, retry.max_attempts).unwrap();
+        }
+        write_contract(out, node.contract.as_ref());
+        if let Some(compensation) = node.compensation {
+            write!(out, " compensation {:?}", name(compensation)).unwrap();
+        }
+        out.push('\n');
+    }
+
+    let edges = sorted(graph.adj_list.iter().enumerate().flat_map(|(src, adj)| {
+        adj.iter().map(move |(dst, conditions)| {
+            let conditions = sorted(conditions.iter().map(|cond| format!("{cond:?}")));
+            (name(src), name(*dst), conditions)
+        })
+    }));
+    for (src, dst, conditions) in edges {
+        writeln!(out, "edge {src:?} {dst:?} {conditions:?}").unwrap();
+    }
+    let mut catch_sources = graph
+        .catch_list
+        .iter()
+        .enumerate()
+        .filter(|(_, catches)| !catches.is_empty())
+        .collect::<Vec<_>>();
+    catch_sources.sort_by_key(|(src, _)| name(*src));
+    for (src, catches) in catch_sources {
+        for (dst, errors) in catches {
+            writeln!(out, "catch {:?} {:?} {errors:?}", name(src), name(*dst)).unwrap();
+        }
+    }
+    writeln!(out, "start {:?}", graph.start.map(name)).unwrap();
+    for (key, ty) in &graph.key_types {
+        writeln!(out, "type {key:?} {ty:?}").unwrap();
+    }
+}
+
+fn write_schema(out: &mut String, schema: &OutputSchema) {
+    let rules = sorted(
+        schema
+            .dynamic_keys()
+            .iter()
+            .map(|(rule, cond)| format!("{rule:?} {cond:?}")),
+    );
+    write!(
+        out,
+        "fixed {:?} may {:?} rules {:?} values {:?}",
+        sorted(schema.fixed_keys()),
+        sorted(schema.may_keys()),
+        rules,
+        schema.value_specs
+    )
+    .unwrap();
+}
+
+fn write_contract(out: &mut String, contract: Option<&Contract>) {
+    if let Some(contract) = contract {
+        write!(
+            out,
+            " contract {:?} {:?}",
+            sorted(&contract.assumes),
+            sorted(&contract.guarantees)
+        )
+        .unwrap();
+    }
+}
@@ -0,0 +1,357 @@
+//! Draft a workflow model from logged executions.
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use super::conformance::LoggedStep;
+use super::failure::ErrorMatch;
+use super::path::{self, ParsePathError};
+use super::schema::{InputCond, KeyRule, OutputSchema};
+use super::{NodeIdx, WorkflowGraph};
+
+/// A part of a node model learned from its calls.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Inferred {
+    RequiredInput(String),
+    FixedKey(String),
+    Rule(KeyRule, InputCond),
+}
+
+/// How well the calls of a node support an inferred part of its model.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Annotation {
+    pub node_idx: NodeIdx,
+    /// The error of the failure whose output schema the part belongs to, or `None` for the node itself.
+    pub error: Option<String>,
+    pub inferred: Inferred,
+    /// Fraction of the calls that agree with the part.
+    pub confidence: f64,
+    /// Number of calls the part was learned from.
+    pub calls: usize,
+}
+
+/// A draft model, to be verified and reviewed.
+#[derive(Debug, Clone)]
+pub struct DraftGraph {
+    pub graph: WorkflowGraph,
+    pub annotations: Vec<Annotation>,
+    /// Output keys that no inferred part explains, as (node, error, key).
+    pub unexplained_outputs: Vec<(NodeIdx, Option<String>, String)>,
+    /// Logged keys that are not valid paths, as (node, error). They are left out of the draft.
+    pub invalid_keys: Vec<(NodeIdx, ParsePathError)>,
+    /// Moves seen in the traces that are not edges of the draft because they would close a cycle, as (source,
+    /// target, error of the source). A retried node has one to itself.
+    pub back_edges: Vec<(NodeIdx, NodeIdx, Option<String>)>,
+}
+
+/// The input and output keys of one call.
+type Call = (BTreeSet<String>, BTreeSet<String>);
+
+/// Fraction of `calls` for which `agrees` holds. No calls agree with everything.
+fn fraction(calls: &[&Call], agrees: impl Fn(&Call) -> bool) -> f64 {
+    if calls.is_empty() {
+        return 1.0;
+    }
+    calls.iter().filter(|call| agrees(call)).count() as f64 / calls.len() as f64
+}
+
+/// Infer an output schema from `calls`, keeping the parts with at least `min_confidence`.
+/// Returns the schema, its parts with their confidence, and the output keys it does not explain.
+fn infer_schema(
+    calls: &[&Call],
+    min_confidence: f64,
+) -> (OutputSchema, Vec<(Inferred, f64)>, BTreeSet<String>) {
+    let mut parts = vec![];
+    let input_keys = calls
+        .iter()
+        .flat_map(|(inputs, _)| inputs.iter())
+        .collect::<BTreeSet<_>>();
+
+    // carried keys, either all of them or one by one
+    let carry_all = fraction(calls, |(inputs, outputs)| inputs.is_subset(outputs));
+    if carry_all >= min_confidence {
+        parts.push((
+            Inferred::Rule(KeyRule::Identity, InputCond::Always),
+            carry_all,
+        ));
+    } else {
+        input_keys.iter().for_each(|s| {
+            let with_key = calls
+                .iter()
+                .filter(|(inputs, _)| inputs.contains(*s))
+                .copied()
+                .collect::<Vec<_>>();
+            let confidence = fraction(&with_key, |(_, outputs)| outputs.contains(*s));
+            if confidence >= min_confidence {
+                parts.push((
+                    Inferred::Rule(KeyRule::Identity, InputCond::MatchesKey(s.to_string())),
+                    confidence,
+                ));
+            }
+        });
+    }
+
+    // prefixes added to every input key
+    let prefixes = calls
+        .iter()
+        .flat_map(|(inputs, outputs)| {
+            outputs
+                .iter()
+                .filter(|o| !inputs.contains(*o))
+                .flat_map(move |o| {
+                    inputs.iter().filter_map(move |s| {
+                        o.strip_suffix(s.as_str())
+                            .filter(|prefix| !prefix.is_empty())
+                            .map(|prefix| prefix.to_string())
+                    })
+                })
+        })
+        .collect::<BTreeSet<_>>();
+    prefixes.into_iter().for_each(|prefix| {
+        let confidence = fraction(calls, |(inputs, outputs)| {
+            inputs
+                .iter()
+                .all(|s| outputs.contains(&format!("{prefix}{s}")))
+        });
+        if confidence >= min_confidence {
+            parts.push((
+                Inferred::Rule(KeyRule::IdWithPrefix(prefix), InputCond::Always),
+                confidence,
+            ));
+        }
+    });
+
+    // the remaining outputs are produced by the node, always or when some input key is present
+    let explained = |inputs: &BTreeSet<String>, o: &str| {
+        parts.iter().any(|(part, _)| match part {
+            Inferred::Rule(KeyRule::Identity, InputCond::Always) => inputs.contains(o),
+            Inferred::Rule(KeyRule::Identity, InputCond::MatchesKey(s)) => s == o,
+            Inferred::Rule(KeyRule::IdWithPrefix(prefix), _) => o
+                .strip_prefix(prefix.as_str())
+                .is_some_and(|s| inputs.contains(s)),
+            _ => false,
+        })
+    };
+    let produced = calls
+        .iter()
+        .flat_map(|(inputs, outputs)| outputs.iter().filter(|o| !explained(inputs, o)))
+        .cloned()
+        .collect::<BTreeSet<_>>();
+    let mut unexplained = BTreeSet::new();
+    produced.into_iter().for_each(|o| {
+        let always = fraction(calls, |(_, outputs)| outputs.contains(&o));
+        if always >= min_confidence {
+            parts.push((Inferred::FixedKey(o), always));
+            return;
+        }
+        let best_cond = input_keys
+            .iter()
+            .map(|s| {
+                let confidence = fraction(calls, |(inputs, outputs)| {
+                    inputs.contains(*s) == outputs.contains(&o)
+                });
+                (s, confidence)
+            })
+            .max_by(|(_, c1), (_, c2)| c1.total_cmp(c2));
+        match best_cond {
+            Some((s, confidence)) if confidence >= min_confidence => parts.push((
+                Inferred::Rule(KeyRule::Fixed(o), InputCond::MatchesKey(s.to_string())),
+                confidence,
+            )),
+            _ => {
+                unexplained.insert(o);
+            }
+        }
+    });
+
+    let schema = parts
+        .iter()
+        .fold(OutputSchema::new(), |builder, (part, _)| match part {
+            Inferred::FixedKey(s) => builder.add_fixed(s.clone()),
+            Inferred::Rule(rule, cond) => {
+                builder.add_rule_for_every_input(rule.clone(), cond.clone())
+            }
+            Inferred::RequiredInput(_) => builder,
+        })
+        .build();
+    (schema, parts, unexplained)
+}
+
+/// The index in `trace` of the step that moved on to the `pos`-th step: the last earlier step whose output keys
+/// contain all of its input keys, or else the previous step.
+fn source_step(trace: &[LoggedStep], pos: usize) -> usize {
+    (0..pos)
+        .rev()
+        .find(|p| {
+            trace[pos]
+                .input_keys
+                .iter()
+                .all(|s| trace[*p].output_keys.contains(s))
+        })
+        .unwrap_or(pos - 1)
+}
+
+/// Whether `dst` can be reached from `src` along success and catch edges.
+fn has_path(graph: &WorkflowGraph, src: NodeIdx, dst: NodeIdx) -> bool {
+    let mut visited = vec![false; graph.nodes.len()];
+    let mut stack = vec![src];
+    while let Some(node) = stack.pop() {
+        if node == dst {
+            return true;
+        }
+        if std::mem::replace(&mut visited[node], true) {
+            continue;
+        }
+        let success_children = graph.adj_list[node].iter().map(|(child, _)| *child);
+        let catch_children = graph.catch_list[node].iter().map(|(child, _)| *child);
+        stack.extend(success_children.chain(catch_children));
+    }
+    false
+}
+
+/// Learn a draft model from logged executions, in the format checked by `conformance::check_conformance`.
+///
+/// Every node is a task. Required inputs are the keys present in every successful call, and output schemas
+/// combine carried keys, prefixed keys and produced keys. Only parts that agree with at least `min_confidence`
+/// of the calls are kept, so 1.0 keeps the parts that agree with every call. An edge is added from the step
+/// that provided the inputs of each step, which is a catch edge if that step failed, unless it would close a
+/// cycle; such moves, like retries, are reported in `back_edges` instead. The start node is the first node of
+/// the first trace. Logged keys that are not valid paths are ignored, and reported in `invalid_keys`.
+pub fn infer_graph(traces: &[Vec<LoggedStep>], min_confidence: f64) -> DraftGraph {
+    let mut names: Vec<&str> = vec![];
+    traces.iter().flatten().for_each(|step| {
+        if !names.contains(&step.node.as_str()) {
+            names.push(&step.node);
+        }
+    });
+    let node_idx = |name: &str| names.iter().position(|n| *n == name).unwrap();
+    let mut invalid_keys = vec![];
+    let mut valid_keys = |idx, keys: &[String]| {
+        keys.iter()
+            .filter(|s| match path::validate(s) {
+                Ok(()) => true,
+                Err(e) => {
+                    if !invalid_keys.contains(&(idx, e.clone())) {
+                        invalid_keys.push((idx, e));
+                    }
+                    false
+                }
+            })
+            .cloned()
+            .collect::<BTreeSet<_>>()
+    };
+    // calls[(node, error)]
+    let mut calls = BTreeMap::<(NodeIdx, Option<String>), Vec<Call>>::new();
+    traces.iter().flatten().for_each(|step| {
+        let idx = node_idx(&step.node);
+        let call = (
+            valid_keys(idx, &step.input_keys),
+            valid_keys(idx, &step.output_keys),
+        );
+        calls
+            .entry((idx, step.error.clone()))
+            .or_default()
+            .push(call);
+    });
+
+    let mut graph = WorkflowGraph::new();
+    let mut annotations = vec![];
+    let mut unexplained_outputs = vec![];
+    let mut annotate =
+        |node_idx, error: &Option<String>, num_calls, parts: Vec<(Inferred, f64)>| {
+            annotations.extend(parts.into_iter().map(|(inferred, confidence)| Annotation {
+                node_idx,
+                error: error.clone(),
+                inferred,
+                confidence,
+                calls: num_calls,
+            }));
+        };
+    names.iter().enumerate().for_each(|(idx, name)| {
+        let successes = calls
+            .get(&(idx, None))
+            .map(|c| c.iter().collect::<Vec<_>>())
+            .unwrap_or_default();
+        let required_inputs = successes
+            .iter()
+            .flat_map(|(inputs, _)| inputs.iter())
+            .collect::<BTreeSet<_>>()
+            .into_iter()
+            .filter_map(|s| {
+                let confidence = fraction(&successes, |(inputs, _)| inputs.contains(s));
+                (confidence >= min_confidence && !successes.is_empty())
+                    .then(|| (Inferred::RequiredInput(s.clone()), confidence))
+            })
+            .collect::<Vec<_>>();
+        let (schema, mut parts, unexplained) = infer_schema(&successes, min_confidence);
+        graph.add_node(
+            name,
+            required_inputs
+                .iter()
+                .map(|(inferred, _)| match inferred {
+                    Inferred::RequiredInput(s) => s.clone(),
+                    _ => unreachable!(),
+                })
+                .collect(),
+            schema,
+        );
+        parts.splice(0..0, required_inputs);
+        annotate(idx, &None, successes.len(), parts);
+        unexplained_outputs.extend(unexplained.into_iter().map(|s| (idx, None, s)));
+    });
+    calls
+        .iter()
+        .filter_map(|((idx, error), calls)| error.as_ref().map(|error| (idx, error, calls)))
+        .for_each(|(idx, error, calls)| {
+            let (schema, parts, unexplained) =
+                infer_schema(&calls.iter().collect::<Vec<_>>(), min_confidence);
+            graph.add_failure(*idx, error, schema);
+            annotate(*idx, &Some(error.clone()), calls.len(), parts);
+            unexplained_outputs.extend(
+                unexplained
+                    .into_iter()
+                    .map(|s| (*idx, Some(error.clone()), s)),
+            );
+        });
+
+    let mut back_edges = vec![];
+    traces.iter().for_each(|trace| {
+        (1..trace.len()).for_each(|pos| {
+            let src_step = &trace[source_step(trace, pos)];
+            let (src, dst) = (node_idx(&src_step.node), node_idx(&trace[pos].node));
+            let back_edge = (src, dst, src_step.error.clone());
+            if back_edges.contains(&back_edge) {
+                return;
+            }
+            let is_edge = match &src_step.error {
+                None => graph.adj_list[src].iter().any(|(child, _)| *child == dst),
+                Some(error) => graph.catch_for(src, error).is_some(),
+            };
+            if !is_edge && has_path(&graph, dst, src) {
+                back_edges.push(back_edge);
+                return;
+            }
+            match &src_step.error {
+                None => {
+                    if !graph.adj_list[src].iter().any(|(child, _)| *child == dst) {
+                        graph.add_edge(src, dst, vec![]);
+                    }
+                }
+                Some(error) => {
+                    if graph.catch_for(src, error).is_none() {
+                        graph.add_catch(src, ErrorMatch::Named(vec![error.clone()]), dst);
+                    }
+                }
+            }
+        });
+    });
+    if let Some(step) = traces.iter().find_map(|trace| trace.first()) {
+        graph.set_start(node_idx(&step.node));
+    }
+    DraftGraph {
+        graph,
+        annotations,
+        unexplained_outputs,
+        invalid_keys,
+        back_edges,
+    }
+}
@@ -1,17 +1,89 @@
+use std::collections::BTreeMap;
 use std::ops::Index;
+use std::sync::Arc;
 
-use self::schema::InputCond;
+use self::contract::Contract;
+use self::failure::{ErrorMatch, Failure, RetryPolicy};
+use self::module::WorkflowModule;
+use self::path::ParsePathError;
+use self::schema::{InputCond, SchemaEdit};
+use self::value::ValueType;
 
+pub mod conformance;
+pub mod contract;
+pub mod diff;
+pub mod failure;
+pub mod hash;
+pub mod infer;
+pub mod module;
+pub mod openapi;
+pub mod path;
 pub mod schema;
+pub mod simulate;
+pub mod value;
 
 pub type NodeIdx = usize;
 
+/// How a node combines its incoming and outgoing edges.
+#[derive(Debug, Clone, Default)]
+pub enum NodeKind {
+    /// An ordinary task: it needs one enabled incoming edge and takes one enabled outgoing edge.
+    #[default]
+    Task,
+    /// A parallel split: every outgoing edge is taken.
+    Fork,
+    /// A parallel merge: every incoming edge must be taken, and the inputs are the union of the
+    /// outputs of all incoming branches.
+    Join,
+    /// Runs a sub-workflow once per element of a collection.
+    Map(Box<MapSpec>),
+    /// Runs a reusable workflow module once.
+    SubWorkflow(Arc<WorkflowModule>),
+}
+
+/// A sub-workflow that a map node runs for every element of the collection stored at `items_key`.
+///
+/// Each run starts with the element bound to `item_key`, plus the other input keys of the map node.
+/// The map node completes when every run reaches `iterator_end`, and then outputs `results_key`.
+#[derive(Debug, Clone)]
+pub struct MapSpec {
+    pub items_key: String,
+    pub item_key: String,
+    pub iterator: WorkflowGraph,
+    pub iterator_end: NodeIdx,
+    pub results_key: String,
+}
+
+impl MapSpec {
+    pub fn new(
+        items_key: impl Into<String>,
+        item_key: impl Into<String>,
+        iterator: WorkflowGraph,
+        iterator_end: NodeIdx,
+        results_key: impl Into<String>,
+    ) -> Self {
+        Self {
+            items_key: items_key.into(),
+            item_key: item_key.into(),
+            iterator,
+            iterator_end,
+            results_key: results_key.into(),
+        }
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct Node {
     pub id: NodeIdx,
     pub name: String,
+    pub kind: NodeKind,
     pub required_inputs: Vec<String>,
     pub output_schema: schema::OutputSchema,
+    pub failures: Vec<Failure>,
+    pub retries: Vec<RetryPolicy>,
+    pub contract: Option<Contract>,
+    /// The node that undoes this one in a saga.
+    pub compensation: Option<NodeIdx>,
 }
 
 impl Node {
@@ -24,17 +96,34 @@ impl Node {
         Self {
             id,
             name,
+            kind: NodeKind::Task,
             required_inputs,
             output_schema,
+            failures: Vec::new(),
+            retries: Vec::new(),
+            contract: None,
+            compensation: None,
+        }
+    }
+
+    /// The contract of the node, or else the contract of the module it runs.
+    pub fn effective_contract(&self) -> Option<&Contract> {
+        match (&self.contract, &self.kind) {
+            (Some(contract), _) => Some(contract),
+            (None, NodeKind::SubWorkflow(module)) => module.contract.as_ref(),
+            _ => None,
         }
     }
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Default)]
 pub struct WorkflowGraph {
     pub nodes: Vec<Node>,
     pub adj_list: Vec<Vec<(NodeIdx, Vec<InputCond>)>>,
+    pub catch_list: Vec<Vec<(NodeIdx, ErrorMatch)>>,
     pub start: Option<NodeIdx>,
+    /// Declared types of keys, whose values are then tracked by the verifier.
+    pub key_types: BTreeMap<String, ValueType>,
 }
 
 impl WorkflowGraph {
@@ -42,37 +131,171 @@ impl WorkflowGraph {
         Self {
             nodes: Vec::new(),
             adj_list: Vec::new(),
+            catch_list: Vec::new(),
             start: None,
+            key_types: BTreeMap::new(),
         }
     }
 
+    /// Panics if a required input or a created key is not a valid path; see `try_add_node`.
     pub fn add_node(
         &mut self,
         name: &str,
         required_inputs: Vec<String>,
         output_schema: schema::OutputSchema,
     ) -> NodeIdx {
+        self.add_node_with_kind(name, NodeKind::Task, required_inputs, output_schema)
+    }
+
+    /// Like `add_node`, but returns an error for a key that is not a valid path. For graphs built from external
+    /// data.
+    pub fn try_add_node(
+        &mut self,
+        name: &str,
+        required_inputs: Vec<String>,
+        output_schema: schema::OutputSchema,
+    ) -> Result<NodeIdx, ParsePathError> {
+        self.try_add_node_with_kind(name, NodeKind::Task, required_inputs, output_schema)
+    }
+
+    pub fn add_node_with_kind(
+        &mut self,
+        name: &str,
+        kind: NodeKind,
+        required_inputs: Vec<String>,
+        output_schema: schema::OutputSchema,
+    ) -> NodeIdx {
+        self.try_add_node_with_kind(name, kind, required_inputs, output_schema)
+            .unwrap_or_else(|e| panic!("{e}"))
+    }
+
+    pub fn try_add_node_with_kind(
+        &mut self,
+        name: &str,
+        kind: NodeKind,
+        required_inputs: Vec<String>,
+        output_schema: schema::OutputSchema,
+    ) -> Result<NodeIdx, ParsePathError> {
+        required_inputs
+            .iter()
+            .map(|s| s.as_str())
+            .chain(output_schema.created_keys())
+            .try_for_each(path::validate)?;
         let id = self.nodes.len();
-        self.nodes.push(Node::new(
-            id,
-            name.to_owned(),
-            required_inputs,
-            output_schema,
-        ));
+        let mut node = Node::new(id, name.to_owned(), required_inputs, output_schema);
+        node.kind = kind;
+        self.nodes.push(node);
         self.adj_list.push(Vec::new());
-        id
+        self.catch_list.push(Vec::new());
+        Ok(id)
+    }
+
+    /// Add a node that starts all of its outgoing branches in parallel.
+    pub fn add_fork(&mut self, name: &str, output_schema: schema::OutputSchema) -> NodeIdx {
+        self.add_node_with_kind(name, NodeKind::Fork, vec![], output_schema)
+    }
+
+    /// Add a node that waits for all of its incoming branches.
+    /// Its inputs are the union of the outputs of the incoming branches.
+    pub fn add_join(
+        &mut self,
+        name: &str,
+        required_inputs: Vec<String>,
+        output_schema: schema::OutputSchema,
+    ) -> NodeIdx {
+        self.add_node_with_kind(name, NodeKind::Join, required_inputs, output_schema)
     }
 
     /// Add an edge from `src` to `dst` with additional transition condition.
     ///  A transition is good if and only if required_inputs are satisfied and:
     ///  * for each `InputCond`, exists a (key, value) pair in outputs of `src` that satisfies the condition
+    ///
+    /// Panics if a condition key is not a valid path; see `try_add_edge`.
     pub fn add_edge(
         &mut self,
         src: NodeIdx,
         dst: NodeIdx,
         additional_transition_condition: Vec<InputCond>,
     ) -> &mut Self {
+        self.try_add_edge(src, dst, additional_transition_condition)
+            .unwrap_or_else(|e| panic!("{e}"))
+    }
+
+    /// Like `add_edge`, but returns an error for a condition key that is not a valid path.
+    pub fn try_add_edge(
+        &mut self,
+        src: NodeIdx,
+        dst: NodeIdx,
+        additional_transition_condition: Vec<InputCond>,
+    ) -> Result<&mut Self, ParsePathError> {
+        additional_transition_condition
+            .iter()
+            .filter_map(InputCond::key)
+            .try_for_each(path::validate)?;
         self.adj_list[src].push((dst, additional_transition_condition));
+        Ok(self)
+    }
+
+    /// Declare that `node` may fail with `error`, producing `output_schema` instead of its usual outputs.
+    pub fn add_failure(
+        &mut self,
+        node: NodeIdx,
+        error: &str,
+        output_schema: schema::OutputSchema,
+    ) -> &mut Self {
+        self.nodes[node].failures.push(Failure {
+            error: error.to_owned(),
+            output_schema,
+        });
+        self
+    }
+
+    /// Add a catch edge from `src` to `dst`, taken when `src` fails with an error matched by `errors`.
+    /// If several catch edges match an error, the first one added is taken.
+    pub fn add_catch(&mut self, src: NodeIdx, errors: ErrorMatch, dst: NodeIdx) -> &mut Self {
+        self.catch_list[src].push((dst, errors));
+        self
+    }
+
+    /// Record a retry policy of `node`. It does not change what the verifier or the simulator report; see
+    /// `RetryPolicy`.
+    pub fn add_retry(
+        &mut self,
+        node: NodeIdx,
+        errors: ErrorMatch,
+        max_attempts: usize,
+    ) -> &mut Self {
+        self.nodes[node].retries.push(RetryPolicy {
+            errors,
+            max_attempts,
+        });
+        self
+    }
+
+    /// Attach `contract` to `node`. A verifier built with `GraphVerifier::with_contracts` then uses the
+    /// contract instead of the node's required inputs and output schema.
+    pub fn set_contract(&mut self, node: NodeIdx, contract: Contract) -> &mut Self {
+        self.nodes[node].contract = Some(contract);
+        self
+    }
+
+    /// Declare that `compensation` undoes `step` in a saga. When a later node fails, the compensations of the
+    /// completed steps should run in reverse order; `verifier::saga::check_compensations` checks that they do.
+    pub fn set_compensation(&mut self, step: NodeIdx, compensation: NodeIdx) -> &mut Self {
+        self.nodes[step].compensation = Some(compensation);
+        self
+    }
+
+    /// Index into `catch_list[node]` of the catch edge taken when `node` fails with `error`.
+    pub fn catch_for(&self, node: NodeIdx, error: &str) -> Option<usize> {
+        self.catch_list[node]
+            .iter()
+            .position(|(_, errors)| errors.matches(error))
+    }
+
+    /// Declare the type of `key`, in every node of the graph.
+    pub fn set_key_type(&mut self, key: &str, ty: ValueType) -> &mut Self {
+        self.key_types.insert(key.to_owned(), ty);
         self
     }
 
@@ -84,9 +307,62 @@ impl WorkflowGraph {
         self
     }
 
+    /// Add a node that runs `map_spec.iterator` for every element of `map_spec.items_key`.
+    /// `map_spec.items_key` is added to the required inputs.
+    pub fn add_map(
+        &mut self,
+        name: &str,
+        mut required_inputs: Vec<String>,
+        map_spec: MapSpec,
+        output_schema: schema::OutputSchema,
+    ) -> NodeIdx {
+        if !required_inputs.contains(&map_spec.items_key) {
+            required_inputs.push(map_spec.items_key.clone());
+        }
+        self.add_node_with_kind(
+            name,
+            NodeKind::Map(Box::new(map_spec)),
+            required_inputs,
+            output_schema,
+        )
+    }
+
+    /// Add a node that runs `module` with its declared inputs and outputs its declared outputs,
+    /// in addition to `output_schema`.
+    pub fn add_subworkflow(
+        &mut self,
+        name: &str,
+        required_inputs: Vec<String>,
+        module: Arc<WorkflowModule>,
+        output_schema: schema::OutputSchema,
+    ) -> NodeIdx {
+        self.add_node_with_kind(
+            name,
+            NodeKind::SubWorkflow(module),
+            required_inputs,
+            output_schema,
+        )
+    }
+
+    /// Apply `edit` to the output schema of `node`.
+    pub fn edit_schema(&mut self, node: NodeIdx, edit: &SchemaEdit) -> &mut Self {
+        self.nodes[node].output_schema.apply_edit(edit);
+        self
+    }
+
     pub fn get_node(&self, node: NodeIdx) -> &Node {
         &self.nodes[node]
     }
+
+    /// All nodes with an edge into `node`, in the order of `nodes`.
+    pub fn predecessors(&self, node: NodeIdx) -> Vec<NodeIdx> {
+        self.adj_list
+            .iter()
+            .enumerate()
+            .filter(|(_, adj)| adj.iter().any(|(dst, _)| *dst == node))
+            .map(|(src, _)| src)
+            .collect()
+    }
 }
 
 impl Index<usize> for WorkflowGraph {
@@ -0,0 +1,61 @@
+use std::collections::BTreeSet;
+use std::sync::OnceLock;
+
+use super::contract::Contract;
+use super::{NodeIdx, WorkflowGraph};
+
+/// A workflow that other workflows reuse as a single node.
+///
+/// A run of the module starts with the keys in `inputs` that the calling node has, and completes when it
+/// reaches `end`. The calling node then outputs the keys in `outputs` that `end` outputs.
+#[derive(Debug, Clone)]
+pub struct WorkflowModule {
+    pub name: String,
+    pub graph: WorkflowGraph,
+    pub end: NodeIdx,
+    pub inputs: Vec<String>,
+    pub outputs: Vec<String>,
+    pub contract: Option<Contract>,
+    summary: OnceLock<ModuleSummary>,
+}
+
+/// What a module does for every subset of its declared inputs.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ModuleSummary {
+    /// (inputs, outputs) pairs such that a run with exactly `inputs` may complete with exactly `outputs`.
+    pub completions: Vec<(BTreeSet<String>, BTreeSet<String>)>,
+    /// Sets of inputs with which a run may stop before `end`.
+    pub failing_inputs: Vec<BTreeSet<String>>,
+}
+
+impl WorkflowModule {
+    pub fn new(
+        name: &str,
+        graph: WorkflowGraph,
+        end: NodeIdx,
+        inputs: Vec<String>,
+        outputs: Vec<String>,
+    ) -> Self {
+        Self {
+            name: name.to_owned(),
+            graph,
+            end,
+            inputs,
+            outputs,
+            contract: None,
+            summary: OnceLock::new(),
+        }
+    }
+
+    /// Attach `contract` to the module. It is used by every sub-workflow node running the module that has no
+    /// contract of its own, and is checked against the module with `check_module_contract`.
+    pub fn with_contract(mut self, contract: Contract) -> Self {
+        self.contract = Some(contract);
+        self
+    }
+
+    /// The summary of this module, computed by `summarize` the first time it is needed.
+    pub fn summary(&self, summarize: impl FnOnce(&Self) -> ModuleSummary) -> &ModuleSummary {
+        self.summary.get_or_init(|| summarize(self))
+    }
+}
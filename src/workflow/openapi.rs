@@ -0,0 +1,367 @@
+//! Import node schemas from OpenAPI 3 operations or JSON Schemas.
+//!
+//! Required request fields become required inputs, and required response fields become fixed output keys.
+//! Nested objects become paths such as `order.customer.id`, and arrays of objects `order.items[*].sku`.
+//! A nullable field is present with a null value, so it is still required, but the fields below it are not.
+//! Only JSON documents are read; local `$ref`s and `allOf` are resolved. A parameter or property name that is not
+//! a single field of a path, such as `a.b` or `tags[]`, is an error, since it would be read as another key.
+
+use std::collections::BTreeMap;
+use std::fmt;
+use std::path::Path;
+
+use serde_json::{Map, Value as Json};
+
+use super::path::KeyPath;
+use super::schema::{OutputSchema, OutputSchemaBuilder};
+use super::value::ValueType;
+use super::{NodeIdx, WorkflowGraph};
+
+#[derive(Debug)]
+pub enum ImportError {
+    Io(std::io::Error),
+    Json(serde_json::Error),
+    /// No operation has the operation id.
+    UnknownOperation(String),
+    /// A `$ref` that is not a local reference to an existing schema.
+    BadRef(String),
+    /// A parameter or property name that is not a single field of a path.
+    InvalidName(String),
+}
+
+impl fmt::Display for ImportError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ImportError::Io(e) => write!(f, "{e}"),
+            ImportError::Json(e) => write!(f, "{e}"),
+            ImportError::UnknownOperation(id) => write!(f, "no operation {id:?}"),
+            ImportError::BadRef(r) => write!(f, "cannot resolve $ref {r:?}"),
+            ImportError::InvalidName(name) => write!(f, "{name:?} is not a single field of a key"),
+        }
+    }
+}
+
+impl std::error::Error for ImportError {}
+
+impl From<std::io::Error> for ImportError {
+    fn from(e: std::io::Error) -> Self {
+        ImportError::Io(e)
+    }
+}
+
+impl From<serde_json::Error> for ImportError {
+    fn from(e: serde_json::Error) -> Self {
+        ImportError::Json(e)
+    }
+}
+
+/// The keys of an operation, to build a node from.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ImportedOperation {
+    pub required_inputs: Vec<String>,
+    /// Keys that every successful response has.
+    pub outputs: Vec<String>,
+    /// Keys that a successful response may have.
+    pub optional_outputs: Vec<String>,
+    /// Types of the input and output keys that have one.
+    pub key_types: BTreeMap<String, ValueType>,
+}
+
+impl ImportedOperation {
+    /// The operation with `operation_id` in an OpenAPI 3 document in JSON. Required parameters and the required
+    /// fields of a required JSON request body are the required inputs, and the first successful JSON response
+    /// gives the outputs.
+    pub fn from_openapi(document: &str, operation_id: &str) -> Result<Self, ImportError> {
+        let root = serde_json::from_str::<Json>(document)?;
+        let resolver = Resolver { root: &root };
+        let unknown = || ImportError::UnknownOperation(operation_id.to_string());
+        let (path_item, operation) = root
+            .get("paths")
+            .and_then(Json::as_object)
+            .ok_or_else(unknown)?
+            .values()
+            .flat_map(|path_item| {
+                METHODS
+                    .iter()
+                    .filter_map(move |method| Some((path_item, path_item.get(*method)?)))
+            })
+            .find(|(_, operation)| {
+                operation.get("operationId").and_then(Json::as_str) == Some(operation_id)
+            })
+            .ok_or_else(unknown)?;
+
+        let mut inputs = Fields::default();
+        let parameters = [path_item, operation]
+            .into_iter()
+            .filter_map(|o| o.get("parameters").and_then(Json::as_array))
+            .flatten();
+        for parameter in parameters {
+            let parameter = resolver.resolve(parameter)?;
+            let Some(name) = parameter.get("name").and_then(Json::as_str) else {
+                continue;
+            };
+            let required = parameter.get("required").and_then(Json::as_bool) == Some(true);
+            let schema = parameter.get("schema").unwrap_or(&Json::Null);
+            resolver.fields(schema, field_name(name)?, required, &mut inputs, 0)?;
+        }
+        if let Some(body) = operation.get("requestBody") {
+            let body = resolver.resolve(body)?;
+            let required = body.get("required").and_then(Json::as_bool) == Some(true);
+            if let Some(schema) = json_schema(body) {
+                resolver.fields(schema, "", required, &mut inputs, 0)?;
+            }
+        }
+
+        let mut outputs = Fields::default();
+        let responses = operation.get("responses").and_then(Json::as_object);
+        let success = responses
+            .into_iter()
+            .flatten()
+            .filter(|(status, _)| status.starts_with('2'))
+            .min_by_key(|(status, _)| status.as_str())
+            .or_else(|| responses?.get_key_value("default"));
+        if let Some((_, response)) = success {
+            if let Some(schema) = json_schema(resolver.resolve(response)?) {
+                resolver.fields(schema, "", true, &mut outputs, 0)?;
+            }
+        }
+        Ok(Self::from_fields(inputs, outputs))
+    }
+
+    pub fn from_openapi_file(
+        path: impl AsRef<Path>,
+        operation_id: &str,
+    ) -> Result<Self, ImportError> {
+        Self::from_openapi(&std::fs::read_to_string(path)?, operation_id)
+    }
+
+    /// The operation whose request and response bodies have the given JSON Schemas, if any.
+    pub fn from_json_schemas(
+        request: Option<&str>,
+        response: Option<&str>,
+    ) -> Result<Self, ImportError> {
+        let fields = |schema: Option<&str>| -> Result<Fields, ImportError> {
+            let mut fields = Fields::default();
+            if let Some(schema) = schema {
+                let root = serde_json::from_str::<Json>(schema)?;
+                Resolver { root: &root }.fields(&root, "", true, &mut fields, 0)?;
+            }
+            Ok(fields)
+        };
+        Ok(Self::from_fields(fields(request)?, fields(response)?))
+    }
+
+    pub fn from_json_schema_files(
+        request: Option<&Path>,
+        response: Option<&Path>,
+    ) -> Result<Self, ImportError> {
+        let read = |path: Option<&Path>| path.map(std::fs::read_to_string).transpose();
+        Self::from_json_schemas(read(request)?.as_deref(), read(response)?.as_deref())
+    }
+
+    fn from_fields(inputs: Fields, outputs: Fields) -> Self {
+        let mut key_types = inputs.types;
+        key_types.extend(outputs.types);
+        Self {
+            required_inputs: inputs.required,
+            outputs: outputs.required,
+            optional_outputs: outputs.optional,
+            key_types,
+        }
+    }
+
+    /// An output schema with the keys of every successful response as fixed keys, and the optional ones as
+    /// may-keys.
+    pub fn output_schema(&self) -> OutputSchemaBuilder {
+        let schema = self
+            .outputs
+            .iter()
+            .fold(OutputSchema::new(), |schema, s| schema.add_fixed(s));
+        self.optional_outputs
+            .iter()
+            .fold(schema, |schema, s| schema.add_may(s))
+    }
+
+    /// Add a node that needs the required inputs and outputs the response, and declare the types of its keys.
+    /// The node does not carry its inputs, since the response replaces them.
+    pub fn add_to(&self, graph: &mut WorkflowGraph, name: &str) -> NodeIdx {
+        let node = graph.add_node(
+            name,
+            self.required_inputs.clone(),
+            self.output_schema().build(),
+        );
+        self.key_types.iter().for_each(|(s, ty)| {
+            graph.set_key_type(s, ty.clone());
+        });
+        node
+    }
+}
+
+const METHODS: [&str; 8] = [
+    "get", "put", "post", "delete", "options", "head", "patch", "trace",
+];
+
+/// `name`, if it is a single field of a path.
+fn field_name(name: &str) -> Result<&str, ImportError> {
+    match name.parse::<KeyPath>() {
+        Ok(path) if path.segments.len() == 1 => Ok(name),
+        _ => Err(ImportError::InvalidName(name.to_string())),
+    }
+}
+
+/// The JSON schema of a request body or response.
+fn json_schema(body: &Json) -> Option<&Json> {
+    let content = body.get("content")?.as_object()?;
+    content
+        .get("application/json")
+        .or_else(|| {
+            content
+                .iter()
+                .find(|(ty, _)| ty.ends_with("json"))
+                .map(|(_, c)| c)
+        })?
+        .get("schema")
+}
+
+/// The fields of a schema, flattened to paths.
+#[derive(Default)]
+struct Fields {
+    required: Vec<String>,
+    optional: Vec<String>,
+    types: BTreeMap<String, ValueType>,
+}
+
+/// The properties of an object schema by name.
+type Properties<'a> = Vec<(&'a String, &'a Json)>;
+
+/// Resolves the `$ref`s of one document.
+struct Resolver<'a> {
+    root: &'a Json,
+}
+
+impl<'a> Resolver<'a> {
+    fn resolve(&self, schema: &'a Json) -> Result<&'a Json, ImportError> {
+        let mut schema = schema;
+        // a chain of references longer than this is a cycle
+        for _ in 0..64 {
+            let Some(reference) = schema.get("$ref").and_then(Json::as_str) else {
+                return Ok(schema);
+            };
+            let bad_ref = || ImportError::BadRef(reference.to_string());
+            let pointer = reference.strip_prefix('#').ok_or_else(bad_ref)?;
+            schema = self.root.pointer(pointer).ok_or_else(bad_ref)?;
+        }
+        Err(ImportError::BadRef(
+            schema["$ref"].as_str().unwrap_or_default().to_string(),
+        ))
+    }
+
+    /// The properties and required properties of an object schema, merging `allOf`.
+    fn properties(&self, schema: &'a Json) -> Result<(Properties<'a>, Vec<&'a str>), ImportError> {
+        let schema = self.resolve(schema)?;
+        let mut properties = schema
+            .get("properties")
+            .and_then(Json::as_object)
+            .map(Map::iter)
+            .into_iter()
+            .flatten()
+            .collect::<Vec<_>>();
+        let mut required = schema
+            .get("required")
+            .and_then(Json::as_array)
+            .into_iter()
+            .flatten()
+            .filter_map(Json::as_str)
+            .collect::<Vec<_>>();
+        for part in schema
+            .get("allOf")
+            .and_then(Json::as_array)
+            .into_iter()
+            .flatten()
+        {
+            let (p, r) = self.properties(part)?;
+            properties.extend(p);
+            required.extend(r);
+        }
+        Ok((properties, required))
+    }
+
+    /// Add the fields of `schema` at `key`, or of its properties if `key` is empty, to `fields`. `required` is
+    /// whether the field must be present. The elements of an array are at `key[*]`.
+    fn fields(
+        &self,
+        schema: &'a Json,
+        key: &str,
+        required: bool,
+        fields: &mut Fields,
+        depth: usize,
+    ) -> Result<(), ImportError> {
+        let schema = self.resolve(schema)?;
+        let (ty, nullable) = schema_type(schema);
+        let (prefix, object) = match schema.get("items") {
+            Some(items) if !key.is_empty() => (format!("{key}[*]"), self.resolve(items)?),
+            _ => (key.to_string(), schema),
+        };
+        // a recursive schema is cut off
+        let (children, children_required) = match depth {
+            0..=16 => self.properties(object)?,
+            _ => (vec![], vec![]),
+        };
+        let required_before = fields.required.len();
+        for (name, child) in children.iter() {
+            let name = field_name(name)?;
+            let child_key = match prefix.as_str() {
+                "" => name.to_string(),
+                _ => format!("{prefix}.{name}"),
+            };
+            let child_required = required && !nullable && children_required.contains(&name);
+            self.fields(child, &child_key, child_required, fields, depth + 1)?;
+        }
+        if key.is_empty() {
+            return Ok(());
+        }
+        // a required object is present if its required fields are
+        if required && fields.required.len() == required_before {
+            fields.required.push(key.to_string());
+        } else if !required && children.is_empty() {
+            fields.optional.push(key.to_string());
+        }
+        let ty = match ty {
+            Some("string") => match schema.get("enum").and_then(Json::as_array) {
+                Some(variants) => Some(ValueType::Enum(
+                    variants
+                        .iter()
+                        .filter_map(Json::as_str)
+                        .map(|s| s.to_string())
+                        .collect(),
+                )),
+                None => Some(ValueType::String),
+            },
+            Some("integer") => Some(ValueType::Int),
+            Some("boolean") => Some(ValueType::Bool),
+            _ => None,
+        };
+        if let Some(ty) = ty {
+            let ty = match nullable {
+                true => ty.nullable(),
+                false => ty,
+            };
+            fields.types.insert(key.to_string(), ty);
+        }
+        Ok(())
+    }
+}
+
+/// The type of a schema other than null, and whether it is nullable, in the OpenAPI 3.0 or JSON Schema way.
+fn schema_type(schema: &Json) -> (Option<&str>, bool) {
+    let nullable = schema.get("nullable").and_then(Json::as_bool) == Some(true);
+    match schema.get("type") {
+        Some(Json::String(ty)) => (Some(ty.as_str()), nullable || ty == "null"),
+        Some(Json::Array(types)) => {
+            let mut types = types.iter().filter_map(Json::as_str);
+            let null = types.clone().any(|ty| ty == "null");
+            (types.find(|ty| *ty != "null"), nullable || null)
+        }
+        _ => (None, nullable),
+    }
+}
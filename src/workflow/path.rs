@@ -0,0 +1,127 @@
+//! Keys are paths into nested payloads, such as `order.items[*].sku`: fields separated by dots, where `[*]` stands
+//! for every element of an array. A key has a value only if its parent has one, so a present key implies its
+//! parents.
+
+use std::fmt;
+use std::str::FromStr;
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum Segment {
+    Field(String),
+    /// Every element of an array.
+    Each,
+}
+
+/// A parsed key. Keys are stored as strings in this syntax, and parsing checks them.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct KeyPath {
+    pub segments: Vec<Segment>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParsePathError {
+    pub key: String,
+    pub position: usize,
+    pub message: String,
+}
+
+impl fmt::Display for ParsePathError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "invalid key {:?}: {} at position {}",
+            self.key, self.message, self.position
+        )
+    }
+}
+
+impl std::error::Error for ParsePathError {}
+
+impl FromStr for KeyPath {
+    type Err = ParsePathError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let error = |position, message: &str| ParsePathError {
+            key: s.to_string(),
+            position,
+            message: message.to_string(),
+        };
+        let mut segments = vec![];
+        let mut pos = 0;
+        while pos < s.len() {
+            let rest = &s[pos..];
+            if let Some(after) = rest.strip_prefix("[*]") {
+                if segments.is_empty() {
+                    return Err(error(pos, "expected a field"));
+                }
+                segments.push(Segment::Each);
+                pos = s.len() - after.len();
+                continue;
+            }
+            let field_start = match rest.strip_prefix('.') {
+                Some(_) if segments.is_empty() => return Err(error(pos, "expected a field")),
+                Some(_) => pos + 1,
+                None if segments.is_empty() => pos,
+                None => return Err(error(pos, "expected `.` or `[*]`")),
+            };
+            let len = s[field_start..]
+                .find(['.', '[', ']'])
+                .unwrap_or(s.len() - field_start);
+            if len == 0 {
+                return Err(error(field_start, "expected a field"));
+            }
+            segments.push(Segment::Field(
+                s[field_start..field_start + len].to_string(),
+            ));
+            pos = field_start + len;
+        }
+        if segments.is_empty() {
+            return Err(error(0, "empty key"));
+        }
+        Ok(KeyPath { segments })
+    }
+}
+
+impl fmt::Display for KeyPath {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.segments
+            .iter()
+            .enumerate()
+            .try_for_each(|(i, segment)| match segment {
+                Segment::Field(name) if i == 0 => write!(f, "{name}"),
+                Segment::Field(name) => write!(f, ".{name}"),
+                Segment::Each => write!(f, "[*]"),
+            })
+    }
+}
+
+/// An error if `key` is not a valid path.
+pub fn validate(key: &str) -> Result<(), ParsePathError> {
+    key.parse::<KeyPath>().map(|_| ())
+}
+
+/// The key one segment up, e.g. `order.items` for `order.items[*]`.
+pub fn parent(key: &str) -> Option<&str> {
+    let end = match key.strip_suffix("[*]") {
+        Some(parent) => parent.len(),
+        None => key.rfind('.')?,
+    };
+    Some(&key[..end])
+}
+
+/// The keys above `key`, from its parent up to the root field.
+pub fn ancestors(key: &str) -> impl Iterator<Item = &str> {
+    std::iter::successors(parent(key), |key| parent(key))
+}
+
+/// Whether `key` is `root` or a key below it.
+pub fn is_within(key: &str, root: &str) -> bool {
+    key.strip_prefix(root)
+        .is_some_and(|rest| rest.is_empty() || rest.starts_with('.') || rest.starts_with('['))
+}
+
+/// The key that was moved under `parent` to become `key`, e.g. `result.x` for `previous_input.result.x` under
+/// `previous_input`.
+pub fn strip_parent<'a>(key: &'a str, parent: &str) -> Option<&'a str> {
+    key.strip_prefix(parent)?.strip_prefix('.')
+}
@@ -1,10 +1,46 @@
-use std::collections::BTreeSet;
+use std::collections::{BTreeMap, BTreeSet};
+
+use super::path;
+use super::value::{CmpOp, Value, ValueSpec};
 
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub enum KeyRule {
     Identity,
     Fixed(String),
     IdWithPrefix(String),
+    /// Carries the key and every key below it, e.g. `order.*` for `Subtree("order")`.
+    Subtree(String),
+    /// Moves the subtree of the first key under the second, e.g. `result.x` to `previous_input.result.x`
+    /// for `MoveUnder("result", "previous_input")`.
+    MoveUnder(String, String),
+}
+
+impl KeyRule {
+    /// The input key that the rule carries to the output key `key`, if any.
+    pub fn carried_key<'a>(&self, key: &'a str) -> Option<&'a str> {
+        match self {
+            KeyRule::Identity => Some(key),
+            KeyRule::Fixed(_) => None,
+            KeyRule::IdWithPrefix(prefix) => key.strip_prefix(prefix.as_str()),
+            KeyRule::Subtree(root) => path::is_within(key, root).then_some(key),
+            KeyRule::MoveUnder(root, parent) => {
+                path::strip_parent(key, parent).filter(|key| path::is_within(key, root))
+            }
+        }
+    }
+
+    /// The output key that the rule carries the input key `key` to, if any.
+    pub fn output_key(&self, key: &str) -> Option<String> {
+        match self {
+            KeyRule::Identity => Some(key.to_string()),
+            KeyRule::Fixed(_) => None,
+            KeyRule::IdWithPrefix(prefix) => Some(format!("{prefix}{key}")),
+            KeyRule::Subtree(root) => path::is_within(key, root).then(|| key.to_string()),
+            KeyRule::MoveUnder(root, parent) => {
+                path::is_within(key, root).then(|| format!("{parent}.{key}"))
+            }
+        }
+    }
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
@@ -12,25 +48,54 @@ pub enum InputCond {
     Always,
     MatchesKey(String),
     MatchesKeyValue(String, String),
+    /// The value of the key compares to the literal. Without a declared type for the key, only its presence is
+    /// checked by the verifier.
+    Compare(String, CmpOp, Value),
+}
+
+impl InputCond {
+    /// The key the condition looks at.
+    pub fn key(&self) -> Option<&str> {
+        match self {
+            InputCond::Always => None,
+            InputCond::MatchesKey(s)
+            | InputCond::MatchesKeyValue(s, _)
+            | InputCond::Compare(s, _, _) => Some(s),
+        }
+    }
 }
 
 #[derive(Debug, Clone)]
 pub struct OutputSchema {
+    /// Keys that are always output.
     pub fixed_keys: Vec<String>,
+    /// Keys that may or may not be output, which the verifier does not control.
+    pub may_keys: Vec<String>,
     pub dynamic_keys: Vec<(KeyRule, InputCond)>,
+    /// How the values of output keys are computed. Only used for typed keys.
+    pub value_specs: BTreeMap<String, ValueSpec>,
 }
 
 pub struct OutputSchemaBuilder {
     fixed_keys: BTreeSet<String>,
+    may_keys: BTreeSet<String>,
     dynamic_keys: Vec<(KeyRule, InputCond)>,
+    value_specs: BTreeMap<String, ValueSpec>,
 }
 
 impl OutputSchemaBuilder {
+    /// Add a key that is always output.
     pub fn add_fixed(mut self, key: impl Into<String>) -> Self {
         self.fixed_keys.insert(key.into());
         self
     }
 
+    /// Add a key that may or may not be output, like an optional field of a response.
+    pub fn add_may(mut self, key: impl Into<String>) -> Self {
+        self.may_keys.insert(key.into());
+        self
+    }
+
     pub fn add_rule_for_every_input(mut self, key: KeyRule, cond: InputCond) -> Self {
         self.dynamic_keys.push((key, cond));
         self
@@ -40,19 +105,43 @@ impl OutputSchemaBuilder {
         self.add_rule_for_every_input(KeyRule::Identity, InputCond::Always)
     }
 
+    /// Carry `root` and every key below it.
+    pub fn carry_subtree(self, root: &str) -> Self {
+        self.add_rule_for_every_input(KeyRule::Subtree(root.to_string()), InputCond::Always)
+    }
+
+    /// Move `root` and every key below it under `parent`.
+    pub fn move_under(self, root: &str, parent: &str) -> Self {
+        self.add_rule_for_every_input(
+            KeyRule::MoveUnder(root.to_string(), parent.to_string()),
+            InputCond::Always,
+        )
+    }
+
+    /// Set how the value of the output key `key` is computed.
+    pub fn set_value(mut self, key: impl Into<String>, spec: ValueSpec) -> Self {
+        self.value_specs.insert(key.into(), spec);
+        self
+    }
+
     pub fn build(self) -> OutputSchema {
         OutputSchema {
             fixed_keys: self.fixed_keys.into_iter().collect(),
+            may_keys: self.may_keys.into_iter().collect(),
             dynamic_keys: self.dynamic_keys,
+            value_specs: self.value_specs,
         }
     }
 }
 
 impl OutputSchema {
+    #[allow(clippy::new_ret_no_self)]
     pub fn new() -> OutputSchemaBuilder {
         OutputSchemaBuilder {
             fixed_keys: Default::default(),
+            may_keys: Default::default(),
             dynamic_keys: Default::default(),
+            value_specs: Default::default(),
         }
     }
 
@@ -60,7 +149,54 @@ impl OutputSchema {
         self.fixed_keys.iter().map(|s| s.as_str())
     }
 
+    pub fn may_keys(&self) -> impl Iterator<Item = &str> {
+        self.may_keys.iter().map(|s| s.as_str())
+    }
+
     pub fn dynamic_keys(&self) -> &[(KeyRule, InputCond)] {
         &self.dynamic_keys
     }
+
+    /// The keys that the schema may create, as opposed to carry.
+    pub fn created_keys(&self) -> impl Iterator<Item = &str> {
+        self.fixed_keys()
+            .chain(self.may_keys())
+            .chain(self.dynamic_keys.iter().filter_map(|(rule, _)| match rule {
+                KeyRule::Fixed(s) => Some(s.as_str()),
+                _ => None,
+            }))
+    }
+
+    pub fn value_spec(&self, key: &str) -> Option<&ValueSpec> {
+        self.value_specs.get(key)
+    }
+}
+
+/// A change to the output schema of a node.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SchemaEdit {
+    AddFixedKey(String),
+    RemoveFixedKey(String),
+    AddRule(KeyRule, InputCond),
+    /// Removes every occurrence of the rule.
+    RemoveRule(KeyRule, InputCond),
+}
+
+impl OutputSchema {
+    pub fn apply_edit(&mut self, edit: &SchemaEdit) {
+        match edit {
+            SchemaEdit::AddFixedKey(key) => {
+                if let Err(i) = self.fixed_keys.binary_search(key) {
+                    self.fixed_keys.insert(i, key.clone());
+                }
+            }
+            SchemaEdit::RemoveFixedKey(key) => self.fixed_keys.retain(|s| s != key),
+            SchemaEdit::AddRule(rule, cond) => {
+                self.dynamic_keys.push((rule.clone(), cond.clone()));
+            }
+            SchemaEdit::RemoveRule(rule, cond) => {
+                self.dynamic_keys.retain(|(r, c)| r != rule || c != cond);
+            }
+        }
+    }
 }
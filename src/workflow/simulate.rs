@@ -0,0 +1,445 @@
+//! Concrete execution of a workflow model, without any services.
+//!
+//! Keys carry string values. A key carried by a rule keeps its value, and a key created by a node has the empty value,
+//! unless the value spec of the key computes it.
+
+use std::collections::BTreeMap;
+
+use super::path;
+use super::schema::{InputCond, KeyRule, OutputSchema};
+use super::value::{Expr, ValueSpec};
+use super::{NodeIdx, NodeKind, WorkflowGraph};
+
+/// Keys and their values.
+pub type Values = BTreeMap<String, String>;
+
+/// What happened when a node was entered.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Outcome {
+    /// The node completed with these outputs.
+    Completed(Values),
+    /// The node failed with this error and these failure outputs.
+    Failed(String, Values),
+    /// The node could not run, because these required inputs were missing.
+    MissingInputs(Vec<String>),
+    /// The workflow run by a map or sub-workflow node stopped before its end.
+    NestedRunStopped,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Step {
+    pub node_idx: NodeIdx,
+    pub inputs: Values,
+    pub outcome: Outcome,
+}
+
+/// The nodes entered by one execution, in topological order.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Trace {
+    pub steps: Vec<Step>,
+}
+
+impl Trace {
+    pub fn step(&self, node: NodeIdx) -> Option<&Step> {
+        self.steps.iter().find(|step| step.node_idx == node)
+    }
+
+    /// Whether the execution entered `node`.
+    pub fn enters(&self, node: NodeIdx) -> bool {
+        self.step(node).is_some()
+    }
+
+    /// Whether the execution entered `node` and the node completed, successfully or with a failure.
+    pub fn reaches(&self, node: NodeIdx) -> bool {
+        self.step(node)
+            .is_some_and(|step| matches!(step.outcome, Outcome::Completed(_) | Outcome::Failed(..)))
+    }
+}
+
+/// Whether the rule condition `cond` holds for the input key `key` of `inputs`.
+/// A `Fixed` rule does not follow a key, so `key` is `None` and the condition only looks at `inputs`.
+fn rule_applies(cond: &InputCond, key: Option<&str>, inputs: &Values) -> bool {
+    match cond {
+        InputCond::Always => true,
+        InputCond::MatchesKey(s) => match key {
+            Some(key) => key == s,
+            None => inputs.contains_key(s),
+        },
+        InputCond::MatchesKeyValue(s, value) => {
+            let key_matches = match key {
+                Some(key) => key == s,
+                None => true,
+            };
+            key_matches && inputs.get(s) == Some(value)
+        }
+        InputCond::Compare(s, op, value) => {
+            let key_matches = match key {
+                Some(key) => key == s,
+                None => true,
+            };
+            key_matches
+                && inputs
+                    .get(s)
+                    .is_some_and(|actual| value.compared_by(*op, actual))
+        }
+    }
+}
+
+/// The value of `expr` given `inputs`, or `None` if a key is missing or an arithmetic operand is not an integer.
+fn eval(expr: &Expr, inputs: &Values) -> Option<String> {
+    let int = |e: &Expr| eval(e, inputs)?.parse::<i64>().ok();
+    match expr {
+        Expr::Key(s) => inputs.get(s).cloned(),
+        Expr::Lit(value) => Some(value.to_string()),
+        Expr::Add(a, b) => int(a)?.checked_add(int(b)?).map(|n| n.to_string()),
+        Expr::Sub(a, b) => int(a)?.checked_sub(int(b)?).map(|n| n.to_string()),
+        Expr::Mul(a, b) => int(a)?.checked_mul(int(b)?).map(|n| n.to_string()),
+        Expr::Concat(a, b) => Some(eval(a, inputs)? + &eval(b, inputs)?),
+    }
+}
+
+/// The outputs of a node with `output_schema` given `inputs`, without its may-keys. Fixed keys take precedence
+/// over carried keys, and value specs over both. The keys above an output key are output with the empty value,
+/// unless carried. A key with a `OneOf` spec keeps its carried value if it is in the set, and else takes the
+/// first value of the set.
+pub fn apply_schema(output_schema: &OutputSchema, inputs: &Values) -> Values {
+    outputs_with(output_schema, inputs, &[])
+}
+
+/// The outputs of a node with `output_schema` given `inputs`, for every set of may-keys it may output, and every
+/// value in the `OneOf` spec of each output key.
+pub fn possible_outputs(output_schema: &OutputSchema, inputs: &Values) -> Vec<Values> {
+    let may_keys = output_schema.may_keys().collect::<Vec<_>>();
+    (0..1usize << may_keys.len())
+        .flat_map(|mask| {
+            let produced = may_keys
+                .iter()
+                .enumerate()
+                .filter(|(i, _)| mask & (1 << i) != 0)
+                .map(|(_, s)| *s)
+                .collect::<Vec<_>>();
+            let outputs = outputs_with(output_schema, inputs, &produced);
+            output_schema
+                .value_specs
+                .iter()
+                .fold(vec![outputs], |all, (s, spec)| match spec {
+                    ValueSpec::OneOf(values) => all
+                        .into_iter()
+                        .flat_map(|outputs| match outputs.contains_key(s) {
+                            true => values
+                                .iter()
+                                .map(|v| {
+                                    let mut outputs = outputs.clone();
+                                    outputs.insert(s.clone(), v.to_string());
+                                    outputs
+                                })
+                                .collect(),
+                            false => vec![outputs],
+                        })
+                        .collect(),
+                    _ => all,
+                })
+        })
+        .collect()
+}
+
+/// Like `apply_schema`, but the node also outputs the may-keys in `produced`.
+fn outputs_with(output_schema: &OutputSchema, inputs: &Values, produced: &[&str]) -> Values {
+    let mut outputs = output_schema
+        .fixed_keys()
+        .chain(produced.iter().copied())
+        .map(|s| (s.to_string(), String::new()))
+        .collect::<Values>();
+    output_schema
+        .dynamic_keys()
+        .iter()
+        .for_each(|(rule, cond)| match rule {
+            KeyRule::Fixed(s) => {
+                if rule_applies(cond, None, inputs) {
+                    outputs.entry(s.clone()).or_default();
+                }
+            }
+            _ => {
+                inputs
+                    .iter()
+                    .filter(|(key, _)| rule_applies(cond, Some(key), inputs))
+                    .filter_map(|(key, value)| Some((rule.output_key(key)?, value)))
+                    .for_each(|(output_key, value)| {
+                        outputs.entry(output_key).or_insert_with(|| value.clone());
+                    });
+            }
+        });
+    output_schema.value_specs.iter().for_each(|(s, spec)| {
+        let Some(output) = outputs.get_mut(s) else {
+            return;
+        };
+        let value = match spec {
+            ValueSpec::Unknown => None,
+            ValueSpec::OneOf(values) if values.iter().any(|v| v.to_string() == *output) => None,
+            ValueSpec::OneOf(values) => values.first().map(|v| v.to_string()),
+            ValueSpec::CopyOf(key) => inputs.get(key).cloned(),
+            ValueSpec::Expr(expr) => eval(expr, inputs),
+        };
+        if let Some(value) = value {
+            *output = value;
+        }
+    });
+    // a key is only present with the keys above it
+    let ancestors = outputs
+        .keys()
+        .flat_map(|s| path::ancestors(s))
+        .map(|s| s.to_string())
+        .collect::<Vec<_>>();
+    ancestors.into_iter().for_each(|s| {
+        outputs.entry(s).or_default();
+    });
+    outputs
+}
+
+/// Whether a node with `outputs` can move on to `dst` along an edge with `conds`: the outputs contain the
+/// required inputs of `dst`, unless it is a join, and satisfy every condition.
+pub fn can_take_edge(
+    graph: &WorkflowGraph,
+    outputs: &Values,
+    dst: NodeIdx,
+    conds: &[InputCond],
+) -> bool {
+    let has_inputs = match graph.nodes[dst].kind {
+        NodeKind::Join => true,
+        _ => graph.nodes[dst]
+            .required_inputs
+            .iter()
+            .all(|s| outputs.contains_key(s)),
+    };
+    has_inputs
+        && conds.iter().all(|cond| match cond {
+            InputCond::Always => true,
+            InputCond::MatchesKey(s) => outputs.contains_key(s),
+            InputCond::MatchesKeyValue(s, value) => outputs.get(s) == Some(value),
+            InputCond::Compare(s, op, value) => outputs
+                .get(s)
+                .is_some_and(|actual| value.compared_by(*op, actual)),
+        })
+}
+
+struct Simulator<'g> {
+    graph: &'g WorkflowGraph,
+    order: Vec<NodeIdx>,
+    /// The number of success and catch edges into each node.
+    num_incoming: Vec<usize>,
+}
+
+/// A partial execution: the steps so far, and the outputs passed along the taken edges into each node.
+#[derive(Clone)]
+struct State {
+    steps: Vec<Step>,
+    incoming: Vec<Vec<Values>>,
+}
+
+impl<'g> Simulator<'g> {
+    fn new(graph: &'g WorkflowGraph) -> Self {
+        let mut order = crate::verifier::topsort::topological_sort_reversed(graph);
+        order.reverse();
+        let mut num_incoming = vec![0; graph.nodes.len()];
+        graph
+            .adj_list
+            .iter()
+            .flat_map(|adj| adj.iter().map(|(dst, _)| *dst))
+            .chain(
+                graph
+                    .catch_list
+                    .iter()
+                    .flat_map(|handlers| handlers.iter().map(|(dst, _)| *dst)),
+            )
+            .for_each(|dst| num_incoming[dst] += 1);
+        Self {
+            graph,
+            order,
+            num_incoming,
+        }
+    }
+
+    /// Every outcome of `node` with `inputs`, once it is entered.
+    fn outcomes(&self, node: NodeIdx, inputs: &Values) -> Vec<Outcome> {
+        let node = &self.graph.nodes[node];
+        let missing = node
+            .required_inputs
+            .iter()
+            .filter(|s| !inputs.contains_key(*s))
+            .cloned()
+            .collect::<Vec<_>>();
+        if !missing.is_empty() {
+            return vec![Outcome::MissingInputs(missing)];
+        }
+        // the outputs of the nested runs that reach their end
+        let nested_outputs = match &node.kind {
+            NodeKind::Map(map_spec) => {
+                // a single iteration stands for every element, since all elements have the same keys
+                let mut item_inputs = inputs.clone();
+                item_inputs.insert(map_spec.item_key.clone(), String::new());
+                let results = Values::from([(map_spec.results_key.clone(), String::new())]);
+                Some(
+                    nested_end_outputs(&map_spec.iterator, map_spec.iterator_end, &item_inputs)
+                        .into_iter()
+                        .map(|end_outputs| end_outputs.map(|_| results.clone()))
+                        .collect::<Vec<_>>(),
+                )
+            }
+            NodeKind::SubWorkflow(module) => {
+                // keys that are not declared inputs are not passed to the module
+                let passed = inputs
+                    .iter()
+                    .filter(|(s, _)| module.inputs.contains(s))
+                    .map(|(s, v)| (s.clone(), v.clone()))
+                    .collect();
+                Some(
+                    nested_end_outputs(&module.graph, module.end, &passed)
+                        .into_iter()
+                        .map(|end_outputs| {
+                            end_outputs.map(|end_outputs| {
+                                end_outputs
+                                    .into_iter()
+                                    .filter(|(s, _)| module.outputs.contains(s))
+                                    .collect()
+                            })
+                        })
+                        .collect(),
+                )
+            }
+            _ => None,
+        };
+        let runs = nested_outputs.unwrap_or_else(|| vec![Some(Values::new())]);
+        let mut outcomes = vec![];
+        possible_outputs(&node.output_schema, inputs)
+            .into_iter()
+            .for_each(|schema_outputs| {
+                runs.iter().for_each(|run| {
+                    let outcome = match run {
+                        Some(exported) => {
+                            let mut outputs = schema_outputs.clone();
+                            exported.iter().for_each(|(s, v)| {
+                                outputs.entry(s.clone()).or_insert(v.clone());
+                            });
+                            Outcome::Completed(outputs)
+                        }
+                        None => Outcome::NestedRunStopped,
+                    };
+                    if !outcomes.contains(&outcome) {
+                        outcomes.push(outcome);
+                    }
+                });
+            });
+        node.failures.iter().for_each(|failure| {
+            possible_outputs(&failure.output_schema, inputs)
+                .into_iter()
+                .for_each(|outputs| outcomes.push(Outcome::Failed(failure.error.clone(), outputs)));
+        });
+        outcomes
+    }
+
+    /// The ways `node` may move on after `outcome`, each as the taken edges, as (target, outputs).
+    fn successors(&self, node: NodeIdx, outcome: &Outcome) -> Vec<Vec<(NodeIdx, Values)>> {
+        match outcome {
+            Outcome::Completed(outputs) => {
+                let enabled = self.graph.adj_list[node]
+                    .iter()
+                    .filter(|(dst, conds)| can_take_edge(self.graph, outputs, *dst, conds))
+                    .map(|(dst, _)| (*dst, outputs.clone()))
+                    .collect::<Vec<_>>();
+                match self.graph.nodes[node].kind {
+                    NodeKind::Fork => vec![enabled],
+                    _ if enabled.is_empty() => vec![vec![]],
+                    _ => enabled.into_iter().map(|edge| vec![edge]).collect(),
+                }
+            }
+            Outcome::Failed(error, outputs) => match self.graph.catch_for(node, error) {
+                Some(catch_idx) => {
+                    let handler = self.graph.catch_list[node][catch_idx].0;
+                    if can_take_edge(self.graph, outputs, handler, &[]) {
+                        vec![vec![(handler, outputs.clone())]]
+                    } else {
+                        vec![vec![]]
+                    }
+                }
+                None => vec![vec![]],
+            },
+            Outcome::MissingInputs(_) | Outcome::NestedRunStopped => vec![vec![]],
+        }
+    }
+
+    /// Continue `state` from the `pos`-th node in topological order, and collect the finished traces.
+    fn explore(&self, pos: usize, mut state: State, traces: &mut Vec<Trace>) {
+        let Some(&node) = self.order.get(pos) else {
+            traces.push(Trace { steps: state.steps });
+            return;
+        };
+        let incoming = std::mem::take(&mut state.incoming[node]);
+        let entered = match self.graph.nodes[node].kind {
+            NodeKind::Join => !incoming.is_empty() && incoming.len() == self.num_incoming[node],
+            _ => !incoming.is_empty(),
+        };
+        if !entered {
+            return self.explore(pos + 1, state, traces);
+        }
+        // a node entered by several branches runs once, on the union of their outputs
+        let mut inputs = Values::new();
+        incoming.into_iter().for_each(|outputs| {
+            outputs.into_iter().for_each(|(s, v)| {
+                inputs.entry(s).or_insert(v);
+            });
+        });
+        self.outcomes(node, &inputs)
+            .into_iter()
+            .for_each(|outcome| {
+                self.successors(node, &outcome)
+                    .into_iter()
+                    .for_each(|edges| {
+                        let mut next = state.clone();
+                        edges.into_iter().for_each(|(dst, outputs)| {
+                            next.incoming[dst].push(outputs);
+                        });
+                        next.steps.push(Step {
+                            node_idx: node,
+                            inputs: inputs.clone(),
+                            outcome: outcome.clone(),
+                        });
+                        self.explore(pos + 1, next, traces);
+                    });
+            });
+    }
+}
+
+/// For every trace of a run of `graph` with `inputs`, the outputs of `end` if the run completes it.
+fn nested_end_outputs(graph: &WorkflowGraph, end: NodeIdx, inputs: &Values) -> Vec<Option<Values>> {
+    simulate(graph, inputs)
+        .into_iter()
+        .map(|trace| match trace.step(end) {
+            Some(Step {
+                outcome: Outcome::Completed(outputs),
+                ..
+            }) => Some(outputs.clone()),
+            _ => None,
+        })
+        .collect()
+}
+
+/// Every execution of `graph` from its start node with `inputs`.
+///
+/// A task takes one of its enabled outgoing edges, and a fork takes all of them. A node outputs any set of its
+/// may-keys. A node may also fail in any of its declared ways, in which case it takes its catch edge if it is enabled. A join runs once all of its
+/// incoming edges are taken. An execution ends when no more nodes can be entered.
+pub fn simulate(graph: &WorkflowGraph, inputs: &Values) -> Vec<Trace> {
+    let simulator = Simulator::new(graph);
+    let mut incoming = vec![vec![]; graph.nodes.len()];
+    incoming[graph.start.unwrap()].push(inputs.clone());
+    let mut traces = vec![];
+    simulator.explore(
+        0,
+        State {
+            steps: vec![],
+            incoming,
+        },
+        &mut traces,
+    );
+    traces
+}
@@ -0,0 +1,209 @@
+//! Types and values of keys, for conditions that look at values.
+
+use std::cmp::Ordering;
+use std::fmt;
+
+/// The type of the values of a key. Keys without a declared type are only tracked by presence.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum ValueType {
+    String,
+    Int,
+    Bool,
+    /// One of the given variants.
+    Enum(Vec<String>),
+    /// A value of the inner type, or null.
+    Nullable(Box<ValueType>),
+}
+
+impl ValueType {
+    pub fn nullable(self) -> Self {
+        match self {
+            ValueType::Nullable(_) => self,
+            _ => ValueType::Nullable(Box::new(self)),
+        }
+    }
+
+    /// The type without null.
+    pub fn non_null(&self) -> &ValueType {
+        match self {
+            ValueType::Nullable(inner) => inner.non_null(),
+            _ => self,
+        }
+    }
+}
+
+/// A literal value in a condition. Enum variants are strings.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum Value {
+    String(String),
+    Int(i64),
+    Bool(bool),
+    Null,
+}
+
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Value::String(s) => write!(f, "{s}"),
+            Value::Int(n) => write!(f, "{n}"),
+            Value::Bool(b) => write!(f, "{b}"),
+            Value::Null => write!(f, "null"),
+        }
+    }
+}
+
+impl Value {
+    /// The value written as `s` for a key of type `ty`, as in `InputCond::MatchesKeyValue`.
+    pub fn parse(ty: &ValueType, s: &str) -> Option<Value> {
+        if s == "null" && matches!(ty, ValueType::Nullable(_)) {
+            return Some(Value::Null);
+        }
+        match ty.non_null() {
+            ValueType::Int => s.parse().ok().map(Value::Int),
+            ValueType::Bool => s.parse().ok().map(Value::Bool),
+            ValueType::Enum(variants) if !variants.iter().any(|v| v == s) => None,
+            _ => Some(Value::String(s.to_string())),
+        }
+    }
+
+    /// Whether the concrete value written as `actual` compares to this value with `op`.
+    /// Only integers are ordered. Panics on an ordering of other values.
+    pub fn compared_by(&self, op: CmpOp, actual: &str) -> bool {
+        let ordering = match self {
+            Value::Int(n) => match actual.parse::<i64>() {
+                Ok(actual) => actual.cmp(n),
+                Err(_) => return op == CmpOp::Ne,
+            },
+            _ if !op.is_equality() => panic!("only integers are ordered, not {self}"),
+            _ if actual == self.to_string() => Ordering::Equal,
+            _ => Ordering::Less,
+        };
+        op.holds(ordering)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum CmpOp {
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+}
+
+impl CmpOp {
+    pub fn is_equality(self) -> bool {
+        matches!(self, CmpOp::Eq | CmpOp::Ne)
+    }
+
+    /// Whether `actual op expected` holds when `actual.cmp(expected)` is `ordering`.
+    pub fn holds(self, ordering: Ordering) -> bool {
+        match self {
+            CmpOp::Eq => ordering.is_eq(),
+            CmpOp::Ne => ordering.is_ne(),
+            CmpOp::Lt => ordering.is_lt(),
+            CmpOp::Le => ordering.is_le(),
+            CmpOp::Gt => ordering.is_gt(),
+            CmpOp::Ge => ordering.is_ge(),
+        }
+    }
+}
+
+impl fmt::Display for CmpOp {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let s = match self {
+            CmpOp::Eq => "==",
+            CmpOp::Ne => "!=",
+            CmpOp::Lt => "<",
+            CmpOp::Le => "<=",
+            CmpOp::Gt => ">",
+            CmpOp::Ge => ">=",
+        };
+        write!(f, "{s}")
+    }
+}
+
+/// How a node computes the value of one of its output keys. A key with a spec takes its value from the spec,
+/// even when it is carried.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum ValueSpec {
+    /// Any value of the type of the key.
+    Unknown,
+    /// One of the given values.
+    OneOf(Vec<Value>),
+    /// The value of the given input key. Unknown if the input key is missing.
+    CopyOf(String),
+    /// The value of the expression over input keys. Unknown if a key in it is missing or null.
+    Expr(Expr),
+}
+
+/// An arithmetic expression over integers, or a concatenation of strings.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum Expr {
+    Key(String),
+    Lit(Value),
+    Add(Box<Expr>, Box<Expr>),
+    Sub(Box<Expr>, Box<Expr>),
+    Mul(Box<Expr>, Box<Expr>),
+    Concat(Box<Expr>, Box<Expr>),
+}
+
+impl Expr {
+    pub fn key(s: &str) -> Self {
+        Expr::Key(s.to_string())
+    }
+
+    pub fn concat(self, other: Expr) -> Self {
+        Expr::Concat(Box::new(self), Box::new(other))
+    }
+
+    /// The input keys in the expression.
+    pub fn keys(&self) -> Vec<&str> {
+        match self {
+            Expr::Key(s) => vec![s.as_str()],
+            Expr::Lit(_) => vec![],
+            Expr::Add(a, b) | Expr::Sub(a, b) | Expr::Mul(a, b) | Expr::Concat(a, b) => {
+                let mut keys = a.keys();
+                keys.extend(b.keys());
+                keys
+            }
+        }
+    }
+}
+
+impl From<i64> for Expr {
+    fn from(n: i64) -> Self {
+        Expr::Lit(Value::Int(n))
+    }
+}
+
+impl From<&str> for Expr {
+    fn from(s: &str) -> Self {
+        Expr::Lit(Value::String(s.to_string()))
+    }
+}
+
+impl std::ops::Add for Expr {
+    type Output = Expr;
+
+    fn add(self, other: Expr) -> Expr {
+        Expr::Add(Box::new(self), Box::new(other))
+    }
+}
+
+impl std::ops::Sub for Expr {
+    type Output = Expr;
+
+    fn sub(self, other: Expr) -> Expr {
+        Expr::Sub(Box::new(self), Box::new(other))
+    }
+}
+
+impl std::ops::Mul for Expr {
+    type Output = Expr;
+
+    fn mul(self, other: Expr) -> Expr {
+        Expr::Mul(Box::new(self), Box::new(other))
+    }
+}
@@ -0,0 +1,79 @@
+use cs257_project::{
+    example_graphs::{order_fulfilment::OrderFulfilment, MakeGraph},
+    verifier::{
+        batch::{verify_batch, Query, QueryResult},
+        GraphVerifier,
+    },
+    workflow::{schema::OutputSchema, NodeIdx, WorkflowGraph},
+};
+use z3::{Config, Context};
+
+/// Whether `target` is reachable when the start node of `graph` is given exactly `keys`.
+fn reachable_with_inputs(graph: &WorkflowGraph, keys: &[String], target: NodeIdx) -> bool {
+    let mut given = graph.clone();
+    let start = given.start.take().unwrap();
+    let schema = keys
+        .iter()
+        .fold(OutputSchema::new(), |schema, key| schema.add_fixed(key))
+        .build();
+    let inputs = given.add_node("given_inputs", vec![], schema);
+    given.add_edge(inputs, start, vec![]).set_start(inputs);
+    let ctx = Context::new(&Config::default());
+    let reachable = GraphVerifier::new(&given, &ctx)
+        .is_reachable(target)
+        .is_some();
+    reachable
+}
+
+#[test]
+fn test_batch_matches_sequential() {
+    let graph_ext = OrderFulfilment.make_graph();
+    let graph = &graph_ext.graph;
+    let nodes = 0..graph.nodes.len();
+    let queries = nodes
+        .clone()
+        .map(Query::Reachable)
+        .chain(nodes.clone().map(Query::MinimumInputSet))
+        .chain([
+            Query::CanEventuallyReach(vec![graph_ext.test_reachable_node]),
+            Query::Robust(vec![graph_ext.test_reachable_node], 1),
+            Query::UnhandledFailures,
+        ])
+        .collect::<Vec<_>>();
+    let results = verify_batch(graph, &queries, 4);
+    assert_eq!(results.len(), queries.len());
+
+    let ctx = Context::new(&Config::default());
+    let graph_verifier = GraphVerifier::new(graph, &ctx);
+    for (query, timed) in queries.iter().zip(results) {
+        match (query, timed.result) {
+            (Query::Reachable(node), QueryResult::Reachable(path)) => {
+                assert_eq!(path.is_some(), graph_verifier.is_reachable(*node).is_some())
+            }
+            (Query::MinimumInputSet(node), QueryResult::MinimumInputSet(keys)) => {
+                // a node may have several minimum sets, so only their sizes must agree
+                let expected = graph_verifier.minimum_input_set_for_reachable(*node);
+                assert_eq!(
+                    keys.as_ref().map(Vec::len),
+                    expected.map(|(keys, _)| keys.len())
+                );
+                if let Some(keys) = keys {
+                    assert!(reachable_with_inputs(graph, &keys, *node));
+                    if let Some((_, fewer)) = keys.split_first() {
+                        assert!(!reachable_with_inputs(graph, fewer, *node));
+                    }
+                }
+            }
+            (Query::CanEventuallyReach(targets), QueryResult::CanEventuallyReach(b)) => {
+                assert_eq!(b, graph_verifier.can_eventually_reach(targets))
+            }
+            (Query::Robust(targets, k), QueryResult::Robust(b)) => {
+                assert_eq!(b, graph_verifier.is_robust(targets, *k))
+            }
+            (Query::UnhandledFailures, QueryResult::UnhandledFailures(failures)) => {
+                assert_eq!(failures, graph_verifier.unhandled_failures())
+            }
+            (query, result) => panic!("{result:?} for {query:?}"),
+        }
+    }
+}
@@ -0,0 +1,126 @@
+use cs257_project::{
+    verifier::{
+        batch::{Query, QueryResult},
+        cache::ResultCache,
+    },
+    workflow::{
+        hash::{canonical_hash, canonical_text},
+        schema::{InputCond, OutputSchema},
+        NodeIdx, WorkflowGraph,
+    },
+};
+
+/// receive -> (approve if `vip`, review) -> ship, with the nodes added in the given order.
+fn graph(order: &[&str]) -> (WorkflowGraph, impl Fn(&str) -> NodeIdx) {
+    let mut g = WorkflowGraph::new();
+    for name in order {
+        let (inputs, schema) = match *name {
+            "receive" => (
+                vec![],
+                OutputSchema::new().add_fixed("order").add_may("vip"),
+            ),
+            "approve" => (vec!["vip"], OutputSchema::new().add_fixed("approved")),
+            "review" => (vec!["order"], OutputSchema::new().add_fixed("reviewed")),
+            _ => (vec!["approved"], OutputSchema::new()),
+        };
+        let inputs = inputs.into_iter().map(|s| s.to_string()).collect();
+        g.add_node(name, inputs, schema.carry_all().build());
+    }
+    let names = order.iter().map(|s| s.to_string()).collect::<Vec<_>>();
+    let idx = move |name: &str| names.iter().position(|n| n == name).unwrap();
+    g.add_edge(
+        idx("receive"),
+        idx("approve"),
+        vec![InputCond::MatchesKey("vip".to_string())],
+    )
+    .add_edge(idx("receive"), idx("review"), vec![])
+    .add_edge(idx("approve"), idx("ship"), vec![])
+    .add_edge(idx("review"), idx("ship"), vec![])
+    .set_start(idx("receive"));
+    (g, idx)
+}
+
+#[test]
+fn test_canonical_hash() {
+    let (a, _) = graph(&["receive", "approve", "review", "ship"]);
+    let (b, _) = graph(&["ship", "review", "receive", "approve"]);
+    assert_eq!(canonical_hash(&a), canonical_hash(&b));
+
+    let mut c = a.clone();
+    c.add_edge(2, 3, vec![]);
+    assert_ne!(canonical_hash(&a), canonical_hash(&c));
+    let mut d = a.clone();
+    d.nodes[3].required_inputs.push("reviewed".to_string());
+    assert_ne!(canonical_hash(&a), canonical_hash(&d));
+    assert_eq!(canonical_text(&a), canonical_text(&b));
+    assert_ne!(canonical_text(&a), canonical_text(&d));
+}
+
+#[test]
+fn test_result_cache() {
+    let dir = std::env::temp_dir().join(format!("verify-cache-test-{}", std::process::id()));
+    let cache = ResultCache::new(&dir);
+    cache.invalidate().unwrap();
+
+    let (a, a_idx) = graph(&["receive", "approve", "review", "ship"]);
+    let queries = vec![
+        Query::Reachable(a_idx("ship")),
+        Query::MinimumInputSet(a_idx("approve")),
+    ];
+    let first = cache.verify_batch(&a, &queries, 2);
+    assert!(first.iter().all(|r| !r.cached));
+
+    // the same graph with its nodes in another order is answered from the cache
+    let (b, b_idx) = graph(&["ship", "review", "receive", "approve"]);
+    let queries = vec![
+        Query::Reachable(b_idx("ship")),
+        Query::MinimumInputSet(b_idx("approve")),
+    ];
+    let second = cache.verify_batch(&b, &queries, 2);
+    assert!(second.iter().all(|r| r.cached));
+    match &second[0].result {
+        QueryResult::Reachable(Some(path)) => {
+            assert_eq!(path.first().unwrap().node_idx, b_idx("receive"));
+            assert_eq!(path.last().unwrap().node_idx, b_idx("ship"));
+        }
+        result => panic!("{result:?}"),
+    }
+    match &second[1].result {
+        QueryResult::MinimumInputSet(keys) => assert_eq!(keys, &Some(vec![])),
+        result => panic!("{result:?}"),
+    }
+
+    assert_eq!(cache.invalidate().unwrap(), 1);
+    assert!(cache
+        .verify_batch(&b, &queries, 2)
+        .iter()
+        .all(|r| !r.cached));
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_hash_collision_is_a_miss() {
+    let dir = std::env::temp_dir().join(format!("verify-cache-collision-{}", std::process::id()));
+    let cache = ResultCache::new(&dir);
+    cache.invalidate().unwrap();
+
+    let (a, a_idx) = graph(&["receive", "approve", "review", "ship"]);
+    let mut b = a.clone();
+    b.nodes[a_idx("ship")]
+        .required_inputs
+        .push("missing".to_string());
+    let queries = vec![Query::MinimumInputSet(a_idx("ship"))];
+    cache.verify_batch(&a, &queries, 1);
+
+    // pretend that `b` has the hash of `a`, by storing the results of `a` under the hash of `b`
+    let path = |graph: &WorkflowGraph| dir.join(format!("{:016x}.json", canonical_hash(graph)));
+    std::fs::rename(path(&a), path(&b)).unwrap();
+    let results = cache.verify_batch(&b, &queries, 1);
+    assert!(!results[0].cached);
+    match &results[0].result {
+        QueryResult::MinimumInputSet(keys) => assert_eq!(keys, &Some(vec!["missing".to_string()])),
+        result => panic!("{result:?}"),
+    }
+    assert!(cache.verify_batch(&b, &queries, 1)[0].cached);
+    std::fs::remove_dir_all(&dir).unwrap();
+}
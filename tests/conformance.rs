@@ -0,0 +1,200 @@
+use cs257_project::workflow::{
+    conformance::{check_conformance, Divergence, LoggedStep, Nonconformance},
+    failure::ErrorMatch,
+    schema::OutputSchema,
+    WorkflowGraph,
+};
+
+/// receive_payment -> charge_card -> ship, where a declined card is reported by notify_customer
+fn payment_graph() -> WorkflowGraph {
+    let mut g = WorkflowGraph::new();
+    let receive_payment = g.add_node(
+        "receive_payment",
+        vec![],
+        OutputSchema::new().add_fixed("payment").carry_all().build(),
+    );
+    let charge_card = g.add_node(
+        "charge_card",
+        vec!["payment".to_string()],
+        OutputSchema::new().add_fixed("receipt").carry_all().build(),
+    );
+    let ship = g.add_node(
+        "ship",
+        vec!["receipt".to_string()],
+        OutputSchema::new().build(),
+    );
+    let notify_customer = g.add_node(
+        "notify_customer",
+        vec!["payment".to_string()],
+        OutputSchema::new().build(),
+    );
+    g.add_edge(receive_payment, charge_card, vec![])
+        .add_edge(charge_card, ship, vec![])
+        .add_failure(
+            charge_card,
+            "declined",
+            OutputSchema::new().carry_all().build(),
+        )
+        .add_catch(charge_card, ErrorMatch::All, notify_customer)
+        .set_start(receive_payment);
+    g
+}
+
+#[test]
+fn test_conformance() {
+    let graph = payment_graph();
+    let check = |lines: &str| check_conformance(&graph, &LoggedStep::parse_lines(lines).unwrap());
+
+    assert_eq!(
+        check(
+            r#"
+            {"node": "receive_payment", "input_keys": ["card"], "output_keys": ["card", "payment"]}
+            {"node": "charge_card", "input_keys": ["card", "payment"], "output_keys": ["card", "payment"], "error": "declined"}
+            {"node": "notify_customer", "input_keys": ["card", "payment"], "output_keys": []}
+            "#
+        ),
+        None
+    );
+
+    // a declined card cannot move on to shipping
+    assert_eq!(
+        check(
+            r#"
+            {"node": "receive_payment", "input_keys": [], "output_keys": ["payment"]}
+            {"node": "charge_card", "input_keys": ["payment"], "output_keys": ["payment"], "error": "declined"}
+            {"node": "ship", "input_keys": ["payment"], "output_keys": []}
+            "#
+        ),
+        Some(Nonconformance {
+            step: 2,
+            divergence: Divergence::IllegalTransition,
+        })
+    );
+
+    // the service stopped returning the receipt
+    assert_eq!(
+        check(
+            r#"
+            {"node": "receive_payment", "input_keys": [], "output_keys": ["payment"]}
+            {"node": "charge_card", "input_keys": ["payment"], "output_keys": ["payment", "transaction"]}
+            "#
+        ),
+        Some(Nonconformance {
+            step: 1,
+            divergence: Divergence::MissingOutput("receipt".to_string()),
+        })
+    );
+}
+
+#[test]
+fn test_each_step_moves_on_once() {
+    // review -> approve | reject
+    let mut g = WorkflowGraph::new();
+    let review = g.add_node(
+        "review",
+        vec![],
+        OutputSchema::new().add_fixed("decision").build(),
+    );
+    let approve = g.add_node("approve", vec![], OutputSchema::new().build());
+    let reject = g.add_node("reject", vec![], OutputSchema::new().build());
+    g.add_edge(review, approve, vec![])
+        .add_edge(review, reject, vec![])
+        .set_start(review);
+    let check = |graph: &WorkflowGraph, lines: &str| {
+        check_conformance(graph, &LoggedStep::parse_lines(lines).unwrap())
+    };
+    let illegal = |step| {
+        Some(Nonconformance {
+            step,
+            divergence: Divergence::IllegalTransition,
+        })
+    };
+
+    // a task takes one of its branches, not both
+    assert_eq!(
+        check(
+            &g,
+            r#"
+            {"node": "review", "input_keys": [], "output_keys": ["decision"]}
+            {"node": "approve", "input_keys": [], "output_keys": []}
+            {"node": "reject", "input_keys": [], "output_keys": []}
+            "#
+        ),
+        illegal(2)
+    );
+    // and runs its successor once
+    assert_eq!(
+        check(
+            &g,
+            r#"
+            {"node": "review", "input_keys": [], "output_keys": ["decision"]}
+            {"node": "approve", "input_keys": [], "output_keys": []}
+            {"node": "approve", "input_keys": [], "output_keys": []}
+            "#
+        ),
+        illegal(2)
+    );
+    // a replayed step does not move on again either
+    assert_eq!(
+        check(
+            &payment_graph(),
+            r#"
+            {"node": "receive_payment", "input_keys": [], "output_keys": ["payment"]}
+            {"node": "charge_card", "input_keys": ["payment"], "output_keys": ["payment", "receipt"]}
+            {"node": "charge_card", "input_keys": ["payment"], "output_keys": ["payment", "receipt"]}
+            "#
+        ),
+        illegal(2)
+    );
+
+    // split -> (pack, bill) -> done
+    let mut g = WorkflowGraph::new();
+    let split = g.add_fork("split", OutputSchema::new().build());
+    let pack = g.add_node("pack", vec![], OutputSchema::new().build());
+    let bill = g.add_node("bill", vec![], OutputSchema::new().build());
+    let done = g.add_join("done", vec![], OutputSchema::new().build());
+    g.add_edge(split, pack, vec![])
+        .add_edge(split, bill, vec![])
+        .add_edge(pack, done, vec![])
+        .add_edge(bill, done, vec![])
+        .set_start(split);
+    // a fork moves on to every branch
+    assert_eq!(
+        check(
+            &g,
+            r#"
+            {"node": "split", "input_keys": [], "output_keys": []}
+            {"node": "pack", "input_keys": [], "output_keys": []}
+            {"node": "bill", "input_keys": [], "output_keys": []}
+            {"node": "done", "input_keys": [], "output_keys": []}
+            "#
+        ),
+        None
+    );
+    // a join waits for both branches
+    assert_eq!(
+        check(
+            &g,
+            r#"
+            {"node": "split", "input_keys": [], "output_keys": []}
+            {"node": "pack", "input_keys": [], "output_keys": []}
+            {"node": "done", "input_keys": [], "output_keys": []}
+            "#
+        ),
+        illegal(2)
+    );
+    // and each branch feeds it once
+    assert_eq!(
+        check(
+            &g,
+            r#"
+            {"node": "split", "input_keys": [], "output_keys": []}
+            {"node": "pack", "input_keys": [], "output_keys": []}
+            {"node": "bill", "input_keys": [], "output_keys": []}
+            {"node": "done", "input_keys": [], "output_keys": []}
+            {"node": "done", "input_keys": [], "output_keys": []}
+            "#
+        ),
+        illegal(4)
+    );
+}
@@ -0,0 +1,150 @@
+use std::sync::Arc;
+
+use cs257_project::{
+    verifier::{
+        contract::{check_contracts, check_module_contract, ContractViolation},
+        GraphVerifier,
+    },
+    workflow::{
+        contract::Contract, module::WorkflowModule, schema::OutputSchema, NodeIdx, WorkflowGraph,
+    },
+};
+use z3::{Config, Context};
+
+fn keys(keys: &[&str]) -> Vec<String> {
+    keys.iter().map(|s| s.to_string()).collect()
+}
+
+/// receive_order -> price_order -> bill_customer
+fn order_graph() -> (WorkflowGraph, NodeIdx, NodeIdx) {
+    let mut g = WorkflowGraph::new();
+    let receive_order = g.add_node(
+        "receive_order",
+        vec![],
+        OutputSchema::new()
+            .add_fixed("order_id")
+            .add_fixed("customer_id")
+            .build(),
+    );
+    let price_order = g.add_node(
+        "price_order",
+        vec!["order_id".to_string()],
+        OutputSchema::new().add_fixed("total").carry_all().build(),
+    );
+    let bill_customer = g.add_node(
+        "bill_customer",
+        vec!["total".to_string(), "customer_id".to_string()],
+        OutputSchema::new().build(),
+    );
+    g.add_edge(receive_order, price_order, vec![])
+        .add_edge(price_order, bill_customer, vec![])
+        .set_contract(
+            receive_order,
+            Contract::new(vec![], keys(&["order_id", "customer_id"])),
+        )
+        .set_contract(
+            price_order,
+            Contract::new(keys(&["order_id"]), keys(&["order_id", "total"])),
+        )
+        .set_start(receive_order);
+    (g, price_order, bill_customer)
+}
+
+#[test]
+fn test_node_contracts() {
+    let (mut graph, price_order, bill_customer) = order_graph();
+    assert!(check_contracts(&graph).is_empty());
+    let ctx = Context::new(&Config::default());
+    {
+        // `customer_id` is carried by `price_order`, but its contract does not guarantee it
+        let graph_verifier = GraphVerifier::new(&graph, &ctx);
+        assert!(graph_verifier.is_reachable(bill_customer).is_some());
+        let graph_verifier = GraphVerifier::with_contracts(&graph, &ctx);
+        assert!(graph_verifier.is_reachable(price_order).is_some());
+        assert!(graph_verifier.is_reachable(bill_customer).is_none());
+    }
+
+    graph.nodes[price_order].contract = Some(Contract::new(
+        keys(&["customer_id"]),
+        keys(&["order_id", "customer_id", "total", "discount"]),
+    ));
+    assert_eq!(
+        check_contracts(&graph),
+        vec![
+            ContractViolation::UnassumedInput {
+                node: price_order,
+                key: "order_id".to_string(),
+            },
+            ContractViolation::MissingGuarantee {
+                node: price_order,
+                key: "order_id".to_string(),
+            },
+            ContractViolation::MissingGuarantee {
+                node: price_order,
+                key: "discount".to_string(),
+            },
+        ]
+    );
+    let graph_verifier = GraphVerifier::with_contracts(&graph, &ctx);
+    assert!(graph_verifier.is_reachable(bill_customer).is_some());
+}
+
+#[test]
+fn test_module_contract() {
+    let mut g = WorkflowGraph::new();
+    let authenticate = g.add_node(
+        "authenticate",
+        vec!["credentials".to_string()],
+        OutputSchema::new().add_fixed("session").carry_all().build(),
+    );
+    let load_user = g.add_node(
+        "load_user",
+        vec!["session".to_string()],
+        OutputSchema::new().add_fixed("user").build(),
+    );
+    g.add_edge(authenticate, load_user, vec![])
+        .set_start(authenticate);
+    let module = WorkflowModule::new(
+        "login",
+        g,
+        load_user,
+        keys(&["credentials", "locale"]),
+        keys(&["session", "user", "locale"]),
+    );
+    let valid = module
+        .clone()
+        .with_contract(Contract::new(keys(&["credentials"]), keys(&["user"])));
+    assert!(check_module_contract(&valid).is_empty());
+    // `load_user` drops every input key
+    let invalid = module.with_contract(Contract::new(
+        keys(&["credentials", "locale"]),
+        keys(&["user", "session"]),
+    ));
+    assert_eq!(
+        check_module_contract(&invalid),
+        vec![ContractViolation::MissingGuarantee {
+            node: load_user,
+            key: "session".to_string(),
+        }]
+    );
+
+    // a caller relies on the module contract only
+    let module = Arc::new(valid);
+    let mut g = WorkflowGraph::new();
+    let login = g.add_subworkflow(
+        "login",
+        keys(&["credentials"]),
+        module,
+        OutputSchema::new().build(),
+    );
+    let show_dashboard = g.add_node(
+        "show_dashboard",
+        keys(&["user"]),
+        OutputSchema::new().build(),
+    );
+    g.add_edge(login, show_dashboard, vec![]).set_start(login);
+    assert!(check_contracts(&g).is_empty());
+    let ctx = Context::new(&Config::default());
+    let graph_verifier = GraphVerifier::with_contracts(&g, &ctx);
+    assert!(graph_verifier.is_reachable(show_dashboard).is_some());
+}
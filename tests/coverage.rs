@@ -0,0 +1,117 @@
+use cs257_project::{
+    verifier::coverage::{covering_test_suite, TestSuite},
+    workflow::{
+        schema::{InputCond, KeyRule, OutputSchema},
+        simulate::{simulate, Values},
+        value::{CmpOp, Value, ValueType},
+        WorkflowGraph,
+    },
+};
+
+/// receive_claim -> triage -> (fast_track | standard | audit), where only vip claims get a priority
+/// and nothing produces the `audit_log` that `audit` needs
+fn claim_graph() -> WorkflowGraph {
+    let mut g = WorkflowGraph::new();
+    let receive_claim = g.add_node(
+        "receive_claim",
+        vec![],
+        OutputSchema::new()
+            .add_fixed("claim")
+            .add_rule_for_every_input(
+                KeyRule::Fixed("priority".to_string()),
+                InputCond::MatchesKey("vip".to_string()),
+            )
+            .build(),
+    );
+    let triage = g.add_node(
+        "triage",
+        vec!["claim".to_string()],
+        OutputSchema::new().carry_all().build(),
+    );
+    let fast_track = g.add_node(
+        "fast_track",
+        vec!["priority".to_string()],
+        OutputSchema::new().build(),
+    );
+    let standard = g.add_node(
+        "standard",
+        vec!["claim".to_string()],
+        OutputSchema::new().build(),
+    );
+    let audit = g.add_node(
+        "audit",
+        vec!["audit_log".to_string()],
+        OutputSchema::new().build(),
+    );
+    g.add_edge(receive_claim, triage, vec![])
+        .add_edge(triage, fast_track, vec![])
+        .add_edge(triage, standard, vec![])
+        .add_edge(triage, audit, vec![])
+        .set_start(receive_claim);
+    g
+}
+
+fn edge(src: &str, dst: &str) -> (String, String) {
+    (src.to_string(), dst.to_string())
+}
+
+#[test]
+fn test_covering_test_suite() {
+    let suite = covering_test_suite(&claim_graph());
+    assert_eq!(suite.uncovered_edges, vec![edge("triage", "audit")]);
+    let covered = suite
+        .tests
+        .iter()
+        .flat_map(|test| test.edges.clone())
+        .collect::<Vec<_>>();
+    for e in [
+        edge("receive_claim", "triage"),
+        edge("triage", "fast_track"),
+        edge("triage", "standard"),
+    ] {
+        assert!(covered.contains(&e));
+    }
+    // only the fast track needs the `vip` input
+    assert_eq!(suite.tests.len(), 2);
+    for test in &suite.tests {
+        let vip = test.inputs.contains_key("vip");
+        assert_eq!(vip, test.path.contains(&"fast_track".to_string()));
+    }
+
+    let replayed = TestSuite::from_json(&suite.to_json()).unwrap();
+    assert_eq!(replayed, suite);
+}
+
+#[test]
+fn test_typed_inputs() {
+    // apply -> (manual_review if amount > 1000 | auto_approve if amount <= 1000)
+    let mut g = WorkflowGraph::new();
+    let apply = g.add_node(
+        "apply",
+        vec!["amount".to_string()],
+        OutputSchema::new().carry_all().build(),
+    );
+    let manual_review = g.add_node("manual_review", vec![], OutputSchema::new().build());
+    let auto_approve = g.add_node("auto_approve", vec![], OutputSchema::new().build());
+    let compare = |op, n| vec![InputCond::Compare("amount".to_string(), op, Value::Int(n))];
+    g.add_edge(apply, manual_review, compare(CmpOp::Gt, 1000))
+        .add_edge(apply, auto_approve, compare(CmpOp::Le, 1000))
+        .set_key_type("amount", ValueType::Int)
+        .set_start(apply);
+
+    let suite = covering_test_suite(&g);
+    assert!(suite.uncovered_edges.is_empty());
+    assert_eq!(suite.tests.len(), 2);
+    // replaying the inputs takes the same path
+    for test in &suite.tests {
+        let amount = test.inputs["amount"].as_i64().unwrap();
+        let inputs = Values::from([("amount".to_string(), amount.to_string())]);
+        let traces = simulate(&g, &inputs);
+        let path = traces[0]
+            .steps
+            .iter()
+            .map(|step| g.nodes[step.node_idx].name.clone())
+            .collect::<Vec<_>>();
+        assert_eq!(path, test.path);
+    }
+}
@@ -0,0 +1,284 @@
+use std::sync::Arc;
+
+use cs257_project::{
+    example_graphs::{buy_sell_stock::BuySellStockGraph, MakeGraph},
+    workflow::{
+        contract::Contract,
+        diff::{diff, SemanticChange, StructuralChange},
+        failure::ErrorMatch,
+        module::WorkflowModule,
+        schema::{InputCond, KeyRule, OutputSchema, SchemaEdit},
+        value::{Value, ValueSpec, ValueType},
+        MapSpec, NodeKind, WorkflowGraph,
+    },
+};
+
+#[test]
+fn test_diff() {
+    let before = BuySellStockGraph::new(false).make_graph().graph;
+    assert!(diff(&before, &before).is_empty());
+
+    let node = |name: &str| before.nodes.iter().position(|n| n.name == name).unwrap();
+    let mut after = before.clone();
+    let notify = after.add_node("notify", vec![], OutputSchema::new().build());
+    after
+        .edit_schema(
+            node("check_stock_price"),
+            &SchemaEdit::AddFixedKey("stock_name".to_string()),
+        )
+        .edit_schema(
+            node("sell"),
+            &SchemaEdit::RemoveRule(
+                KeyRule::IdWithPrefix("previous_input.".to_string()),
+                InputCond::Always,
+            ),
+        )
+        .add_edge(node("report_result"), notify, vec![]);
+    after.adj_list[node("buy_or_sell")][0].1.clear();
+
+    let result = diff(&before, &after);
+    assert_eq!(
+        result.structural,
+        vec![
+            StructuralChange::FixedKeysChanged {
+                node: "check_stock_price".to_string(),
+                added: vec!["stock_name".to_string()],
+                removed: vec![],
+            },
+            StructuralChange::RulesChanged {
+                node: "sell".to_string(),
+                added: vec![],
+                removed: vec![(
+                    KeyRule::IdWithPrefix("previous_input.".to_string()),
+                    InputCond::Always
+                )],
+            },
+            StructuralChange::NodeAdded("notify".to_string()),
+            StructuralChange::EdgeConditionsChanged {
+                src: "buy_or_sell".to_string(),
+                dst: "buy".to_string(),
+                before: vec![InputCond::MatchesKeyValue(
+                    "rec".to_string(),
+                    "buy".to_string()
+                )],
+                after: vec![],
+            },
+            StructuralChange::EdgeAdded {
+                src: "report_result".to_string(),
+                dst: "notify".to_string(),
+            },
+        ]
+    );
+    // `stock_name` no longer has to be given by the user
+    assert!(result
+        .semantic
+        .contains(&SemanticChange::MinimumInputsChanged {
+            node: "buy".to_string(),
+            before: vec!["stock_name".to_string()],
+            after: vec![],
+        }));
+    let report = result.to_string();
+    assert!(report.contains("+ node notify"));
+    assert!(report.contains("~ buy needs [] instead of [stock_name]"));
+}
+
+#[test]
+fn test_diff_of_annotations() {
+    let module = |contract: Option<Contract>| {
+        let mut g = WorkflowGraph::new();
+        let inner = g.add_node("inner", vec![], OutputSchema::new().build());
+        g.set_start(inner);
+        let module = WorkflowModule::new("payments", g, inner, vec![], vec![]);
+        Arc::new(match contract {
+            Some(contract) => module.with_contract(contract),
+            None => module,
+        })
+    };
+    let mut before = WorkflowGraph::new();
+    let charge = before.add_subworkflow(
+        "charge",
+        vec![],
+        module(None),
+        OutputSchema::new().add_fixed("status").build(),
+    );
+    let ship = before.add_node("ship", vec![], OutputSchema::new().build());
+    let refund = before.add_node("refund", vec![], OutputSchema::new().build());
+    before
+        .add_edge(charge, ship, vec![])
+        .add_failure(
+            charge,
+            "Declined",
+            OutputSchema::new().add_fixed("error").build(),
+        )
+        .add_catch(charge, ErrorMatch::All, refund)
+        .set_key_type("status", ValueType::String)
+        .set_start(charge);
+
+    let strings = |keys: &[&str]| keys.iter().map(|s| s.to_string()).collect::<Vec<_>>();
+    let contract = Contract::new(vec![], strings(&["status"]));
+    let statuses = ValueType::Enum(strings(&["OK", "DECLINED"]));
+    let status_spec = ValueSpec::OneOf(vec![Value::String("OK".to_string())]);
+    let declined = ErrorMatch::Named(strings(&["Declined"]));
+    let mut after = before.clone();
+    after.nodes[charge].kind = NodeKind::SubWorkflow(module(Some(contract.clone())));
+    after.nodes[charge]
+        .output_schema
+        .value_specs
+        .insert("status".to_string(), status_spec.clone());
+    after.nodes[charge].failures[0].output_schema = OutputSchema::new()
+        .add_fixed("error")
+        .add_fixed("cause")
+        .build();
+    after.catch_list[charge][0].1 = declined.clone();
+    after
+        .set_contract(ship, contract.clone())
+        .set_compensation(charge, refund)
+        .set_key_type("status", statuses.clone());
+
+    let result = diff(&before, &after);
+    assert_eq!(
+        result.structural,
+        vec![
+            StructuralChange::KeyTypeChanged {
+                key: "status".to_string(),
+                before: Some(ValueType::String),
+                after: Some(statuses),
+            },
+            StructuralChange::FailureOutputsChanged {
+                node: "charge".to_string(),
+                error: "Declined".to_string(),
+            },
+            StructuralChange::ValueSpecChanged {
+                node: "charge".to_string(),
+                key: "status".to_string(),
+                before: None,
+                after: Some(status_spec),
+            },
+            StructuralChange::ModuleContractChanged {
+                node: "charge".to_string(),
+                before: None,
+                after: Some(contract.clone()),
+            },
+            StructuralChange::CompensationChanged {
+                node: "charge".to_string(),
+                before: None,
+                after: Some("refund".to_string()),
+            },
+            StructuralChange::ContractChanged {
+                node: "ship".to_string(),
+                before: None,
+                after: Some(contract),
+            },
+            StructuralChange::CatchErrorsChanged {
+                src: "charge".to_string(),
+                dst: "refund".to_string(),
+                before: ErrorMatch::All,
+                after: declined,
+            },
+        ]
+    );
+    let report = result.to_string();
+    assert!(report.contains("~ node charge: outputs on failure Declined"));
+    assert!(report.contains("~ node charge: compensation None -> Some(\"refund\")"));
+}
+
+#[test]
+fn test_diff_of_nested_graphs_and_edges() {
+    let iterator = |steps: &[&str]| {
+        let mut g = WorkflowGraph::new();
+        let nodes = steps
+            .iter()
+            .map(|step| g.add_node(step, vec![], OutputSchema::new().build()))
+            .collect::<Vec<_>>();
+        nodes.windows(2).for_each(|pair| {
+            g.add_edge(pair[0], pair[1], vec![]);
+        });
+        g.set_start(nodes[0]);
+        (g, *nodes.last().unwrap())
+    };
+    let (prices, price) = iterator(&["price"]);
+    let (module_graph, inner) = iterator(&["inner"]);
+    let mut before = WorkflowGraph::new();
+    let quote = before.add_map(
+        "quote",
+        vec![],
+        MapSpec::new("stocks", "stock", prices, price, "prices"),
+        OutputSchema::new().add_fixed("prices").build(),
+    );
+    let charge = before.add_subworkflow(
+        "charge",
+        vec![],
+        Arc::new(WorkflowModule::new(
+            "payments",
+            module_graph,
+            inner,
+            vec!["amount".to_string()],
+            vec![],
+        )),
+        OutputSchema::new().build(),
+    );
+    let conditions = vec![
+        InputCond::MatchesKey("prices".to_string()),
+        InputCond::MatchesKeyValue("mode".to_string(), "fast".to_string()),
+    ];
+    before
+        .add_edge(quote, charge, conditions.clone())
+        .set_start(quote);
+
+    // the same conditions in another order are not a change
+    let mut after = before.clone();
+    after.adj_list[quote][0].1.reverse();
+    assert!(diff(&before, &after).structural.is_empty());
+
+    let (prices, price) = iterator(&["price", "round"]);
+    let (module_graph, inner) = iterator(&["inner", "receipt"]);
+    after.nodes[quote].kind = NodeKind::Map(Box::new(MapSpec::new(
+        "stocks", "stock", prices, price, "quotes",
+    )));
+    after.nodes[charge].kind = NodeKind::SubWorkflow(Arc::new(WorkflowModule::new(
+        "payments",
+        module_graph,
+        inner,
+        vec!["amount".to_string(), "currency".to_string()],
+        vec![],
+    )));
+    after.add_edge(quote, charge, vec![]);
+
+    let result = diff(&before, &after);
+    let nested =
+        |node: &str, part: &str, before: &str, after: &str| StructuralChange::NestedChanged {
+            node: node.to_string(),
+            part: part.to_string(),
+            before: before.to_string(),
+            after: after.to_string(),
+        };
+    let changes = &result.structural;
+    assert!(changes.contains(&nested("quote", "results key", "prices", "quotes")));
+    assert!(changes.contains(&nested("quote", "iterator end", "price", "round")));
+    assert!(changes.contains(&nested(
+        "charge",
+        "module inputs",
+        "[amount]",
+        "[amount, currency]"
+    )));
+    assert!(changes.contains(&nested("charge", "module end", "inner", "receipt")));
+    assert!(changes.iter().any(|change| matches!(
+        change,
+        StructuralChange::NestedChanged { node, part, .. }
+            if node == "quote" && part == "iterator graph"
+    )));
+    assert!(changes.iter().any(|change| matches!(
+        change,
+        StructuralChange::NestedChanged { node, part, .. }
+            if node == "charge" && part == "module graph"
+    )));
+    // the parallel edge is added, the one with conditions is unchanged
+    assert!(changes.contains(&StructuralChange::EdgeAdded {
+        src: "quote".to_string(),
+        dst: "charge".to_string(),
+    }));
+    assert_eq!(changes.len(), 7);
+    assert!(result
+        .to_string()
+        .contains("~ node charge: module graph changed"));
+}
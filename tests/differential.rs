@@ -0,0 +1,530 @@
+//! Differential testing of the verifier against a brute-force oracle on small random graphs.
+//!
+//! The oracle simulates every subset of the keys that may matter, with every value of the typed ones, and looks at
+//! every execution. The graphs are random DAGs, diamonds, layers and fan-outs, some with failures and catch edges,
+//! and some with edges guarded by the value of an enum key.
+//! Set `DIFFERENTIAL_CASES` to run more graphs than the default. Before changing the encoding, run the CI-sized
+//! test too: `cargo test --release --test differential -- --ignored`.
+
+use std::collections::BTreeSet;
+
+use cs257_project::{
+    example_graphs::{
+        random::{Diamonds, FanOut, Layered, RandomDag, RandomParams, RuleMix},
+        MakeGraph,
+    },
+    verifier::GraphVerifier,
+    workflow::{
+        failure::ErrorMatch,
+        schema::{InputCond, KeyRule, OutputSchema},
+        simulate::{can_take_edge, simulate, Outcome, Step, Trace, Values},
+        value::{Value, ValueSpec, ValueType},
+        NodeIdx, NodeKind, WorkflowGraph,
+    },
+};
+use rand::{Rng, SeedableRng};
+use rand_xorshift::XorShiftRng;
+use z3::{Config, Context};
+
+/// The enum key of value-guarded graphs.
+const GUARD_KEY: &str = "key_0";
+const GUARD_VALUES: [&str; 2] = ["a", "b"];
+/// A key that no node outputs, to disable edges.
+const NEVER_OUTPUT: &str = "never_output";
+
+/// Keys mentioned by `graph`, and the keys that rules may turn into them.
+fn key_universe(graph: &WorkflowGraph) -> Vec<String> {
+    let mut keys = BTreeSet::new();
+    let mut rules = vec![];
+    let cond_key = |cond: &InputCond| cond.key().map(|s| s.to_string());
+    for node in &graph.nodes {
+        keys.extend(node.required_inputs.iter().cloned());
+        let schemas = std::iter::once(&node.output_schema)
+            .chain(node.failures.iter().map(|failure| &failure.output_schema));
+        for schema in schemas {
+            keys.extend(schema.created_keys().map(|s| s.to_string()));
+            for (rule, cond) in schema.dynamic_keys() {
+                if !matches!(rule, KeyRule::Fixed(_)) {
+                    rules.push(rule.clone());
+                }
+                keys.extend(cond_key(cond));
+            }
+        }
+    }
+    for adj in &graph.adj_list {
+        for (_, conds) in adj {
+            keys.extend(conds.iter().filter_map(cond_key));
+        }
+    }
+    loop {
+        let stripped = keys
+            .iter()
+            .flat_map(|s| rules.iter().filter_map(|rule| rule.carried_key(s)))
+            .map(|s| s.to_string())
+            .filter(|s| !keys.contains(s))
+            .collect::<Vec<_>>();
+        if stripped.is_empty() {
+            break;
+        }
+        keys.extend(stripped);
+    }
+    keys.into_iter().collect()
+}
+
+/// Every assignment of values to `keys`: a key of an enum type takes each of its values, any other key the
+/// empty value.
+fn input_values(graph: &WorkflowGraph, keys: &[String]) -> Vec<Values> {
+    keys.iter().fold(vec![Values::new()], |assignments, key| {
+        let values = match graph.key_types.get(key) {
+            Some(ValueType::Enum(values)) => values.clone(),
+            _ => vec![String::new()],
+        };
+        assignments
+            .iter()
+            .flat_map(|assignment| {
+                values.iter().map(|value| {
+                    let mut assignment = assignment.clone();
+                    assignment.insert(key.clone(), value.clone());
+                    assignment
+                })
+            })
+            .collect()
+    })
+}
+
+/// Whether an execution stops at `step`, as `GraphVerifier` sees it: a task cannot take any outgoing edge, a fork
+/// cannot take one of them, the node fails without a catch edge it can take, or it cannot run at all.
+fn is_stuck(graph: &WorkflowGraph, step: &Step) -> bool {
+    let node = step.node_idx;
+    match &step.outcome {
+        Outcome::Completed(outputs) => {
+            let mut enabled = graph.adj_list[node]
+                .iter()
+                .map(|(dst, conds)| can_take_edge(graph, outputs, *dst, conds));
+            match graph.nodes[node].kind {
+                NodeKind::Fork if !graph.adj_list[node].is_empty() => !enabled.all(|b| b),
+                _ => !enabled.any(|b| b),
+            }
+        }
+        Outcome::Failed(error, outputs) => match graph.catch_for(node, error) {
+            Some(catch_idx) => {
+                !can_take_edge(graph, outputs, graph.catch_list[node][catch_idx].0, &[])
+            }
+            None => true,
+        },
+        Outcome::MissingInputs(_) | Outcome::NestedRunStopped => true,
+    }
+}
+
+/// For every input set that contains the required inputs of the start node, every execution with any values.
+struct Oracle<'g> {
+    graph: &'g WorkflowGraph,
+    /// The size of each input set, and its inputs with every assignment of values.
+    input_sets: Vec<(usize, Vec<Values>)>,
+    runs: Vec<(usize, Vec<Trace>)>,
+}
+
+impl<'g> Oracle<'g> {
+    fn new(graph: &'g WorkflowGraph) -> Self {
+        let universe = key_universe(graph);
+        let required = &graph.nodes[graph.start.unwrap()].required_inputs;
+        let input_sets = (0..1usize << universe.len())
+            .map(|mask| {
+                universe
+                    .iter()
+                    .enumerate()
+                    .filter(|(i, _)| mask & (1 << i) != 0)
+                    .map(|(_, s)| s.clone())
+                    .collect::<Vec<_>>()
+            })
+            .filter(|keys| required.iter().all(|s| keys.contains(s)))
+            .map(|keys| (keys.len(), input_values(graph, &keys)))
+            .collect::<Vec<_>>();
+        let runs = input_sets
+            .iter()
+            .map(|(size, inputs)| {
+                let traces = inputs.iter().flat_map(|inputs| simulate(graph, inputs));
+                (*size, traces.collect())
+            })
+            .collect();
+        Self {
+            graph,
+            input_sets,
+            runs,
+        }
+    }
+
+    fn is_reachable(&self, target: NodeIdx) -> bool {
+        self.min_reachable(target).is_some()
+    }
+
+    fn min_reachable(&self, target: NodeIdx) -> Option<usize> {
+        self.runs
+            .iter()
+            .filter(|(_, traces)| traces.iter().any(|trace| trace.reaches(target)))
+            .map(|(size, _)| *size)
+            .min()
+    }
+
+    /// The sizes of the input sets with which no execution stops before `target`. Executions end at `target`,
+    /// so that its successors cannot get stuck. Its edges stay, so that the joins after it still wait for them.
+    fn eventually_reached(&self, target: NodeIdx) -> Vec<usize> {
+        let mut cut = self.graph.clone();
+        cut.adj_list[target].iter_mut().for_each(|(_, conds)| {
+            conds.push(InputCond::MatchesKey(NEVER_OUTPUT.to_string()));
+        });
+        cut.nodes[target].failures.clear();
+        self.input_sets
+            .iter()
+            .filter(|(_, inputs)| {
+                inputs
+                    .iter()
+                    .flat_map(|inputs| simulate(&cut, inputs))
+                    .all(|trace| {
+                        trace
+                            .steps
+                            .iter()
+                            .all(|step| step.node_idx == target || !is_stuck(&cut, step))
+                    })
+            })
+            .map(|(size, _)| *size)
+            .collect()
+    }
+
+    fn can_eventually_reach(&self, target: NodeIdx) -> bool {
+        self.eventually_reached(target).len() == self.input_sets.len()
+    }
+
+    fn min_eventually_reached(&self, target: NodeIdx) -> Option<usize> {
+        self.eventually_reached(target).into_iter().min()
+    }
+}
+
+/// The first answer of the verifier that differs from the oracle.
+fn mismatch(graph: &WorkflowGraph) -> Option<String> {
+    let oracle = Oracle::new(graph);
+    let ctx = Context::new(&Config::default());
+    let graph_verifier = GraphVerifier::new(graph, &ctx);
+    for target in 0..graph.nodes.len() {
+        let name = &graph.nodes[target].name;
+        let reachable = graph_verifier.is_reachable(target).is_some();
+        if reachable != oracle.is_reachable(target) {
+            return Some(format!("is_reachable({name}) is {reachable}"));
+        }
+        let eventually = graph_verifier.can_eventually_reach(&[target]);
+        if eventually != oracle.can_eventually_reach(target) {
+            return Some(format!("can_eventually_reach({name}) is {eventually}"));
+        }
+        let min_reachable = graph_verifier.minimum_input_set_for_reachable(target);
+        let min_size = min_reachable.as_ref().map(|(keys, _)| keys.len());
+        if min_size != oracle.min_reachable(target) {
+            return Some(format!(
+                "minimum_input_set_for_reachable({name}) is {min_reachable:?}, expected size {:?}",
+                oracle.min_reachable(target)
+            ));
+        }
+        if let Some((keys, _)) = &min_reachable {
+            if !input_values(graph, keys)
+                .iter()
+                .flat_map(|inputs| simulate(graph, inputs))
+                .any(|trace| trace.reaches(target))
+            {
+                return Some(format!(
+                    "minimum_input_set_for_reachable({name}) is {keys:?}, which does not reach it"
+                ));
+            }
+        }
+        let min_eventually = graph_verifier.minimum_input_set_for_can_eventually_reach(&[target]);
+        if min_eventually != oracle.min_eventually_reached(target) {
+            return Some(format!(
+                "minimum_input_set_for_can_eventually_reach({name}) is {min_eventually:?}, expected {:?}",
+                oracle.min_eventually_reached(target)
+            ));
+        }
+    }
+    None
+}
+
+/// Keep the nodes reachable from the start node along edges and catch edges, since the verifier needs every node
+/// to be.
+fn without_unreachable_nodes(graph: &WorkflowGraph) -> WorkflowGraph {
+    let start = graph.start.unwrap();
+    let mut reachable = vec![false; graph.nodes.len()];
+    let mut stack = vec![start];
+    while let Some(node) = stack.pop() {
+        if !std::mem::replace(&mut reachable[node], true) {
+            stack.extend(graph.adj_list[node].iter().map(|(dst, _)| *dst));
+            stack.extend(graph.catch_list[node].iter().map(|(dst, _)| *dst));
+        }
+    }
+    let mut kept = WorkflowGraph::new();
+    let mut new_idx = vec![None; graph.nodes.len()];
+    for node in graph.nodes.iter().filter(|node| reachable[node.id]) {
+        let idx = kept.add_node_with_kind(
+            &node.name,
+            node.kind.clone(),
+            node.required_inputs.clone(),
+            node.output_schema.clone(),
+        );
+        kept.nodes[idx].failures = node.failures.clone();
+        new_idx[node.id] = Some(idx);
+    }
+    for (src, adj) in graph.adj_list.iter().enumerate() {
+        for (dst, conds) in adj {
+            if let (Some(src), Some(dst)) = (new_idx[src], new_idx[*dst]) {
+                kept.add_edge(src, dst, conds.clone());
+            }
+        }
+    }
+    for (src, catches) in graph.catch_list.iter().enumerate() {
+        for (dst, errors) in catches {
+            if let (Some(src), Some(dst)) = (new_idx[src], new_idx[*dst]) {
+                kept.add_catch(src, errors.clone(), dst);
+            }
+        }
+    }
+    kept.key_types = graph.key_types.clone();
+    kept.set_start(new_idx[start].unwrap());
+    kept
+}
+
+/// Graphs with one part of `graph` removed: an edge, an edge condition, a catch edge, a failure, a required input,
+/// a fixed key, a rule or a value spec.
+fn shrink_candidates(graph: &WorkflowGraph) -> Vec<WorkflowGraph> {
+    let mut candidates = vec![];
+    for src in 0..graph.nodes.len() {
+        for i in 0..graph.catch_list[src].len() {
+            let mut g = graph.clone();
+            g.catch_list[src].remove(i);
+            candidates.push(without_unreachable_nodes(&g));
+        }
+        for i in 0..graph.nodes[src].failures.len() {
+            let mut g = graph.clone();
+            g.nodes[src].failures.remove(i);
+            candidates.push(g);
+        }
+        for i in 0..graph.adj_list[src].len() {
+            let mut g = graph.clone();
+            g.adj_list[src].remove(i);
+            candidates.push(without_unreachable_nodes(&g));
+            for j in 0..graph.adj_list[src][i].1.len() {
+                let mut g = graph.clone();
+                g.adj_list[src][i].1.remove(j);
+                candidates.push(g);
+            }
+        }
+    }
+    for node in 0..graph.nodes.len() {
+        let schema = &graph.nodes[node].output_schema;
+        for i in 0..graph.nodes[node].required_inputs.len() {
+            let mut g = graph.clone();
+            g.nodes[node].required_inputs.remove(i);
+            candidates.push(g);
+        }
+        for i in 0..schema.fixed_keys.len() {
+            let mut g = graph.clone();
+            g.nodes[node].output_schema.fixed_keys.remove(i);
+            candidates.push(g);
+        }
+        for i in 0..schema.dynamic_keys.len() {
+            let mut g = graph.clone();
+            g.nodes[node].output_schema.dynamic_keys.remove(i);
+            candidates.push(g);
+        }
+        for key in schema.value_specs.keys() {
+            let mut g = graph.clone();
+            g.nodes[node].output_schema.value_specs.remove(key);
+            candidates.push(g);
+        }
+    }
+    candidates
+}
+
+/// Remove parts of `graph` as long as the verifier still disagrees with the oracle.
+fn shrink(mut graph: WorkflowGraph, mut reason: String) -> (WorkflowGraph, String) {
+    while let Some((smaller, smaller_reason)) = shrink_candidates(&graph)
+        .into_iter()
+        .find_map(|g| mismatch(&g).map(|reason| (g, reason)))
+    {
+        graph = smaller;
+        reason = smaller_reason;
+    }
+    (graph, reason)
+}
+
+fn describe(graph: &WorkflowGraph) -> String {
+    let describe_schema = |schema: &OutputSchema| {
+        format!(
+            "{:?} {:?} {:?}",
+            schema.fixed_keys, schema.dynamic_keys, schema.value_specs
+        )
+    };
+    let mut lines = graph
+        .nodes
+        .iter()
+        .map(|node| {
+            let failures = node
+                .failures
+                .iter()
+                .map(|failure| {
+                    format!(
+                        ", fails with {:?} outputting {}",
+                        failure.error,
+                        describe_schema(&failure.output_schema)
+                    )
+                })
+                .collect::<String>();
+            format!(
+                "{} ({:?}): needs {:?}, outputs {}{failures}",
+                node.name,
+                node.kind,
+                node.required_inputs,
+                describe_schema(&node.output_schema)
+            )
+        })
+        .collect::<Vec<_>>();
+    for (src, adj) in graph.adj_list.iter().enumerate() {
+        for (dst, conds) in adj {
+            lines.push(format!(
+                "{} -> {} if {:?}",
+                graph.nodes[src].name, graph.nodes[*dst].name, conds
+            ));
+        }
+    }
+    for (src, catches) in graph.catch_list.iter().enumerate() {
+        for (dst, errors) in catches {
+            lines.push(format!(
+                "{} catches {:?} -> {}",
+                graph.nodes[src].name, errors, graph.nodes[*dst].name
+            ));
+        }
+    }
+    lines.extend(
+        graph
+            .key_types
+            .iter()
+            .map(|(key, ty)| format!("{key}: {ty:?}")),
+    );
+    lines.push(format!("start: {}", graph.nodes[graph.start.unwrap()].name));
+    lines.join("\n")
+}
+
+/// Let some nodes fail, with outputs of their own, and catch some of the failures in a later node.
+fn add_failures(graph: &mut WorkflowGraph, rng: &mut XorShiftRng) {
+    for node in 0..graph.nodes.len() {
+        if !rng.gen_bool(0.4) {
+            continue;
+        }
+        let error = format!("error_{node}");
+        let schema = match rng.gen_range(0..3) {
+            0 => OutputSchema::new().build(),
+            1 => OutputSchema::new().carry_all().build(),
+            _ => OutputSchema::new()
+                .add_fixed(format!("key_{}", rng.gen_range(0..3)))
+                .build(),
+        };
+        graph.add_failure(node, &error, schema);
+        // nodes are numbered in topological order, so a catch edge to a later node keeps the graph acyclic
+        if node + 1 < graph.nodes.len() && rng.gen_bool(0.6) {
+            let errors = match rng.gen_bool(0.5) {
+                true => ErrorMatch::All,
+                false => ErrorMatch::Named(vec![error]),
+            };
+            let handler = rng.gen_range(node + 1..graph.nodes.len());
+            graph.add_catch(node, errors, handler);
+        }
+    }
+}
+
+/// Make `GUARD_KEY` an enum, let the nodes that create it output any of its values, and guard some edges by them.
+fn add_value_guards(graph: &mut WorkflowGraph, rng: &mut XorShiftRng) {
+    graph.set_key_type(
+        GUARD_KEY,
+        ValueType::Enum(GUARD_VALUES.iter().map(|s| s.to_string()).collect()),
+    );
+    let one_of = ValueSpec::OneOf(
+        GUARD_VALUES
+            .iter()
+            .map(|s| Value::String(s.to_string()))
+            .collect(),
+    );
+    for node in &mut graph.nodes {
+        let schema = &mut node.output_schema;
+        if schema.created_keys().any(|s| s == GUARD_KEY) {
+            schema
+                .value_specs
+                .insert(GUARD_KEY.to_string(), one_of.clone());
+        }
+    }
+    for adj in &mut graph.adj_list {
+        for (_, conds) in adj {
+            if rng.gen_bool(0.4) {
+                let value = GUARD_VALUES[rng.gen_range(0..GUARD_VALUES.len())];
+                conds.push(InputCond::MatchesKeyValue(
+                    GUARD_KEY.to_string(),
+                    value.to_string(),
+                ));
+            }
+        }
+    }
+}
+
+/// Compare the verifier with the oracle on the graphs of `seeds`, and panic with a shrunk graph on a mismatch.
+fn check_against_oracle(seeds: std::ops::Range<u64>) {
+    let rule_mixes = [
+        RuleMix::default(),
+        RuleMix {
+            identity: 1,
+            fixed: 0,
+            prefix: 0,
+        },
+        RuleMix {
+            identity: 1,
+            fixed: 2,
+            prefix: 2,
+        },
+    ];
+    for seed in seeds {
+        let params = RandomParams::new(seed, 2 + seed as usize % 5)
+            .edge_density(0.3)
+            .num_keys(2 + seed as usize % 3)
+            .rule_mix(rule_mixes[seed as usize % rule_mixes.len()])
+            .conditional_edges(0.3);
+        let mut graph = match seed % 4 {
+            0 => RandomDag(params).make_graph(),
+            1 => Diamonds(params).make_graph(),
+            2 => Layered {
+                params,
+                num_layers: 2,
+            }
+            .make_graph(),
+            _ => FanOut(params).make_graph(),
+        }
+        .graph;
+        let mut rng = XorShiftRng::seed_from_u64(seed);
+        match seed / 4 % 3 {
+            0 => {}
+            1 => add_failures(&mut graph, &mut rng),
+            _ => add_value_guards(&mut graph, &mut rng),
+        }
+        if let Some(reason) = mismatch(&graph) {
+            let (graph, reason) = shrink(graph, reason);
+            panic!("seed {seed}: {reason}\n{}", describe(&graph));
+        }
+    }
+}
+
+#[test]
+fn test_verifier_against_oracle() {
+    let num_cases = std::env::var("DIFFERENTIAL_CASES")
+        .map(|s| s.parse().unwrap())
+        .unwrap_or(120u64);
+    check_against_oracle(0..num_cases);
+}
+
+/// The run to do before changing the encoding. It takes about a quarter of an hour in release mode.
+#[test]
+#[ignore]
+fn test_verifier_against_oracle_ci() {
+    check_against_oracle(0..3000);
+}
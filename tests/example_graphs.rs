@@ -0,0 +1,164 @@
+use cs257_project::{
+    example_graphs::{
+        etl_pipeline::EtlPipeline, loan_approval::LoanApproval, order_fulfilment::OrderFulfilment,
+        payment_saga::PaymentSaga, user_signup::UserSignup, MakeGraph,
+    },
+    verifier::{provenance::key_provenance, GraphVerifier},
+    workflow::{NodeIdx, WorkflowGraph},
+};
+use z3::{Config, Context};
+
+fn node(graph: &WorkflowGraph, name: &str) -> NodeIdx {
+    graph.nodes.iter().position(|n| n.name == name).unwrap()
+}
+
+fn minimum_input_set(graph_verifier: &GraphVerifier, target: NodeIdx) -> Vec<String> {
+    let (mut keys, _) = graph_verifier
+        .minimum_input_set_for_reachable(target)
+        .unwrap();
+    keys.sort();
+    keys
+}
+
+#[test]
+fn test_order_fulfilment() {
+    let graph_ext = OrderFulfilment.make_graph();
+    let graph = &graph_ext.graph;
+    let ctx = Context::new(&Config::default());
+    let graph_verifier = GraphVerifier::new(graph, &ctx);
+    let ship_order = graph_ext.test_reachable_node;
+    let notify_customer = node(graph, "notify_customer");
+
+    assert_eq!(
+        minimum_input_set(&graph_verifier, ship_order),
+        vec!["address", "items", "payment_method"]
+    );
+    // without a payment method, the order is stuck before shipping
+    assert!(!graph_verifier.can_eventually_reach(&[notify_customer]));
+    assert_eq!(
+        graph_verifier.minimum_input_set_for_can_eventually_reach(&[notify_customer]),
+        Some(3)
+    );
+    assert!(graph_verifier
+        .is_reachable_on_failure(
+            node(graph, "release_inventory"),
+            node(graph, "charge_payment"),
+            Some("PaymentDeclined")
+        )
+        .is_some());
+    assert_eq!(graph_verifier.unhandled_failures(), vec![]);
+}
+
+#[test]
+fn test_loan_approval() {
+    let graph_ext = LoanApproval.make_graph();
+    let graph = &graph_ext.graph;
+    let ctx = Context::new(&Config::default());
+    let graph_verifier = GraphVerifier::new(graph, &ctx);
+    let approve_loan = graph_ext.test_reachable_node;
+    let credit_check = node(graph, "credit_check");
+
+    assert_eq!(
+        minimum_input_set(&graph_verifier, approve_loan),
+        vec!["amount", "applicant_id"]
+    );
+    // every decision, automatic or manual, is sent to the applicant
+    assert!(graph_verifier.can_eventually_reach(&[node(graph, "notify_applicant")]));
+    assert!(graph_verifier
+        .is_reachable_on_failure(node(graph, "manual_review"), credit_check, None)
+        .is_some());
+    assert!(graph_verifier
+        .is_reachable_on_failure(node(graph, "auto_decision"), credit_check, None)
+        .is_none());
+    assert!(graph_verifier
+        .is_reachable_on_failure(approve_loan, credit_check, None)
+        .is_some());
+    assert_eq!(graph_verifier.unhandled_failures(), vec![]);
+}
+
+#[test]
+fn test_etl_pipeline() {
+    let graph_ext = EtlPipeline.make_graph();
+    let graph = &graph_ext.graph;
+    let ctx = Context::new(&Config::default());
+    let graph_verifier = GraphVerifier::new(graph, &ctx);
+    let report = graph_ext.test_reachable_node;
+
+    // `load` finds the target table among the inputs that `transform` kept
+    assert_eq!(
+        minimum_input_set(&graph_verifier, report),
+        vec!["source_uri", "target_table"]
+    );
+    assert_eq!(graph_verifier.join_conflicts(report), Vec::<String>::new());
+    assert_eq!(
+        graph_verifier.unhandled_failures(),
+        vec![(node(graph, "extract"), "SourceUnavailable".to_string())]
+    );
+    // an unavailable source stops the job before it reports or quarantines anything
+    assert!(!graph_verifier.can_eventually_reach(&[report, node(graph, "quarantine")]));
+    assert_eq!(
+        graph_verifier.minimum_input_set_for_can_eventually_reach(&[report]),
+        None
+    );
+}
+
+#[test]
+fn test_user_signup() {
+    let graph_ext = UserSignup.make_graph();
+    let graph = &graph_ext.graph;
+    let ctx = Context::new(&Config::default());
+    let graph_verifier = GraphVerifier::new(graph, &ctx);
+    let welcome_user = graph_ext.test_reachable_node;
+
+    assert_eq!(
+        minimum_input_set(&graph_verifier, welcome_user),
+        vec!["email", "password"]
+    );
+    assert_eq!(
+        minimum_input_set(&graph_verifier, node(graph, "grant_referral_bonus")),
+        vec!["email", "password", "referral_code"]
+    );
+    assert!(graph_verifier.can_eventually_reach(&[
+        welcome_user,
+        node(graph, "show_error"),
+        node(graph, "delete_account")
+    ]));
+    assert_eq!(graph_verifier.unhandled_failures(), vec![]);
+
+    // the password is dropped when the account is created, and the email is kept
+    assert_eq!(key_provenance(graph, welcome_user, "password"), None);
+    let email = key_provenance(graph, welcome_user, "email").unwrap();
+    assert_eq!(email.user_input, Some("email".to_string()));
+}
+
+#[test]
+fn test_payment_saga() {
+    let graph_ext = PaymentSaga.make_graph();
+    let graph = &graph_ext.graph;
+    let ctx = Context::new(&Config::default());
+    let graph_verifier = GraphVerifier::new(graph, &ctx);
+    let confirm_order = graph_ext.test_reachable_node;
+    let cancel_order = node(graph, "cancel_order");
+    let create_shipment = node(graph, "create_shipment");
+
+    // a failed shipment refunds the payment, then releases the stock
+    for compensation in ["refund_payment", "release_inventory", "cancel_order"] {
+        assert!(graph_verifier
+            .is_reachable_on_failure(node(graph, compensation), create_shipment, None)
+            .is_some());
+    }
+    // a declined card has nothing to refund
+    assert!(graph_verifier
+        .is_reachable_on_failure(
+            node(graph, "refund_payment"),
+            node(graph, "charge_card"),
+            None
+        )
+        .is_none());
+    assert_eq!(graph_verifier.unhandled_failures(), vec![]);
+    assert!(!graph_verifier.can_eventually_reach(&[confirm_order, cancel_order]));
+    assert_eq!(
+        graph_verifier.minimum_input_set_for_can_eventually_reach(&[confirm_order, cancel_order]),
+        Some(4)
+    );
+}
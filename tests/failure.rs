@@ -0,0 +1,156 @@
+use cs257_project::{
+    verifier::GraphVerifier,
+    workflow::{failure::ErrorMatch, schema::OutputSchema, NodeIdx, WorkflowGraph},
+};
+use z3::{Config, Context};
+
+struct OrderGraph {
+    graph: WorkflowGraph,
+    charge_card: NodeIdx,
+    ship: NodeIdx,
+    refund: NodeIdx,
+}
+
+/// place_order -> charge_card -> ship, where a declined card is refunded
+fn order_graph() -> OrderGraph {
+    let mut g = WorkflowGraph::new();
+    let place_order = g.add_node(
+        "place_order",
+        vec![],
+        OutputSchema::new().add_fixed("order_id").build(),
+    );
+    let charge_card = g.add_node(
+        "charge_card",
+        vec!["order_id".to_string()],
+        OutputSchema::new()
+            .add_fixed("payment_id")
+            .carry_all()
+            .build(),
+    );
+    let ship = g.add_node(
+        "ship",
+        vec!["payment_id".to_string()],
+        OutputSchema::new().build(),
+    );
+    let refund = g.add_node(
+        "refund",
+        vec!["order_id".to_string(), "error".to_string()],
+        OutputSchema::new().build(),
+    );
+    g.add_edge(place_order, charge_card, vec![])
+        .add_edge(charge_card, ship, vec![])
+        .add_failure(
+            charge_card,
+            "CardDeclined",
+            OutputSchema::new()
+                .add_fixed("error")
+                .add_fixed("cause")
+                .carry_all()
+                .build(),
+        )
+        .add_failure(
+            charge_card,
+            "Timeout",
+            OutputSchema::new().add_fixed("error").build(),
+        )
+        .add_retry(
+            charge_card,
+            ErrorMatch::Named(vec!["Timeout".to_string()]),
+            3,
+        )
+        .add_catch(
+            charge_card,
+            ErrorMatch::Named(vec!["CardDeclined".to_string()]),
+            refund,
+        )
+        .set_start(place_order);
+    OrderGraph {
+        graph: g,
+        charge_card,
+        ship,
+        refund,
+    }
+}
+
+#[test]
+fn test_reachable_on_failure() {
+    let OrderGraph {
+        graph,
+        charge_card,
+        ship,
+        refund,
+    } = order_graph();
+    let ctx = Context::new(&Config::default());
+    let graph_verifier = GraphVerifier::new(&graph, &ctx);
+    assert!(graph_verifier.is_reachable(refund).is_some());
+    let (execution, _) = graph_verifier
+        .is_reachable_on_failure(refund, charge_card, Some("CardDeclined"))
+        .unwrap();
+    let charge_card_step = execution
+        .iter()
+        .find(|step| step.node_idx == charge_card)
+        .unwrap();
+    assert_eq!(charge_card_step.error.as_deref(), Some("CardDeclined"));
+    assert!(charge_card_step
+        .output_keys
+        .contains(&"order_id".to_string()));
+    assert!(graph_verifier
+        .is_reachable_on_failure(refund, charge_card, Some("Timeout"))
+        .is_none());
+    assert!(graph_verifier
+        .is_reachable_on_failure(ship, charge_card, None)
+        .is_none());
+}
+
+#[test]
+fn test_failure_and_target_in_one_execution() {
+    // start -> (charge_card, refund), and only one of them runs
+    let mut g = WorkflowGraph::new();
+    let start = g.add_node("start", vec![], OutputSchema::new().build());
+    let charge_card = g.add_node("charge_card", vec![], OutputSchema::new().build());
+    let refund = g.add_node("refund", vec![], OutputSchema::new().build());
+    let handler = g.add_node("handler", vec![], OutputSchema::new().build());
+    g.add_edge(start, charge_card, vec![])
+        .add_edge(start, refund, vec![])
+        .add_failure(charge_card, "E", OutputSchema::new().build())
+        .add_catch(charge_card, ErrorMatch::All, handler)
+        .set_start(start);
+    let ctx = Context::new(&Config::default());
+    let graph_verifier = GraphVerifier::new(&g, &ctx);
+    assert!(graph_verifier.is_reachable(refund).is_some());
+    assert!(graph_verifier
+        .is_reachable_on_failure(handler, charge_card, Some("E"))
+        .is_some());
+    assert!(graph_verifier
+        .is_reachable_on_failure(refund, charge_card, Some("E"))
+        .is_none());
+}
+
+#[test]
+fn test_unhandled_failures() {
+    let OrderGraph {
+        mut graph,
+        charge_card,
+        ship,
+        refund,
+    } = order_graph();
+    {
+        let ctx = Context::new(&Config::default());
+        let graph_verifier = GraphVerifier::new(&graph, &ctx);
+        assert_eq!(
+            graph_verifier.unhandled_failures(),
+            vec![(charge_card, "Timeout".to_string())]
+        );
+        assert!(!graph_verifier.can_eventually_reach(&[ship, refund]));
+    }
+
+    // a timeout is now caught, but its outputs lack the `order_id` that `refund` needs
+    graph.add_catch(charge_card, ErrorMatch::All, refund);
+    let ctx = Context::new(&Config::default());
+    let graph_verifier = GraphVerifier::new(&graph, &ctx);
+    assert_eq!(
+        graph_verifier.unhandled_failures(),
+        vec![(charge_card, "Timeout".to_string())]
+    );
+    assert!(!graph_verifier.can_eventually_reach(&[ship, refund]));
+}
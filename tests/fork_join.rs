@@ -0,0 +1,79 @@
+use cs257_project::{
+    verifier::GraphVerifier,
+    workflow::{schema::OutputSchema, NodeIdx, WorkflowGraph},
+};
+use z3::{Config, Context};
+
+/// receive_order -> split -> {reserve_inventory, charge_card} -> merge -> ship
+fn order_graph(charge_card_can_run: bool) -> (WorkflowGraph, NodeIdx, NodeIdx) {
+    let mut g = WorkflowGraph::new();
+    let receive_order = g.add_node(
+        "receive_order",
+        vec![],
+        OutputSchema::new().add_fixed("order_id").build(),
+    );
+    let split = g.add_fork("split", OutputSchema::new().carry_all().build());
+    let reserve_inventory = g.add_node(
+        "reserve_inventory",
+        vec!["order_id".to_string()],
+        OutputSchema::new()
+            .add_fixed("reservation_id")
+            .carry_all()
+            .build(),
+    );
+    let mut charge_card_required_inputs = vec!["order_id".to_string()];
+    if !charge_card_can_run {
+        charge_card_required_inputs.push("card_token".to_string());
+    }
+    let charge_card = g.add_node(
+        "charge_card",
+        charge_card_required_inputs,
+        OutputSchema::new()
+            .add_fixed("payment_id")
+            .carry_all()
+            .build(),
+    );
+    let merge = g.add_join(
+        "merge",
+        vec!["reservation_id".to_string()],
+        OutputSchema::new().carry_all().build(),
+    );
+    let ship = g.add_node(
+        "ship",
+        vec![
+            "order_id".to_string(),
+            "reservation_id".to_string(),
+            "payment_id".to_string(),
+        ],
+        OutputSchema::new().build(),
+    );
+    g.add_edge(receive_order, split, vec![])
+        .add_edge(split, reserve_inventory, vec![])
+        .add_edge(split, charge_card, vec![])
+        .add_edge(reserve_inventory, merge, vec![])
+        .add_edge(charge_card, merge, vec![])
+        .add_edge(merge, ship, vec![])
+        .set_start(receive_order);
+    (g, merge, ship)
+}
+
+#[test]
+fn test_join_merges_branch_outputs() {
+    let (graph, merge, ship) = order_graph(true);
+    let ctx = Context::new(&Config::default());
+    let graph_verifier = GraphVerifier::new(&graph, &ctx);
+    assert!(graph_verifier.is_reachable(merge).is_some());
+    // `ship` needs `reservation_id` and `payment_id`, which come from different branches
+    assert!(graph_verifier.is_reachable(ship).is_some());
+    assert_eq!(graph_verifier.join_conflicts(merge), vec!["order_id"]);
+}
+
+#[test]
+fn test_join_waits_for_all_branches() {
+    let (graph, merge, ship) = order_graph(false);
+    let ctx = Context::new(&Config::default());
+    let graph_verifier = GraphVerifier::new(&graph, &ctx);
+    // `reserve_inventory` alone satisfies the required inputs of `merge`, but `charge_card` never runs
+    assert!(graph_verifier.is_reachable(merge).is_none());
+    assert!(graph_verifier.is_reachable(ship).is_none());
+}
@@ -0,0 +1,74 @@
+use cs257_project::{
+    verifier::impact::{impact_of_edits, Check, ImpactChange},
+    workflow::schema::{OutputSchema, SchemaEdit},
+    workflow::WorkflowGraph,
+};
+
+#[test]
+fn test_impact_of_removing_a_key() {
+    let mut graph = WorkflowGraph::new();
+    let check_stock_price = graph.add_node(
+        "check_stock_price",
+        vec![],
+        OutputSchema::new()
+            .add_fixed("stock_name")
+            .add_fixed("stock_price")
+            .build(),
+    );
+    let buy_sell_rec = graph.add_node(
+        "buy_sell_rec",
+        vec!["stock_price".to_string()],
+        OutputSchema::new().add_fixed("rec").carry_all().build(),
+    );
+    let report_result = graph.add_node(
+        "report_result",
+        vec!["rec".to_string(), "stock_name".to_string()],
+        OutputSchema::new().build(),
+    );
+    graph
+        .add_edge(check_stock_price, buy_sell_rec, vec![])
+        .add_edge(buy_sell_rec, report_result, vec![])
+        .set_start(check_stock_price);
+    let properties = [
+        "F report_result".parse().unwrap(),
+        "G (report_result -> O buy_sell_rec)".parse().unwrap(),
+    ];
+
+    // without `stock_price`, nothing after `check_stock_price` can run
+    let changes = impact_of_edits(
+        &graph,
+        &[(
+            check_stock_price,
+            SchemaEdit::RemoveFixedKey("stock_price".to_string()),
+        )],
+        &properties,
+    )
+    .unwrap();
+    let change = |check, before, after| ImpactChange {
+        check,
+        before,
+        after,
+    };
+    assert_eq!(
+        changes,
+        vec![
+            change(Check::Reachable(buy_sell_rec), true, false),
+            change(Check::Reachable(report_result), true, false),
+            change(Check::EventuallyReached(buy_sell_rec), true, false),
+            change(Check::EventuallyReached(report_result), true, false),
+            change(Check::Property(0), true, false),
+        ]
+    );
+
+    // nothing downstream of `buy_sell_rec` uses `stock_price`
+    assert!(impact_of_edits(
+        &graph,
+        &[(
+            buy_sell_rec,
+            SchemaEdit::AddFixedKey("stock_price".to_string()),
+        )],
+        &properties,
+    )
+    .unwrap()
+    .is_empty());
+}
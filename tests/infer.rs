@@ -0,0 +1,123 @@
+use cs257_project::{
+    verifier::{topsort, GraphVerifier},
+    workflow::{
+        conformance::{check_conformance, LoggedStep},
+        infer::{infer_graph, Annotation, Inferred},
+        schema::{InputCond, KeyRule},
+    },
+};
+use z3::{Config, Context};
+
+fn logged(lines: &str) -> Vec<LoggedStep> {
+    LoggedStep::parse_lines(lines).unwrap()
+}
+
+#[test]
+fn test_infer_graph() {
+    let traces = vec![
+        logged(
+            r#"
+            {"node": "receive_order", "input_keys": ["order"], "output_keys": ["order"]}
+            {"node": "price_order", "input_keys": ["order"], "output_keys": ["order", "price", "raw_order"]}
+            {"node": "charge", "input_keys": ["order", "price", "raw_order"], "output_keys": ["receipt"]}
+            "#,
+        ),
+        logged(
+            r#"
+            {"node": "receive_order", "input_keys": ["coupon", "order"], "output_keys": ["coupon", "order"]}
+            {"node": "price_order", "input_keys": ["coupon", "order"], "output_keys": ["coupon", "discount", "order", "price", "raw_coupon", "raw_order"]}
+            {"node": "charge", "input_keys": ["coupon", "discount", "order", "price", "raw_coupon", "raw_order"], "output_keys": [], "error": "declined"}
+            {"node": "notify", "input_keys": [], "output_keys": []}
+            "#,
+        ),
+    ];
+    let draft = infer_graph(&traces, 1.0);
+    let graph = &draft.graph;
+    assert_eq!(graph.start, Some(0));
+
+    let price_order = &graph.nodes[1];
+    assert_eq!(price_order.required_inputs, vec!["order".to_string()]);
+    assert_eq!(
+        price_order.output_schema.fixed_keys,
+        vec!["price".to_string()]
+    );
+    let rules = price_order.output_schema.dynamic_keys();
+    assert!(rules.contains(&(KeyRule::Identity, InputCond::Always)));
+    assert!(rules.contains(&(KeyRule::IdWithPrefix("raw_".to_string()), InputCond::Always)));
+    assert!(rules.contains(&(
+        KeyRule::Fixed("discount".to_string()),
+        InputCond::MatchesKey("coupon".to_string())
+    )));
+    assert!(draft
+        .annotations
+        .iter()
+        .all(|annotation| annotation.confidence == 1.0));
+    // a declined charge is caught by `notify`
+    assert_eq!(graph.nodes[2].failures[0].error, "declined");
+    assert_eq!(graph.catch_list[2][0].0, 3);
+
+    // the draft accepts the traces it was learned from
+    for trace in &traces {
+        assert_eq!(check_conformance(graph, trace), None);
+    }
+
+    // with a lower confidence, `coupon` becomes a required input of `price_order`
+    let draft = infer_graph(&traces, 0.5);
+    assert!(draft.graph.nodes[1]
+        .required_inputs
+        .contains(&"coupon".to_string()));
+    assert!(draft.annotations.contains(&Annotation {
+        node_idx: 1,
+        error: None,
+        inferred: Inferred::RequiredInput("coupon".to_string()),
+        confidence: 0.5,
+        calls: 2,
+    }));
+}
+
+#[test]
+fn test_infer_retry_loop() {
+    let traces = vec![logged(
+        r#"
+        {"node": "receive", "input_keys": [], "output_keys": ["order"]}
+        {"node": "charge", "input_keys": ["order"], "output_keys": ["order"], "error": "timeout"}
+        {"node": "charge", "input_keys": ["order"], "output_keys": ["receipt"]}
+        {"node": "ship", "input_keys": ["receipt"], "output_keys": []}
+        "#,
+    )];
+    let draft = infer_graph(&traces, 1.0);
+    // the retry is reported instead of being added as a catch edge from `charge` to itself
+    assert_eq!(draft.back_edges, vec![(1, 1, Some("timeout".to_string()))]);
+    assert!(draft.graph.catch_list[1].is_empty());
+    assert_eq!(topsort::find_cycle(&draft.graph), None);
+    let ctx = Context::new(&Config::default());
+    let graph_verifier = GraphVerifier::new(&draft.graph, &ctx);
+    assert!(graph_verifier.is_reachable(2).is_some());
+}
+
+#[test]
+fn test_infer_invalid_keys() {
+    let traces = vec![logged(
+        r#"
+        {"node": "receive", "input_keys": ["order", ""], "output_keys": ["order", "items[0]"]}
+        {"node": "ship", "input_keys": ["order", "a..b"], "output_keys": []}
+        "#,
+    )];
+    let draft = infer_graph(&traces, 1.0);
+    let invalid = draft
+        .invalid_keys
+        .iter()
+        .map(|(node, e)| (*node, e.key.as_str()))
+        .collect::<Vec<_>>();
+    assert_eq!(invalid, vec![(0, ""), (0, "items[0]"), (1, "a..b")]);
+    assert_eq!(
+        draft.graph.nodes[0].required_inputs,
+        vec!["order".to_string()]
+    );
+    assert_eq!(
+        draft.graph.nodes[1].required_inputs,
+        vec!["order".to_string()]
+    );
+    assert!(draft.unexplained_outputs.is_empty());
+    assert_eq!(draft.graph.adj_list[0], vec![(1, vec![])]);
+}
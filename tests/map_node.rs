@@ -0,0 +1,78 @@
+use cs257_project::{
+    verifier::GraphVerifier,
+    workflow::{schema::OutputSchema, MapSpec, NodeIdx, WorkflowGraph},
+};
+use z3::{Config, Context};
+
+/// reserve_item -> charge_item, run once per line item
+fn charge_line_item() -> MapSpec {
+    let mut iterator = WorkflowGraph::new();
+    let reserve_item = iterator.add_node(
+        "reserve_item",
+        vec!["item".to_string()],
+        OutputSchema::new()
+            .add_fixed("reservation_id")
+            .carry_all()
+            .build(),
+    );
+    let charge_item = iterator.add_node(
+        "charge_item",
+        vec!["reservation_id".to_string(), "card_token".to_string()],
+        OutputSchema::new().add_fixed("charge_id").build(),
+    );
+    iterator
+        .add_edge(reserve_item, charge_item, vec![])
+        .set_start(reserve_item);
+    MapSpec::new("line_items", "item", iterator, charge_item, "results")
+}
+
+/// receive_order -> process_items (map) -> confirm
+fn order_graph(carry_card_token: bool) -> (WorkflowGraph, NodeIdx, NodeIdx) {
+    let mut g = WorkflowGraph::new();
+    let receive_order_schema = if carry_card_token {
+        OutputSchema::new().add_fixed("line_items").carry_all()
+    } else {
+        OutputSchema::new().add_fixed("line_items")
+    };
+    let receive_order = g.add_node("receive_order", vec![], receive_order_schema.build());
+    let process_items = g.add_map(
+        "process_items",
+        vec![],
+        charge_line_item(),
+        OutputSchema::new().build(),
+    );
+    let confirm = g.add_node(
+        "confirm",
+        vec!["results".to_string()],
+        OutputSchema::new().build(),
+    );
+    g.add_edge(receive_order, process_items, vec![])
+        .add_edge(process_items, confirm, vec![])
+        .set_start(receive_order);
+    (g, process_items, confirm)
+}
+
+#[test]
+fn test_reachable_through_map() {
+    let (graph, process_items, confirm) = order_graph(true);
+    let ctx = Context::new(&Config::default());
+    let graph_verifier = GraphVerifier::new(&graph, &ctx);
+    assert!(graph_verifier.is_reachable(process_items).is_some());
+    assert!(graph_verifier.is_reachable(confirm).is_some());
+    // every iteration needs `card_token`, which must be provided by the user
+    let (min_input_keys, _) = graph_verifier
+        .minimum_input_set_for_reachable(confirm)
+        .unwrap();
+    assert_eq!(min_input_keys, vec!["card_token"]);
+    assert!(!graph_verifier.can_eventually_reach(&[confirm]));
+}
+
+#[test]
+fn test_map_requires_iterator_inputs() {
+    let (graph, process_items, confirm) = order_graph(false);
+    let ctx = Context::new(&Config::default());
+    let graph_verifier = GraphVerifier::new(&graph, &ctx);
+    // the map node is entered, but no iteration can reach `charge_item`
+    assert!(graph_verifier.is_reachable(process_items).is_none());
+    assert!(graph_verifier.is_reachable(confirm).is_none());
+}
@@ -0,0 +1,62 @@
+use cs257_project::{
+    verifier::GraphVerifier,
+    workflow::{
+        schema::OutputSchema,
+        simulate::{simulate, Values},
+        NodeIdx, WorkflowGraph,
+    },
+};
+use z3::{Config, Context};
+
+/// A graph that looks up a user and then emails them, with the index of the email node.
+fn notify_graph(email_may_be_missing: bool) -> (WorkflowGraph, NodeIdx) {
+    let schema = OutputSchema::new().add_fixed("name").carry_all();
+    let schema = match email_may_be_missing {
+        true => schema.add_may("email"),
+        false => schema.add_fixed("email"),
+    };
+    let mut g = WorkflowGraph::new();
+    let lookup_user = g.add_node("lookup_user", vec!["user_id".to_string()], schema.build());
+    let send_email = g.add_node(
+        "send_email",
+        vec!["email".to_string(), "name".to_string()],
+        OutputSchema::new().build(),
+    );
+    g.add_edge(lookup_user, send_email, vec![])
+        .set_start(lookup_user);
+    (g, send_email)
+}
+
+#[test]
+fn test_may_keys() {
+    let ctx = Context::new(&Config::default());
+    let (graph, send_email) = notify_graph(true);
+    let graph_verifier = GraphVerifier::new(&graph, &ctx);
+    // some execution outputs the email
+    let (path, _) = graph_verifier.is_reachable(send_email).unwrap();
+    assert!(path[0].output_keys.contains(&"email".to_string()));
+    // but not every execution does
+    assert!(!graph_verifier.can_eventually_reach(&[send_email]));
+    // unless the email is also given as an input, since it is carried
+    assert_eq!(
+        graph_verifier.minimum_input_set_for_can_eventually_reach(&[send_email]),
+        Some(2)
+    );
+
+    let traces = simulate(
+        &graph,
+        &Values::from([("user_id".to_string(), String::new())]),
+    );
+    assert_eq!(traces.len(), 2);
+    assert_eq!(
+        traces
+            .iter()
+            .filter(|trace| trace.reaches(send_email))
+            .count(),
+        1
+    );
+
+    let (graph, send_email) = notify_graph(false);
+    let graph_verifier = GraphVerifier::new(&graph, &ctx);
+    assert!(graph_verifier.can_eventually_reach(&[send_email]));
+}
@@ -0,0 +1,130 @@
+use cs257_project::{
+    verifier::GraphVerifier,
+    workflow::{
+        openapi::{ImportError, ImportedOperation},
+        schema::{InputCond, OutputSchema},
+        value::ValueType,
+        WorkflowGraph,
+    },
+};
+use z3::{Config, Context};
+
+const ORDERS: &str = concat!(
+    env!("CARGO_MANIFEST_DIR"),
+    "/tests/data/orders.openapi.json"
+);
+
+fn sorted(keys: &[String]) -> Vec<&str> {
+    let mut keys = keys.iter().map(|s| s.as_str()).collect::<Vec<_>>();
+    keys.sort();
+    keys
+}
+
+#[test]
+fn test_import_openapi_operation() {
+    let operation = ImportedOperation::from_openapi_file(ORDERS, "createOrder").unwrap();
+    assert_eq!(
+        sorted(&operation.required_inputs),
+        vec![
+            "customer.id",
+            "items[*].quantity",
+            "items[*].sku",
+            "storeId"
+        ]
+    );
+    // `shipping` is nullable, so its carrier may be missing
+    assert_eq!(
+        sorted(&operation.outputs),
+        vec!["discount", "orderId", "shipping", "status", "total"]
+    );
+    assert_eq!(
+        sorted(&operation.optional_outputs),
+        vec!["shipping.carrier", "trackingUrl"]
+    );
+    assert_eq!(operation.key_types["note"], ValueType::String.nullable());
+    assert_eq!(operation.key_types["discount"], ValueType::Int.nullable());
+    assert_eq!(
+        operation.key_types["status"],
+        ValueType::Enum(vec!["pending".to_string(), "confirmed".to_string()])
+    );
+    assert!(matches!(
+        ImportedOperation::from_openapi_file(ORDERS, "deleteOrder"),
+        Err(ImportError::UnknownOperation(_))
+    ));
+
+    let mut g = WorkflowGraph::new();
+    let create_order = operation.add_to(&mut g, "create_order");
+    let confirmed = g.add_node(
+        "confirmed",
+        vec!["total".to_string()],
+        OutputSchema::new().build(),
+    );
+    let shipped = g.add_node("shipped", vec![], OutputSchema::new().build());
+    let status = |s: &str| {
+        vec![InputCond::MatchesKeyValue(
+            "status".to_string(),
+            s.to_string(),
+        )]
+    };
+    g.add_edge(create_order, confirmed, status("confirmed"))
+        .add_edge(create_order, shipped, status("shipped"))
+        .set_start(create_order);
+    let ctx = Context::new(&Config::default());
+    let graph_verifier = GraphVerifier::new(&g, &ctx);
+    assert!(graph_verifier.is_reachable(confirmed).is_some());
+    // not a status of the response
+    assert!(graph_verifier.is_reachable(shipped).is_none());
+}
+
+#[test]
+fn test_import_json_schemas() {
+    let request = r#"{
+        "type": "object",
+        "required": ["user"],
+        "properties": {
+            "user": {
+                "type": ["object", "null"],
+                "required": ["name"],
+                "properties": { "name": { "type": "string" } }
+            },
+            "tags": { "type": "array", "items": { "type": "string" } }
+        }
+    }"#;
+    let operation = ImportedOperation::from_json_schemas(Some(request), None).unwrap();
+    assert_eq!(operation.required_inputs, vec!["user"]);
+    assert!(operation.outputs.is_empty());
+    assert!(!operation.key_types.contains_key("tags"));
+    assert!(matches!(
+        ImportedOperation::from_json_schemas(Some(r##"{"$ref": "#/nowhere"}"##), None),
+        Err(ImportError::BadRef(_))
+    ));
+}
+
+#[test]
+fn test_invalid_property_names() {
+    let schema = |name: &str| {
+        format!(
+            r#"{{"type": "object", "required": ["{name}"], "properties": {{"{name}": {{"type": "string"}}}}}}"#
+        )
+    };
+    for name in ["tags[]", "a[b]", "a.b", ""] {
+        match ImportedOperation::from_json_schemas(Some(&schema(name)), None) {
+            Err(ImportError::InvalidName(invalid)) => assert_eq!(invalid, name),
+            result => panic!("{name}: {result:?}"),
+        }
+    }
+    assert_eq!(
+        ImportedOperation::from_json_schemas(Some(&schema("tags")), None)
+            .unwrap()
+            .required_inputs,
+        vec!["tags"]
+    );
+    let document = r#"{"paths": {"/orders": {"get": {
+        "operationId": "listOrders",
+        "parameters": [{"name": "filter.status", "in": "query", "required": true}]
+    }}}}"#;
+    assert!(matches!(
+        ImportedOperation::from_openapi(document, "listOrders"),
+        Err(ImportError::InvalidName(_))
+    ));
+}
@@ -0,0 +1,130 @@
+use cs257_project::{
+    verifier::GraphVerifier,
+    workflow::{
+        path::{self, KeyPath, Segment},
+        schema::{InputCond, OutputSchema},
+        simulate::{simulate, Values},
+        WorkflowGraph,
+    },
+};
+use z3::{Config, Context};
+
+#[test]
+fn test_parse_paths() {
+    let key = "order.items[*].sku".parse::<KeyPath>().unwrap();
+    assert_eq!(
+        key.segments,
+        vec![
+            Segment::Field("order".to_string()),
+            Segment::Field("items".to_string()),
+            Segment::Each,
+            Segment::Field("sku".to_string()),
+        ]
+    );
+    assert_eq!(key.to_string(), "order.items[*].sku");
+    assert_eq!(
+        path::ancestors("order.items[*].sku").collect::<Vec<_>>(),
+        vec!["order.items[*]", "order.items", "order"]
+    );
+    assert!(path::is_within("order.items", "order"));
+    assert!(!path::is_within("orders", "order"));
+    for invalid in ["", ".order", "order..id", "[*]", "order[0]", "order."] {
+        assert!(invalid.parse::<KeyPath>().is_err(), "{invalid}");
+    }
+}
+
+#[test]
+fn test_subtree_rules() {
+    let mut g = WorkflowGraph::new();
+    let receive = g.add_node(
+        "receive",
+        vec!["order.id".to_string()],
+        OutputSchema::new()
+            .add_fixed("order.items[*].sku")
+            .carry_subtree("order")
+            .build(),
+    );
+    let price = g.add_node(
+        "price",
+        vec!["order.items".to_string()],
+        OutputSchema::new()
+            .add_fixed("result.total")
+            .move_under("order", "previous_input")
+            .build(),
+    );
+    let invoice = g.add_node(
+        "invoice",
+        vec!["previous_input.order.id".to_string(), "result".to_string()],
+        OutputSchema::new().build(),
+    );
+    let audit = g.add_node(
+        "audit",
+        vec!["order.id".to_string()],
+        OutputSchema::new().build(),
+    );
+    g.add_edge(receive, price, vec![])
+        .add_edge(price, invoice, vec![])
+        .add_edge(price, audit, vec![])
+        .set_start(receive);
+
+    let ctx = Context::new(&Config::default());
+    let graph_verifier = GraphVerifier::new(&g, &ctx);
+    // `order.items` and `result` are present because keys below them are
+    assert!(graph_verifier.is_reachable(invoice).is_some());
+    // the order was moved under `previous_input`
+    assert!(graph_verifier.is_reachable(audit).is_none());
+    let (keys, _) = graph_verifier
+        .minimum_input_set_for_reachable(invoice)
+        .unwrap();
+    assert_eq!(keys, vec!["order.id"]);
+
+    let inputs = Values::from([("order.id".to_string(), "42".to_string())]);
+    let traces = simulate(&g, &inputs);
+    assert!(traces.iter().all(|trace| trace.reaches(invoice)));
+    assert_eq!(
+        traces[0].step(invoice).unwrap().inputs["previous_input.order.id"],
+        "42"
+    );
+}
+
+#[test]
+fn test_invalid_keys_are_errors() {
+    let mut g = WorkflowGraph::new();
+    let error = g
+        .try_add_node(
+            "confirm",
+            vec!["results[]".to_string()],
+            OutputSchema::new().build(),
+        )
+        .unwrap_err();
+    assert_eq!(error.key, "results[]");
+    assert!(g.nodes.is_empty());
+    let error = g
+        .try_add_node("receive", vec![], OutputSchema::new().add_fixed("").build())
+        .unwrap_err();
+    assert_eq!(
+        error.to_string(),
+        "invalid key \"\": empty key at position 0"
+    );
+
+    let receive = g
+        .try_add_node("receive", vec![], OutputSchema::new().carry_all().build())
+        .unwrap();
+    let ship = g
+        .try_add_node(
+            "ship",
+            vec!["order.id".to_string()],
+            OutputSchema::new().build(),
+        )
+        .unwrap();
+    let error = g
+        .try_add_edge(
+            receive,
+            ship,
+            vec![InputCond::MatchesKey("order..id".to_string())],
+        )
+        .unwrap_err();
+    assert_eq!(error.key, "order..id");
+    assert!(g.adj_list[receive].is_empty());
+    assert!(g.try_add_edge(receive, ship, vec![]).is_ok());
+}
@@ -0,0 +1,119 @@
+use cs257_project::{
+    verifier::property::{check_property, Formula, UnknownNode},
+    workflow::{
+        failure::ErrorMatch,
+        schema::{InputCond, KeyRule, OutputSchema},
+        WorkflowGraph,
+    },
+};
+
+/// receive_order -> validate_order -> check_fraud -> (manual_review ->) charge_card -> ship,
+/// where a declined card is refunded
+fn order_graph() -> WorkflowGraph {
+    let mut g = WorkflowGraph::new();
+    let receive_order = g.add_node(
+        "receive_order",
+        vec![],
+        OutputSchema::new().add_fixed("order").carry_all().build(),
+    );
+    let validate_order = g.add_node(
+        "validate_order",
+        vec!["order".to_string()],
+        OutputSchema::new()
+            .add_fixed("order_id")
+            .carry_all()
+            .build(),
+    );
+    // risky orders are flagged
+    let check_fraud = g.add_node(
+        "check_fraud",
+        vec!["order_id".to_string()],
+        OutputSchema::new()
+            .add_rule_for_every_input(
+                KeyRule::Fixed("fraud_flag".to_string()),
+                InputCond::MatchesKey("high_risk".to_string()),
+            )
+            .carry_all()
+            .build(),
+    );
+    let manual_review = g.add_node(
+        "manual_review",
+        vec!["fraud_flag".to_string()],
+        OutputSchema::new().carry_all().build(),
+    );
+    let charge_card = g.add_node(
+        "charge_card",
+        vec!["order_id".to_string()],
+        OutputSchema::new()
+            .add_fixed("payment_id")
+            .carry_all()
+            .build(),
+    );
+    let ship = g.add_node(
+        "ship",
+        vec!["payment_id".to_string()],
+        OutputSchema::new().build(),
+    );
+    let refund = g.add_node(
+        "refund",
+        vec!["order_id".to_string()],
+        OutputSchema::new().build(),
+    );
+    g.add_edge(receive_order, validate_order, vec![])
+        .add_edge(validate_order, check_fraud, vec![])
+        .add_edge(check_fraud, manual_review, vec![])
+        .add_edge(check_fraud, charge_card, vec![])
+        .add_edge(manual_review, charge_card, vec![])
+        .add_edge(charge_card, ship, vec![])
+        .add_failure(
+            charge_card,
+            "CardDeclined",
+            OutputSchema::new().carry_all().build(),
+        )
+        .add_catch(charge_card, ErrorMatch::All, refund)
+        .set_start(receive_order);
+    g
+}
+
+#[test]
+fn test_parse_formula() {
+    let formula = "G (charge_card -> O validate_order) & !F \"ship it\" U key(results[])"
+        .parse::<Formula>()
+        .unwrap();
+    assert_eq!(
+        formula.to_string(),
+        "(G (\"charge_card\" -> O \"validate_order\") & (!F \"ship it\" U key(\"results[]\")))"
+    );
+    assert_eq!(formula.to_string().parse::<Formula>().unwrap(), formula);
+    let error = "G (ship -> ".parse::<Formula>().unwrap_err();
+    assert_eq!(error.position, 11);
+    let error = "G ship $ refund".parse::<Formula>().unwrap_err();
+    assert_eq!(error.position, 7);
+}
+
+#[test]
+fn test_check_property() {
+    let graph = order_graph();
+    let check = |formula: &str| check_property(&graph, &formula.parse().unwrap()).unwrap();
+    assert!(check("G (charge_card -> O validate_order)").is_none());
+    assert!(check("G (ship -> G !refund)").is_none());
+    assert!(check("F (ship | refund)").is_none());
+    assert!(check("G !refund").is_some());
+    assert!(check("G (manual_review -> X charge_card)").is_none());
+
+    // a flagged order may skip the manual review
+    let counterexample = check("G (check_fraud & key(fraud_flag) -> F manual_review)").unwrap();
+    let names = counterexample
+        .iter()
+        .map(|step| graph.nodes[step.node_idx].name.as_str())
+        .collect::<Vec<_>>();
+    assert!(!names.contains(&"manual_review"));
+    let check_fraud = counterexample
+        .iter()
+        .find(|step| graph.nodes[step.node_idx].name == "check_fraud")
+        .unwrap();
+    assert!(check_fraud.output_keys.contains(&"fraud_flag".to_string()));
+
+    let error = check_property(&graph, &"G (ship -> O pay)".parse().unwrap()).unwrap_err();
+    assert_eq!(error, UnknownNode("pay".to_string()));
+}
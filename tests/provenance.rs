@@ -0,0 +1,57 @@
+use cs257_project::{
+    example_graphs::{buy_sell_stock::BuySellStockGraph, MakeGraph},
+    verifier::provenance::{all_key_provenances, key_provenance, ProvenanceStep},
+    workflow::schema::KeyRule,
+};
+
+#[test]
+fn test_key_provenance() {
+    let graph_ext = BuySellStockGraph::new(false).make_graph();
+    let graph = &graph_ext.graph;
+    let report_result = graph_ext.test_reachable_node;
+    let node = |name: &str| graph.nodes.iter().position(|n| n.name == name).unwrap();
+    let step = |name: &str, output_key: &str, rule: KeyRule| ProvenanceStep {
+        node_idx: node(name),
+        output_key: output_key.to_string(),
+        rule,
+    };
+
+    let provenance = key_provenance(graph, report_result, "previous_input.rec").unwrap();
+    assert_eq!(provenance.user_input, None);
+    assert_eq!(provenance.steps.len(), 3);
+    assert_eq!(
+        provenance.steps[0],
+        step("buy_sell_rec", "rec", KeyRule::Fixed("rec".to_string()))
+    );
+    assert_eq!(
+        provenance.steps[1],
+        step("buy_or_sell", "rec", KeyRule::Identity)
+    );
+
+    // `stock_name` is given by the user, and carried through `buy` or `sell`
+    let mut provenances = all_key_provenances(graph, report_result, "previous_input.stock_name");
+    provenances.sort_by_key(|p| p.steps[3].node_idx);
+    assert_eq!(provenances.len(), 2);
+    let carried = vec![
+        step("check_stock_price", "stock_name", KeyRule::Identity),
+        step("buy_sell_rec", "stock_name", KeyRule::Identity),
+        step("buy_or_sell", "stock_name", KeyRule::Identity),
+    ];
+    ["buy", "sell"]
+        .iter()
+        .zip(provenances.iter())
+        .for_each(|(name, provenance)| {
+            assert_eq!(provenance.user_input.as_deref(), Some("stock_name"));
+            assert_eq!(provenance.steps[..3], carried);
+            assert_eq!(
+                provenance.steps[3],
+                step(
+                    name,
+                    "previous_input.stock_name",
+                    KeyRule::IdWithPrefix("previous_input.".to_string())
+                )
+            );
+        });
+
+    assert!(key_provenance(graph, report_result, "stock_price").is_none());
+}
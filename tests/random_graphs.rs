@@ -0,0 +1,62 @@
+use cs257_project::{
+    example_graphs::{
+        random::{Cyclic, Diamonds, FanOut, Layered, RandomDag, RandomParams, RuleMix},
+        MakeGraph,
+    },
+    verifier::{topsort, GraphVerifier},
+    workflow::diff::diff,
+};
+use z3::{Config, Context};
+
+#[test]
+fn test_random_graphs() {
+    for seed in 0..3 {
+        for num_nodes in [1, 2, 12] {
+            let params = RandomParams::new(seed, num_nodes)
+                .edge_density(0.2)
+                .num_keys(6)
+                .rule_mix(RuleMix {
+                    identity: 1,
+                    fixed: 3,
+                    prefix: 1,
+                })
+                .conditional_edges(0.3);
+            let acyclic: Vec<Box<dyn MakeGraph>> = vec![
+                Box::new(RandomDag(params.clone())),
+                Box::new(Diamonds(params.clone())),
+                Box::new(FanOut(params.clone())),
+                Box::new(Layered {
+                    params: params.clone(),
+                    num_layers: 3,
+                }),
+            ];
+            for mk in &acyclic {
+                let graph_ext = mk.make_graph();
+                let graph = &graph_ext.graph;
+                assert_eq!(
+                    topsort::topological_sort_reversed(graph).len(),
+                    graph.nodes.len(),
+                    "{}",
+                    mk.name()
+                );
+                assert_eq!(topsort::find_cycle(graph), None, "{}", mk.name());
+                // the same seed gives the same graph
+                assert!(diff(graph, &mk.make_graph().graph).is_empty());
+                let ctx = Context::new(&Config::default());
+                let graph_verifier = GraphVerifier::new(graph, &ctx);
+                graph_verifier.is_reachable(graph_ext.test_reachable_node);
+            }
+
+            let cyclic = Cyclic(params).make_graph();
+            assert!(topsort::find_cycle(&cyclic.graph).is_some());
+        }
+    }
+}
+
+#[test]
+#[should_panic(expected = "only acyclic workflows can be verified")]
+fn test_cyclic_graph_is_rejected() {
+    let cyclic = Cyclic(RandomParams::new(0, 12)).make_graph();
+    let ctx = Context::new(&Config::default());
+    GraphVerifier::new(&cyclic.graph, &ctx);
+}
@@ -0,0 +1,96 @@
+use cs257_project::{
+    verifier::GraphVerifier,
+    workflow::{schema::OutputSchema, NodeIdx, WorkflowGraph},
+};
+use z3::{Config, Context};
+
+struct TravelGraph {
+    graph: WorkflowGraph,
+    validate_trip: NodeIdx,
+    quote_airline: NodeIdx,
+    quote_aggregator: NodeIdx,
+    book: NodeIdx,
+}
+
+/// receive_trip -> validate_trip -> fork -> (quote_airline, quote_aggregator) -> join -> book
+fn travel_graph() -> TravelGraph {
+    let mut g = WorkflowGraph::new();
+    let receive_trip = g.add_node(
+        "receive_trip",
+        vec![],
+        OutputSchema::new().add_fixed("trip").build(),
+    );
+    let validate_trip = g.add_node(
+        "validate_trip",
+        vec!["trip".to_string()],
+        OutputSchema::new().add_fixed("trip_id").carry_all().build(),
+    );
+    let fork = g.add_fork("fork", OutputSchema::new().carry_all().build());
+    let quote_airline = g.add_node(
+        "quote_airline",
+        vec!["trip".to_string()],
+        OutputSchema::new().add_fixed("quote").carry_all().build(),
+    );
+    let quote_aggregator = g.add_node(
+        "quote_aggregator",
+        vec!["trip".to_string()],
+        OutputSchema::new().add_fixed("quote").carry_all().build(),
+    );
+    let join = g.add_join("join", vec![], OutputSchema::new().carry_all().build());
+    let book = g.add_node(
+        "book",
+        vec!["quote".to_string(), "trip_id".to_string()],
+        OutputSchema::new().build(),
+    );
+    g.add_edge(receive_trip, validate_trip, vec![])
+        .add_edge(validate_trip, fork, vec![])
+        .add_edge(fork, quote_airline, vec![])
+        .add_edge(fork, quote_aggregator, vec![])
+        .add_edge(quote_airline, join, vec![])
+        .add_edge(quote_aggregator, join, vec![])
+        .add_edge(join, book, vec![])
+        .set_start(receive_trip);
+    TravelGraph {
+        graph: g,
+        validate_trip,
+        quote_airline,
+        quote_aggregator,
+        book,
+    }
+}
+
+#[test]
+fn test_robustness() {
+    let TravelGraph {
+        mut graph,
+        validate_trip,
+        quote_airline,
+        quote_aggregator,
+        book,
+    } = travel_graph();
+    {
+        let ctx = Context::new(&Config::default());
+        let graph_verifier = GraphVerifier::new(&graph, &ctx);
+        assert!(graph_verifier.can_eventually_reach(&[book]));
+        // `trip_id` is only produced by `validate_trip`
+        assert!(!graph_verifier.is_robust(&[book], 1));
+        assert_eq!(
+            graph_verifier.minimum_failure_set(&[book]),
+            Some(vec![validate_trip])
+        );
+    }
+
+    // once `receive_trip` also produces `trip_id`, both quotes must fail to break the booking
+    graph.nodes[0].output_schema = OutputSchema::new()
+        .add_fixed("trip")
+        .add_fixed("trip_id")
+        .build();
+    let ctx = Context::new(&Config::default());
+    let graph_verifier = GraphVerifier::new(&graph, &ctx);
+    assert!(graph_verifier.is_robust(&[book], 1));
+    assert!(!graph_verifier.is_robust(&[book], 2));
+    assert_eq!(
+        graph_verifier.minimum_failure_set(&[book]),
+        Some(vec![quote_airline, quote_aggregator])
+    );
+}
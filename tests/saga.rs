@@ -0,0 +1,90 @@
+use cs257_project::{
+    example_graphs::{payment_saga::PaymentSaga, MakeGraph},
+    verifier::saga::{check_compensations, CompensationIssue},
+    workflow::{failure::ErrorMatch, schema::OutputSchema, NodeIdx, WorkflowGraph},
+};
+
+fn node(graph: &WorkflowGraph, name: &str) -> NodeIdx {
+    graph.nodes.iter().position(|n| n.name == name).unwrap()
+}
+
+/// The issues found after each failure, as (failing node, error, issue).
+fn issues(graph: &WorkflowGraph) -> Vec<(NodeIdx, String, CompensationIssue)> {
+    check_compensations(graph)
+        .into_iter()
+        .map(|violation| (violation.failing_node, violation.error, violation.issue))
+        .collect()
+}
+
+#[test]
+fn test_check_compensations() {
+    let graph = PaymentSaga.make_graph().graph;
+    assert!(issues(&graph).is_empty());
+    let reserve_inventory = node(&graph, "reserve_inventory");
+    let charge_card = node(&graph, "charge_card");
+    let create_shipment = node(&graph, "create_shipment");
+    let refund_payment = node(&graph, "refund_payment");
+    let release_inventory = node(&graph, "release_inventory");
+    let cancel_order = node(&graph, "cancel_order");
+
+    // an invalid address that only releases the stock keeps the payment
+    let mut skips_refund = graph.clone();
+    skips_refund
+        .add_failure(
+            create_shipment,
+            "AddressInvalid",
+            OutputSchema::new().carry_all().build(),
+        )
+        .catch_list[create_shipment]
+        .insert(
+            0,
+            (
+                release_inventory,
+                ErrorMatch::Named(vec!["AddressInvalid".to_string()]),
+            ),
+        );
+    assert_eq!(
+        issues(&skips_refund),
+        vec![(
+            create_shipment,
+            "AddressInvalid".to_string(),
+            CompensationIssue::NotCompensated {
+                step: charge_card,
+                compensation: refund_payment
+            }
+        )]
+    );
+
+    // releasing the stock before refunding the payment is out of order
+    let mut swapped = graph.clone();
+    swapped.catch_list[create_shipment][0].0 = release_inventory;
+    swapped.adj_list[release_inventory] = vec![(refund_payment, vec![])];
+    swapped.adj_list[refund_payment] = vec![(cancel_order, vec![])];
+    assert_eq!(
+        issues(&swapped),
+        vec![(
+            create_shipment,
+            "CarrierUnavailable".to_string(),
+            CompensationIssue::OutOfOrder {
+                earlier: reserve_inventory,
+                later: charge_card
+            }
+        )]
+    );
+
+    // a refund that drops the reservation cannot release the stock
+    let mut drops_reservation = graph.clone();
+    drops_reservation.nodes[refund_payment].output_schema = OutputSchema::new().build();
+    assert_eq!(
+        issues(&drops_reservation),
+        vec![(
+            create_shipment,
+            "CarrierUnavailable".to_string(),
+            CompensationIssue::MissingInputs {
+                step: reserve_inventory,
+                compensation: release_inventory,
+                missing: vec!["reservation_id".to_string()]
+            }
+        )]
+    );
+}
@@ -0,0 +1,98 @@
+use cs257_project::{
+    verifier::GraphVerifier,
+    workflow::{
+        failure::ErrorMatch,
+        schema::{InputCond, OutputSchema},
+        simulate::{simulate, Outcome, Values},
+        WorkflowGraph,
+    },
+};
+use z3::{Config, Context};
+
+/// receive_payment -> route -> (charge_card | send_invoice), where the payment method picks the branch
+/// and a declined card is reported by notify_customer
+fn payment_graph() -> WorkflowGraph {
+    let mut g = WorkflowGraph::new();
+    let receive_payment = g.add_node(
+        "receive_payment",
+        vec![],
+        OutputSchema::new().add_fixed("payment").carry_all().build(),
+    );
+    let route = g.add_node(
+        "route",
+        vec!["payment".to_string()],
+        OutputSchema::new().carry_all().build(),
+    );
+    let charge_card = g.add_node(
+        "charge_card",
+        vec!["payment".to_string()],
+        OutputSchema::new().add_fixed("receipt").build(),
+    );
+    let send_invoice = g.add_node(
+        "send_invoice",
+        vec!["payment".to_string()],
+        OutputSchema::new().build(),
+    );
+    let notify_customer = g.add_node(
+        "notify_customer",
+        vec!["payment".to_string()],
+        OutputSchema::new().build(),
+    );
+    let method_is = |method: &str| {
+        vec![InputCond::MatchesKeyValue(
+            "method".to_string(),
+            method.to_string(),
+        )]
+    };
+    g.add_edge(receive_payment, route, vec![])
+        .add_edge(route, charge_card, method_is("card"))
+        .add_edge(route, send_invoice, method_is("invoice"))
+        .add_failure(
+            charge_card,
+            "declined",
+            OutputSchema::new().carry_all().build(),
+        )
+        .add_catch(charge_card, ErrorMatch::All, notify_customer)
+        .set_start(receive_payment);
+    g
+}
+
+fn inputs(pairs: &[(&str, &str)]) -> Values {
+    pairs
+        .iter()
+        .map(|(k, v)| (k.to_string(), v.to_string()))
+        .collect()
+}
+
+#[test]
+fn test_simulate() {
+    let graph = payment_graph();
+    let (charge_card, send_invoice, notify_customer) = (2, 3, 4);
+
+    let traces = simulate(&graph, &inputs(&[("method", "card")]));
+    assert_eq!(traces.len(), 2);
+    assert!(traces.iter().all(|trace| !trace.enters(send_invoice)));
+    let declined = traces
+        .iter()
+        .find(|trace| trace.enters(notify_customer))
+        .unwrap();
+    assert!(matches!(
+        &declined.step(charge_card).unwrap().outcome,
+        Outcome::Failed(error, outputs) if error == "declined" && outputs["method"] == "card"
+    ));
+
+    let traces = simulate(&graph, &inputs(&[("method", "invoice")]));
+    assert_eq!(traces.len(), 1);
+    assert!(traces[0].reaches(send_invoice));
+
+    // every node reached by a concrete execution is reachable for the verifier
+    let ctx = Context::new(&Config::default());
+    let graph_verifier = GraphVerifier::new(&graph, &ctx);
+    for method in ["card", "invoice", "cash"] {
+        for trace in simulate(&graph, &inputs(&[("method", method)])) {
+            for step in &trace.steps {
+                assert!(graph_verifier.is_reachable(step.node_idx).is_some());
+            }
+        }
+    }
+}
@@ -0,0 +1,124 @@
+use std::collections::BTreeSet;
+use std::sync::Arc;
+
+use cs257_project::{
+    verifier::GraphVerifier,
+    workflow::{
+        module::{ModuleSummary, WorkflowModule},
+        schema::OutputSchema,
+        NodeIdx, WorkflowGraph,
+    },
+};
+use z3::{Config, Context};
+
+/// authenticate -> load_user
+fn login_module() -> WorkflowModule {
+    let mut g = WorkflowGraph::new();
+    let authenticate = g.add_node(
+        "authenticate",
+        vec!["credentials".to_string()],
+        OutputSchema::new().add_fixed("session").carry_all().build(),
+    );
+    let load_user = g.add_node(
+        "load_user",
+        vec!["session".to_string()],
+        OutputSchema::new().add_fixed("user").carry_all().build(),
+    );
+    g.add_edge(authenticate, load_user, vec![])
+        .set_start(authenticate);
+    WorkflowModule::new(
+        "login",
+        g,
+        load_user,
+        vec!["credentials".to_string(), "locale".to_string()],
+        vec!["session".to_string(), "user".to_string()],
+    )
+}
+
+/// receive_request -> login (module) -> `page`
+fn app_graph(module: &Arc<WorkflowModule>, page: &str) -> (WorkflowGraph, NodeIdx) {
+    let mut g = WorkflowGraph::new();
+    let receive_request = g.add_node(
+        "receive_request",
+        vec!["request_id".to_string()],
+        OutputSchema::new().carry_all().build(),
+    );
+    let login = g.add_subworkflow(
+        "login",
+        vec![],
+        module.clone(),
+        OutputSchema::new().carry_all().build(),
+    );
+    let page = g.add_node(
+        page,
+        vec!["user".to_string(), "request_id".to_string()],
+        OutputSchema::new().build(),
+    );
+    g.add_edge(receive_request, login, vec![])
+        .add_edge(login, page, vec![])
+        .set_start(receive_request);
+    (g, page)
+}
+
+#[test]
+fn test_inlined_subworkflow() {
+    let module = Arc::new(login_module());
+    let (graph, show_dashboard) = app_graph(&module, "show_dashboard");
+    let ctx = Context::new(&Config::default());
+    let graph_verifier = GraphVerifier::new(&graph, &ctx);
+    assert!(graph_verifier.is_reachable(show_dashboard).is_some());
+    let (mut min_input_keys, _) = graph_verifier
+        .minimum_input_set_for_reachable(show_dashboard)
+        .unwrap();
+    min_input_keys.sort();
+    assert_eq!(min_input_keys, vec!["credentials", "request_id"]);
+    assert!(!graph_verifier.can_eventually_reach(&[show_dashboard]));
+}
+
+#[test]
+fn test_summarized_subworkflow() {
+    let module = Arc::new(login_module());
+    let keys = |keys: &[&str]| keys.iter().map(|s| s.to_string()).collect::<BTreeSet<_>>();
+
+    let (graph, show_dashboard) = app_graph(&module, "show_dashboard");
+    let ctx = Context::new(&Config::default());
+    let graph_verifier = GraphVerifier::with_module_summaries(&graph, &ctx);
+    assert!(graph_verifier.is_reachable(show_dashboard).is_some());
+    let (mut min_input_keys, _) = graph_verifier
+        .minimum_input_set_for_reachable(show_dashboard)
+        .unwrap();
+    min_input_keys.sort();
+    assert_eq!(min_input_keys, vec!["credentials", "request_id"]);
+    assert!(!graph_verifier.can_eventually_reach(&[show_dashboard]));
+
+    // the summary is computed once, and reused by another workflow
+    let summary = module.summary(|_| panic!("summary is computed again"));
+    assert_eq!(
+        summary.completions,
+        vec![
+            (keys(&["credentials"]), keys(&["session", "user"])),
+            (keys(&["credentials", "locale"]), keys(&["session", "user"])),
+        ]
+    );
+    assert_eq!(summary.failing_inputs, vec![keys(&[]), keys(&["locale"])]);
+    let (graph, show_settings) = app_graph(&module, "show_settings");
+    let graph_verifier = GraphVerifier::with_module_summaries(&graph, &ctx);
+    assert!(graph_verifier.is_reachable(show_settings).is_some());
+}
+
+#[test]
+fn test_module_with_many_inputs_is_inlined() {
+    let mut login = login_module();
+    login.inputs.extend((0..70).map(|i| format!("header_{i}")));
+    let module = Arc::new(login);
+    let (graph, show_dashboard) = app_graph(&module, "show_dashboard");
+    let ctx = Context::new(&Config::default());
+    let graph_verifier = GraphVerifier::with_module_summaries(&graph, &ctx);
+    assert!(graph_verifier.is_reachable(show_dashboard).is_some());
+    // the module was not summarized
+    let empty = ModuleSummary {
+        completions: vec![],
+        failing_inputs: vec![],
+    };
+    assert_eq!(module.summary(|_| empty.clone()), &empty);
+}
@@ -0,0 +1,199 @@
+use cs257_project::{
+    verifier::GraphVerifier,
+    workflow::{
+        schema::{InputCond, OutputSchema},
+        simulate::{apply_schema, simulate, Values},
+        value::{CmpOp, Expr, Value, ValueSpec, ValueType},
+        WorkflowGraph,
+    },
+};
+use z3::{Config, Context};
+
+fn loan_graph(typed: bool) -> WorkflowGraph {
+    let mut g = WorkflowGraph::new();
+    let apply = g.add_node(
+        "apply",
+        vec!["amount".to_string()],
+        OutputSchema::new().carry_all().build(),
+    );
+    let manual_review = g.add_node("manual_review", vec![], OutputSchema::new().build());
+    let auto_approve = g.add_node(
+        "auto_approve",
+        vec![],
+        OutputSchema::new().carry_all().build(),
+    );
+    let large_payout = g.add_node("large_payout", vec![], OutputSchema::new().build());
+    let compare = |op, n| vec![InputCond::Compare("amount".to_string(), op, Value::Int(n))];
+    g.add_edge(apply, manual_review, compare(CmpOp::Gt, 1000))
+        .add_edge(apply, auto_approve, compare(CmpOp::Le, 1000))
+        .add_edge(auto_approve, large_payout, compare(CmpOp::Gt, 5000))
+        .set_start(apply);
+    if typed {
+        g.set_key_type("amount", ValueType::Int);
+    }
+    g
+}
+
+#[test]
+fn test_int_comparisons() {
+    let graph = loan_graph(true);
+    let ctx = Context::new(&Config::default());
+    let graph_verifier = GraphVerifier::new(&graph, &ctx);
+    assert!(graph_verifier.is_reachable(1).is_some());
+    assert!(graph_verifier.is_reachable(2).is_some());
+    // the amount is carried by auto_approve, so it is still at most 1000
+    assert!(graph_verifier.is_reachable(3).is_none());
+    // one of the branches of apply is always taken
+    assert!(graph_verifier.can_eventually_reach(&[1, 2]));
+
+    // without a type, only the presence of the amount is checked
+    let graph = loan_graph(false);
+    let graph_verifier = GraphVerifier::new(&graph, &ctx);
+    assert!(graph_verifier.is_reachable(3).is_some());
+}
+
+#[test]
+fn test_nullable_enum() {
+    let mut g = WorkflowGraph::new();
+    let check = g.add_node(
+        "check",
+        vec![],
+        OutputSchema::new().add_fixed("status").build(),
+    );
+    let ok = g.add_node("ok", vec![], OutputSchema::new().build());
+    let missing = g.add_node("missing", vec![], OutputSchema::new().build());
+    let pending = g.add_node("pending", vec![], OutputSchema::new().build());
+    let status = |s: &str| {
+        vec![InputCond::MatchesKeyValue(
+            "status".to_string(),
+            s.to_string(),
+        )]
+    };
+    g.add_edge(check, ok, status("ok"))
+        .add_edge(check, missing, status("null"))
+        .add_edge(check, pending, status("pending"))
+        .set_key_type(
+            "status",
+            ValueType::Enum(vec!["ok".to_string(), "failed".to_string()]).nullable(),
+        )
+        .set_start(check);
+    let ctx = Context::new(&Config::default());
+    let graph_verifier = GraphVerifier::new(&g, &ctx);
+    assert!(graph_verifier.is_reachable(ok).is_some());
+    assert!(graph_verifier.is_reachable(missing).is_some());
+    // not a variant of the enum
+    assert!(graph_verifier.is_reachable(pending).is_none());
+    // a failed status takes no edge
+    assert!(!graph_verifier.can_eventually_reach(&[ok, missing, pending]));
+}
+
+#[test]
+fn test_value_specs() {
+    let mut g = WorkflowGraph::new();
+    let quote = g.add_node(
+        "quote",
+        vec!["quantity".to_string(), "order_id".to_string()],
+        OutputSchema::new()
+            .add_fixed("price")
+            .set_value(
+                "price",
+                ValueSpec::OneOf(vec![Value::Int(10), Value::Int(20)]),
+            )
+            .add_fixed("order_ref")
+            .set_value(
+                "order_ref",
+                ValueSpec::Expr(Expr::from("order-").concat(Expr::key("order_id"))),
+            )
+            .carry_all()
+            .build(),
+    );
+    let cart_schema = OutputSchema::new()
+        .add_fixed("total")
+        .set_value(
+            "total",
+            ValueSpec::Expr(Expr::key("price") * Expr::key("quantity")),
+        )
+        .carry_all()
+        .build();
+    let inputs = Values::from([
+        ("price".to_string(), "10".to_string()),
+        ("quantity".to_string(), "3".to_string()),
+    ]);
+    assert_eq!(apply_schema(&cart_schema, &inputs)["total"], "30");
+    let cart = g.add_node(
+        "cart",
+        vec!["price".to_string(), "quantity".to_string()],
+        cart_schema,
+    );
+    let big_order = g.add_node("big_order", vec![], OutputSchema::new().build());
+    let odd_total = g.add_node("odd_total", vec![], OutputSchema::new().build());
+    let known_ref = g.add_node("known_ref", vec![], OutputSchema::new().build());
+    let bare_ref = g.add_node("bare_ref", vec![], OutputSchema::new().build());
+    let total = |op, n| vec![InputCond::Compare("total".to_string(), op, Value::Int(n))];
+    let order_ref = |s: &str| {
+        vec![InputCond::MatchesKeyValue(
+            "order_ref".to_string(),
+            s.to_string(),
+        )]
+    };
+    g.add_edge(quote, cart, vec![])
+        .add_edge(cart, big_order, total(CmpOp::Gt, 1000))
+        .add_edge(cart, odd_total, total(CmpOp::Eq, 15))
+        .add_edge(cart, known_ref, order_ref("order-42"))
+        .add_edge(cart, bare_ref, order_ref("42"))
+        .set_key_type("quantity", ValueType::Int)
+        .set_key_type("price", ValueType::Int)
+        .set_key_type("total", ValueType::Int)
+        .set_key_type("order_id", ValueType::String)
+        .set_key_type("order_ref", ValueType::String)
+        .set_start(quote);
+    let ctx = Context::new(&Config::default());
+    let graph_verifier = GraphVerifier::new(&g, &ctx);
+    assert!(graph_verifier.is_reachable(big_order).is_some());
+    // the total is a multiple of the price, which is 10 or 20
+    assert!(graph_verifier.is_reachable(odd_total).is_none());
+    assert!(graph_verifier.is_reachable(known_ref).is_some());
+    assert!(graph_verifier.is_reachable(bare_ref).is_none());
+}
+
+#[test]
+fn test_one_of_in_simulation() {
+    let mut g = WorkflowGraph::new();
+    let charge = g.add_node(
+        "charge",
+        vec![],
+        OutputSchema::new()
+            .add_fixed("status")
+            .set_value(
+                "status",
+                ValueSpec::OneOf(vec![
+                    Value::String("OK".to_string()),
+                    Value::String("DECLINED".to_string()),
+                ]),
+            )
+            .build(),
+    );
+    let ship = g.add_node("ship", vec![], OutputSchema::new().build());
+    let notify = g.add_node("notify", vec![], OutputSchema::new().build());
+    let status = |s: &str| {
+        vec![InputCond::MatchesKeyValue(
+            "status".to_string(),
+            s.to_string(),
+        )]
+    };
+    g.add_edge(charge, ship, status("OK"))
+        .add_edge(charge, notify, status("DECLINED"))
+        .set_key_type(
+            "status",
+            ValueType::Enum(vec!["OK".to_string(), "DECLINED".to_string()]),
+        )
+        .set_start(charge);
+    let ctx = Context::new(&Config::default());
+    let graph_verifier = GraphVerifier::new(&g, &ctx);
+    assert!(graph_verifier.is_reachable(ship).is_some());
+    assert!(graph_verifier.is_reachable(notify).is_some());
+    // the simulator tries every value of the status
+    let traces = simulate(&g, &Values::new());
+    assert!(traces.iter().any(|trace| trace.reaches(ship)));
+    assert!(traces.iter().any(|trace| trace.reaches(notify)));
+}